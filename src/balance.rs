@@ -0,0 +1,330 @@
+//! Contains the balance analyzer for the stat formulas, i.e. the `Stats` functions deriving game
+//! stats (`max_hp`, `action_points`, `mobility`, ...) from base stats.
+//!
+//! The analyzer sweeps all distributions of a fixed point budget across the six base stats and
+//! reports:
+//!
+//! * **Marginal value per point**: How much each derived stat grows per point put into each base
+//! stat, averaged across the sweep
+//! * **Dominant builds**: Builds that are at least as good in every derived stat as a large share
+//! of all other builds
+//! * **Ignored stats**: Base stats that no formula takes into account
+//! * **Constant stats**: Derived stats that don't change no matter how points are distributed
+//!
+//! Formulas are analyzed with the active `Ruleset`, so rulesets can be checked before they ship.
+//!
+//! Reports render as tables and ASCII charts through the `text` module.
+
+use crate::characters::Stats;
+use crate::text::{text_util, BarStyle, FrameType, InfoGrid, TextFormatting};
+
+/// Names of the base stats, in the order they are swept
+pub const BASE_STATS: [&str; 6] = ["DEX", "STR", "GRT", "WIL", "CHA", "INT"];
+
+/// A formula deriving a stat from base stats
+pub type StatFormula = fn(&Stats) -> i64;
+
+/// All derived stats analyzed by default, with their formulas
+pub const DERIVED_STATS: [(&str, StatFormula); 10] = [
+    ("MHP", Stats::max_hp),
+    ("MMP", Stats::max_mp),
+    ("MAP", Stats::max_ap),
+    ("TAP", Stats::action_points),
+    ("MVE", Stats::move_speed),
+    ("PDF", Stats::phys_defense),
+    ("MDF", Stats::mag_defense),
+    ("MOB", Stats::mobility),
+    ("HRG", Stats::health_regen),
+    ("MRG", Stats::magic_regen),
+];
+
+/// Returns `stats` with `points` added to the base stat at index `stat` of `BASE_STATS`
+fn add_points(stats: &Stats, stat: usize, points: i64) -> Stats {
+    let mut stats = *stats;
+    match stat {
+        0 => stats.dex += points,
+        1 => stats.str += points,
+        2 => stats.grt += points,
+        3 => stats.wil += points,
+        4 => stats.cha += points,
+        _ => stats.int += points,
+    }
+    stats
+}
+
+/// Sweeps stat distributions at a fixed point budget to analyze the stat formulas
+pub struct BalanceAnalyzer {
+    /// Number of points distributed across the base stats
+    budget: i64,
+    /// Points are distributed in steps of this size. Smaller steps sweep more builds.
+    step: i64,
+    /// Builds dominating at least this share of all builds are flagged
+    dominance_threshold: f64,
+    /// The derived stats analyzed, with their formulas
+    formulas: Vec<(String, StatFormula)>,
+}
+
+impl BalanceAnalyzer {
+    pub fn new(budget: i64) -> BalanceAnalyzer {
+        BalanceAnalyzer {
+            budget,
+            // Roughly ten steps per stat keep the sweep at a few thousand builds
+            step: (budget / 10).max(1),
+            dominance_threshold: 0.25,
+            formulas: DERIVED_STATS.iter().map(|(name, f)| (name.to_string(), *f)).collect(),
+        }
+    }
+
+    pub fn set_step(&mut self, step: i64) {
+        self.step = step.max(1);
+    }
+
+    pub fn set_dominance_threshold(&mut self, dominance_threshold: f64) {
+        self.dominance_threshold = dominance_threshold;
+    }
+
+    pub fn set_formulas(&mut self, formulas: Vec<(String, StatFormula)>) {
+        self.formulas = formulas;
+    }
+
+    /// Lists all distributions of the budget across the base stats, in multiples of the step
+    /// size. Points left over when the budget isn't a multiple of the step are not distributed.
+    pub fn sweep(&self) -> Vec<Stats> {
+        let empty = Stats { dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0 };
+        let mut builds = vec![];
+        Self::distribute(empty, 0, self.budget / self.step, self.step, &mut builds);
+        builds
+    }
+
+    /// Recursively distributes `steps` steps across the base stats starting at index `stat`
+    fn distribute(stats: Stats, stat: usize, steps: i64, step: i64, builds: &mut Vec<Stats>) {
+        if stat == BASE_STATS.len() - 1 {
+            builds.push(add_points(&stats, stat, steps * step));
+            return;
+        }
+        for s in 0..=steps {
+            Self::distribute(add_points(&stats, stat, s * step), stat + 1, steps - s, step, builds);
+        }
+    }
+
+    /// Runs the analysis
+    pub fn analyze(&self) -> BalanceReport {
+        let derive = |stats: &Stats| self.formulas.iter().map(|(_, f)| f(stats)).collect::<Vec<i64>>();
+
+        let mut builds: Vec<Build> = self.sweep().into_iter().map(|stats| Build {
+            derived: derive(&stats),
+            stats,
+            dominated_share: 0f64,
+        }).collect();
+
+        // Average growth of each derived stat per point, taken across all builds of the sweep
+        let mut growth = vec![vec![0i64; self.formulas.len()]; BASE_STATS.len()];
+        for build in builds.iter() {
+            for (stat, row) in growth.iter_mut().enumerate() {
+                let plus_one = derive(&add_points(&build.stats, stat, 1));
+                for (k, total) in row.iter_mut().enumerate() {
+                    *total += plus_one[k] - build.derived[k];
+                }
+            }
+        }
+        let marginal = growth.iter()
+            .map(|row| row.iter().map(|total| *total as f64 / builds.len() as f64).collect())
+            .collect();
+
+        // Each build dominates the builds it's at least as good as in every derived stat (and
+        // better in at least one)
+        let dominated: Vec<usize> = builds.iter().map(|a| builds.iter().filter(|b|
+            a.derived.iter().zip(b.derived.iter()).all(|(x, y)| x >= y) && a.derived != b.derived
+        ).count()).collect();
+        let others = (builds.len() - 1).max(1) as f64;
+        for (build, count) in builds.iter_mut().zip(dominated) {
+            build.dominated_share = count as f64 / others;
+        }
+
+        let ranges = (0..self.formulas.len()).map(|k| (
+            builds.iter().map(|b| b.derived[k]).min().unwrap_or(0),
+            builds.iter().map(|b| b.derived[k]).max().unwrap_or(0),
+        )).collect();
+
+        BalanceReport {
+            budget: self.budget,
+            derived_names: self.formulas.iter().map(|(name, _)| name.clone()).collect(),
+            builds,
+            marginal,
+            ranges,
+            dominance_threshold: self.dominance_threshold,
+        }
+    }
+}
+
+/// One build of the sweep, with its derived stats
+pub struct Build {
+    pub stats: Stats,
+    /// Derived stats, in the order of the analyzed formulas
+    pub derived: Vec<i64>,
+    /// Share of all other builds this build dominates
+    pub dominated_share: f64,
+}
+
+impl Build {
+    /// Describes the point distribution of this build, e.g. `"10 DEX, 20 GRT"`
+    pub fn describe(&self) -> String {
+        let described = self.stats.format_as_req_string();
+        if described.is_empty() { "No points".to_string() } else { described }
+    }
+}
+
+/// The results of a balance analysis
+pub struct BalanceReport {
+    budget: i64,
+    derived_names: Vec<String>,
+    builds: Vec<Build>,
+    /// Average growth of each derived stat (inner) per point put into each base stat (outer)
+    marginal: Vec<Vec<f64>>,
+    /// Minimum and maximum of each derived stat across the sweep
+    ranges: Vec<(i64, i64)>,
+    dominance_threshold: f64,
+}
+
+impl BalanceReport {
+    pub fn iter_builds(&self) -> core::slice::Iter<Build> {
+        self.builds.iter()
+    }
+
+    /// Average growth of the derived stat `derived` per point put into the base stat `base`
+    /// (indices of `BASE_STATS` and of the analyzed formulas)
+    pub fn marginal_value(&self, base: usize, derived: usize) -> f64 {
+        self.marginal[base][derived]
+    }
+
+    /// Sums up the marginal value of a point in the base stat `base` across all derived stats.
+    /// To make derived stats comparable, each is measured relative to its range across the
+    /// sweep, e.g. `0.05` means one point moves derived stats by 5% of their ranges in total.
+    pub fn relative_value(&self, base: usize) -> f64 {
+        self.marginal[base].iter().zip(self.ranges.iter())
+            .filter(|(_, (min, max))| max > min)
+            .map(|(value, (min, max))| value / (max - min) as f64)
+            .sum()
+    }
+
+    /// Names of the base stats no derived stat grows with
+    pub fn ignored_stats(&self) -> Vec<&str> {
+        (0..BASE_STATS.len())
+            .filter(|b| self.marginal[*b].iter().all(|v| *v == 0f64))
+            .map(|b| BASE_STATS[b])
+            .collect()
+    }
+
+    /// Names of the derived stats that are the same for every build
+    pub fn constant_stats(&self) -> Vec<&String> {
+        self.derived_names.iter().zip(self.ranges.iter())
+            .filter(|(_, (min, max))| min == max)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Builds dominating at least the dominance threshold's share of all builds, most dominant
+    /// first
+    pub fn dominant_builds(&self) -> Vec<&Build> {
+        let mut dominant: Vec<&Build> = self.builds.iter()
+            .filter(|b| b.dominated_share >= self.dominance_threshold)
+            .collect();
+        dominant.sort_by(|a, b| b.dominated_share.total_cmp(&a.dominated_share));
+        dominant
+    }
+
+    /// Renders the full report into lines of width `w`: The marginal value table, a chart of the
+    /// relative value of each base stat and all findings.
+    pub fn render(&self, w: usize, formatting: TextFormatting) -> Vec<String> {
+        let mut lines = vec![format!("Marginal value per point ({} points, {} builds)", self.budget, self.builds.len())];
+
+        // Table: One row per base stat, one column per derived stat
+        let mut columns = vec![("Stat", 5)];
+        columns.extend(self.derived_names.iter().map(|name| (name.as_str(), 6)));
+        let rows: Vec<Vec<String>> = BASE_STATS.iter().enumerate().map(|(b, name)| {
+            let mut row = vec![name.to_string()];
+            row.extend(self.marginal[b].iter().map(|v| format!("{:>6.2}", v)));
+            row
+        }).collect();
+        lines.extend(text_util::render_table(&columns, &rows, &FrameType::Single));
+
+        // Chart: Relative value of each base stat, in percent of the most valuable one
+        lines.push(String::new());
+        lines.push("Relative value per point (% of the most valuable stat)".to_string());
+        let values: Vec<f64> = (0..BASE_STATS.len()).map(|b| self.relative_value(b)).collect();
+        let top = values.iter().cloned().fold(0f64, f64::max);
+        for (name, value) in BASE_STATS.iter().zip(values) {
+            let percent = if top > 0f64 { (100f64 * value / top).round() as i64 } else { 0 };
+            let bar = text_util::render_bar_with_num(name, w.max(14), percent, 100,
+                                                     BarStyle::TwoChars('█', '░'), Some(('[', ']')),
+                                                     Some((&formatting, "stat", format!("{:.4}", value))));
+            lines.push(bar);
+        }
+
+        // Findings
+        lines.push(String::new());
+        for name in self.ignored_stats() {
+            lines.push(format!("! {} is ignored by all formulas.", name));
+        }
+        for name in self.constant_stats() {
+            lines.push(format!("! {} is the same for every build.", name));
+        }
+        for build in self.dominant_builds() {
+            lines.push(format!("! {} dominates {:.0}% of all builds.", build.describe(),
+                               100f64 * build.dominated_share));
+        }
+        if lines.last().map_or(false, |l| l.is_empty()) {
+            lines.push("No issues found.".to_string());
+        }
+
+        lines
+    }
+}
+
+impl InfoGrid for BalanceReport {
+    fn display(&self, w: usize, h: usize, formatting: TextFormatting) -> Vec<String> {
+        let mut output = self.render(w, formatting);
+
+        // Backfill output to ensure it has sufficient lines
+        while output.len() < h {
+            output.push(" ".repeat(w));
+        }
+        output.truncate(h);
+
+        output
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_analysis() {
+        let mut analyzer = BalanceAnalyzer::new(12);
+        analyzer.set_step(3);
+        assert_eq!(analyzer.sweep().len(), 126);
+        assert!(analyzer.sweep().iter().all(|s| s.dex + s.str + s.grt + s.wil + s.cha + s.int == 12));
+
+        // MVE once was a constant 0, ignoring its stats
+        analyzer.set_formulas(vec![
+            ("MHP".to_string(), Stats::max_hp),
+            ("MVE".to_string(), |_| 0),
+            ("PDF".to_string(), Stats::phys_defense),
+        ]);
+        let report = analyzer.analyze();
+
+        assert_eq!(report.constant_stats(), vec!["MVE"]);
+        assert_eq!(report.ignored_stats(), vec!["DEX", "INT"]);
+        // Every point of GRT or WIL adds 24 MHP
+        assert_eq!(report.marginal_value(2, 0), 24f64);
+        // GRT feeds both MHP and PDF, so putting everything into GRT dominates everything else
+        let dominant = report.dominant_builds();
+        assert_eq!(dominant[0].describe(), "12 GRT");
+        assert!(report.relative_value(2) > report.relative_value(3));
+
+        let lines = report.render(40, TextFormatting::Plain);
+        assert!(lines.contains(&"! MVE is the same for every build.".to_string()));
+    }
+}
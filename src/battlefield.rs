@@ -0,0 +1,79 @@
+//! Contains the (lightweight) spatial model of combat.
+//!
+//! Every party stands in a **formation of two rows**. The front row shields the back row:
+//!
+//! * **Melee** maneuvers can only reach opponents in the front row, and can only be performed
+//! from the front row. Once a party's front row has fallen, its back row is exposed.
+//! * Characters in the back row take reduced damage (**cover**) while someone of their party
+//! still stands in front of them.
+//! * Changing rows costs MVE, which is refilled every turn.
+
+use std::fmt::{Display, Formatter};
+use crate::characters::Character;
+use crate::world::WorldContext;
+
+/// MVE needed to move from one row to the other in a single turn
+pub const ROW_MOVE_COST: i64 = 2;
+
+/// Damage factor for attacks on covered characters in the back row
+pub const COVER_FACTOR: f64 = 0.7;
+
+/// Describes the rows of a party's formation
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Row {
+    Front,
+    Back,
+}
+
+impl Row {
+    /// Returns the respective other row
+    pub fn other(&self) -> Row {
+        match self {
+            Row::Front => Row::Back,
+            Row::Back => Row::Front,
+        }
+    }
+}
+
+impl Display for Row {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Row::Front => write!(f, "front row"),
+            Row::Back => write!(f, "back row"),
+        }
+    }
+}
+
+/// Describes how far a maneuver reaches
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Range {
+    /// Reaches opponents in the front row only, and only from the front row
+    Melee,
+    /// Reaches any opponent from anywhere
+    Ranged,
+}
+
+/// Checks whether someone of the party named `party` still stands in the front row
+pub fn front_row_standing(party: &String, context: &dyn WorldContext) -> bool {
+    !context.find_characters(
+        &|c: &Character| c.party_check(party) && !c.is_defeated() && c.get_row() == Row::Front).is_empty()
+}
+
+/// Checks whether `target` can be reached by a maneuver with the given `range`
+pub fn can_reach(range: Range, target: &Character, context: &dyn WorldContext) -> bool {
+    match range {
+        Range::Ranged => true,
+        Range::Melee => target.get_row() == Row::Front || !front_row_standing(target.party(), context),
+    }
+}
+
+/// Checks whether `character` can perform a maneuver with the given `range` from their row
+pub fn can_perform(range: Range, character: &Character) -> bool {
+    range == Range::Ranged || character.get_row() == Row::Front
+}
+
+/// Checks whether `target` is covered, i.e. stands in the back row with someone of their party
+/// still standing in front of them
+pub fn is_covered(target: &Character, context: &dyn WorldContext) -> bool {
+    target.get_row() == Row::Back && front_row_standing(target.party(), context)
+}
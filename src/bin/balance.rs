@@ -0,0 +1,42 @@
+use tusslehussle::balance::BalanceAnalyzer;
+use tusslehussle::ruleset::{set_ruleset, Ruleset};
+use tusslehussle::text::TextFormatting;
+
+const USAGE: &str = "Usage: balance [--budget N] [--step N] [--dominance SHARE] [--ruleset PATH] [--width N]";
+
+fn main() -> Result<(), String> {
+    let mut budget = 30;
+    let mut step = None;
+    let mut dominance = None;
+    let mut width = 60;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}.\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--budget" => budget = parse(&value()?)?,
+            "--step" => step = Some(parse(&value()?)?),
+            "--dominance" => dominance = Some(parse(&value()?)?),
+            "--ruleset" => set_ruleset(Ruleset::load(&value()?)?),
+            "--width" => width = parse(&value()?)?,
+            _ => return Err(format!("Unknown argument {}.\n{}", arg, USAGE)),
+        }
+    }
+
+    let mut analyzer = BalanceAnalyzer::new(budget);
+    if let Some(step) = step {
+        analyzer.set_step(step);
+    }
+    if let Some(dominance) = dominance {
+        analyzer.set_dominance_threshold(dominance);
+    }
+
+    for line in analyzer.analyze().render(width, TextFormatting::Console) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid number {}.\n{}", value, USAGE))
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::sync::Arc;
+use tusslehussle::demo::build_demo_combat;
+use tusslehussle::ruleset::Ruleset;
+use tusslehussle::simulation::Simulation;
+
+const USAGE: &str = "Usage: simulate [--runs N] [--threads N] [--seed N] [--max-turns N] [--ruleset PATH] [--csv PATH] [--json PATH]";
+
+fn main() -> Result<(), String> {
+    let mut simulation = Simulation::new(build_demo_combat, 1000);
+    let mut csv_path = None;
+    let mut json_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}.\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--runs" => simulation.set_runs(parse(&value()?)?),
+            "--threads" => simulation.set_threads(parse(&value()?)?),
+            "--seed" => simulation.set_base_seed(parse(&value()?)?),
+            "--max-turns" => simulation.set_max_turns(parse(&value()?)?),
+            "--ruleset" => simulation.set_ruleset(Some(Arc::new(Ruleset::load(&value()?)?))),
+            "--csv" => csv_path = Some(value()?),
+            "--json" => json_path = Some(value()?),
+            _ => return Err(format!("Unknown argument {}.\n{}", arg, USAGE)),
+        }
+    }
+
+    let report = simulation.run();
+
+    println!("{} runs, {:.1} turns on average, {:.1}% undecided", report.iter_runs().count(),
+             report.average_turns(), 100f64 * report.undecided_rate());
+    for party in report.get_parties() {
+        let hp = report.hp_remaining(party);
+        println!("{}: {:.1}% wins, HP remaining {:.1}% on average (10%: {:.1}%, median: {:.1}%, 90%: {:.1}%)",
+                 party, 100f64 * report.win_rate(party), 100f64 * hp.mean(), 100f64 * hp.quantile(0.1),
+                 100f64 * hp.quantile(0.5), 100f64 * hp.quantile(0.9));
+    }
+    for (name, impact) in report.reaction_impact() {
+        println!("{}: {} actions, {} damage dealt, {} damage prevented", name, impact.actions,
+                 impact.damage_dealt, impact.damage_prevented);
+    }
+
+    if let Some(path) = csv_path {
+        fs::write(&path, report.to_csv()).map_err(|e| format!("Could not write {}: {}", path, e))?;
+    }
+    if let Some(path) = json_path {
+        fs::write(&path, report.to_json()).map_err(|e| format!("Could not write {}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid number {}.\n{}", value, USAGE))
+}
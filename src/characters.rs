@@ -1,20 +1,26 @@
-use std::cell::{Ref, RefCell};
+use std::cell::Cell;
 use std::cmp::max;
 use std::fmt::Display;
 use std::os::linux::raw::stat;
-use std::rc::Rc;
-use crate::effects::Effect;
-use crate::combat::{DamageType, Actor, Damage, Action, EntityPointer};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::effects::{Effect, EffectSelector, StackingPolicy, TimedEffect};
+use crate::combat::{DamageType, Actor, Damage, Action, ActionCost, CostLedger, EntityPointer};
 use crate::world::WorldContext;
-use crate::mov::{BarehandedBlow, Maneuver, Reaction};
+use crate::mov::{BarehandedBlow, Maneuver, MoveState, Reaction, Reposition, Wait, WindUp};
 use crate::text::{BarStyle, InfoGrid, TextFormatting, text_util, InfoLine, MakesWords};
-use crate::equipment::{Equipment, };
+use crate::equipment::{Equipment, ItemSet};
+use crate::consumables::ConsumableUsage;
+use crate::inventory::{Inventory, Item};
+use crate::spells::Spell;
+use crate::ruleset::{active_generation, active_ruleset, ruleset, Ruleset};
+use crate::battlefield::{can_perform, Row, ROW_MOVE_COST};
 use crate::layouts::{LayoutDirection, LayoutWeight, LinearLayout};
 
 /// Fundamental stats that any game entity can provide.
 /// These stats are 'dynamic' during gameplay and can change.
 /// From these basic stats, a broader set of Character data can be generated, and is fully described
 /// in the `CharacterStats` object.
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Stats {
     /// Dexterity
     pub dex: i64,
@@ -33,7 +39,8 @@ pub struct Stats {
 
 /// Describes the complete game stats that inform how a character interacts with the world. They are
 /// calculated from The base `Stats` and - during simulation - also from prevalent `Effect`s
-struct GameStats {
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GameStats {
     /// Max HP
     mhp: i64,
     /// Max MP
@@ -62,12 +69,58 @@ impl Display for GameStats {
     }
 }
 
+/// Stable handle of a character. Every character gets a fresh ID when it's built, which never
+/// changes afterwards and is never handed out twice, so characters are addressed by their ID
+/// (e.g. by `EntityPointer`s and world contexts) rather than by their name.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct CharacterId(u64);
+
+/// The next `CharacterId` to hand out
+static NEXT_CHARACTER_ID: AtomicU64 = AtomicU64::new(1);
+
+impl CharacterId {
+    /// Hands out a fresh ID that has never been handed out before
+    pub fn generate() -> CharacterId {
+        CharacterId(NEXT_CHARACTER_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Display for CharacterId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Points to a character by their ID. The character's name is kept alongside for narration
+/// only; two references are equal if they point to the same character.
+#[derive(Clone, Debug)]
+pub struct CharacterRef {
+    id: CharacterId,
+    name: String,
+}
+
+impl CharacterRef {
+    pub fn id(&self) -> CharacterId {
+        self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+}
+
+impl PartialEq for CharacterRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
 /// Characters are the key actors in the game world and make 100% of the player controlled entities.
 pub struct Character {
-    /// Character name
-    /// Character names are considered **unique for the entire world**, making Characters addressable
-    /// This requirement is not entirely strict (e.g. an individual world context can have named
-    /// characters that are not saved outside the context, unlike player characters)
+    /// Stable ID of this character, used to address it
+    id: CharacterId,
+    /// Character name, used for display only. Names don't need to be unique, e.g. an encounter
+    /// can have three characters named "Goblin".
     name: String,
     /// If this Character is owned and **controlled by a player**, their user name is stored here
     owner: Option<String>,
@@ -78,30 +131,69 @@ pub struct Character {
     base_stats: Stats,
     /// Every Character can hold a certain amount of equipment, held in this list
     equipment: Vec<Equipment>,
+    /// Items the Character carries without having them equipped
+    inventory: Inventory,
+    /// All spells this Character knows, in order of preference
+    spellbook: Vec<Spell>,
+    /// Timing of this Character's moves (cooldowns, charge-up moves), kept across turns
+    move_state: MoveState,
+    /// The row this Character stands in within their party's formation
+    row: Row,
+    /// If this Character was summoned, the ID of their summoner and the name of the summoning
+    /// (before any numbering)
+    summoned_by: Option<(CharacterId, String)>,
     /// All passive effects from other sources affecting this character.
     /// Passive effect affect a character for a set amount of (remaining) turns,
-    /// stored with each effect wihtin the tuples
+    /// stored with each effect (alongside the effect's source)
     /// (does not include effects from equipment or other sources on the character, which affects
     /// the character exactly every turn they have it equipped)
-    timed_effects: Vec<(Box<dyn Effect>, i64)>,
+    timed_effects: Vec<TimedEffect>,
+    /// Effects of the surroundings this character is currently in (e.g. a combat's environment),
+    /// set by the context holding the character
+    ambient_effects: Vec<Box<dyn Effect>>,
     /// Current Character Hit Points
     hp: i64,
     /// Current Character Mental Points
-    /// This is implemented via Internal Mutability Pattern / `RefCell`, because **MP can be updated
-    /// during action resolution** (for magic reactions)
-    mp: RefCell<i64>,
+    mp: i64,
     /// Current Action Points of this Character.
     /// Action Points are spent to **react to other actions**, allowing the Character more actions
-    /// per turn effectively.
-    /// This is implemented via Internal Mutability Pattern / `RefCell`, because **AP are updated
-    /// during action resolution** (for reactions)
-    ap: RefCell<i64>,
+    /// per turn effectively. Reactions only reserve their AP on the `ActionStack`'s `CostLedger`
+    /// while the stack is built, and pay once they resolve.
+    ap: i64,
+    /// Movement left to this Character in the current turn. Refilled to their MVE every turn
+    /// and spent on changing rows.
+    mve: i64,
     /// Represents this Character's current body fitness.
     /// Depletes slowly and replenishes when resting
     vit: i64,
-    /// A cached reference to the character's current `GameStats`. Since these are required often
-    /// to calculate base game movement, they can be cached as a reference in each Character
-    game_stats: Option<GameStats>,
+    /// A cached copy of the character's current `GameStats`. Since these are required often
+    /// to calculate base game movement, they are cached in each Character and invalidated
+    /// whenever anything they're calculated from changes (base stats, equipment, effects).
+    /// Cached along with the generation of the ruleset they were calculated with, since they are
+    /// stale once another ruleset becomes active.
+    game_stats: Cell<Option<(u64, GameStats)>>,
+}
+
+thread_local! {
+    /// If set (in debug builds), every cached `GameStats` read on this thread is checked against
+    /// a fresh calculation, panicking on stale caches
+    static VERIFY_STATS_CACHE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Checks cached `GameStats` read on the current thread against fresh calculations until the
+/// returned guard is dropped. Only has an effect in debug builds.
+pub fn verify_stats_cache() -> StatsCacheVerification {
+    StatsCacheVerification(VERIFY_STATS_CACHE.with(|verify| verify.replace(true)))
+}
+
+/// Keeps cached `GameStats` checked while alive (see `verify_stats_cache`), and restores the
+/// previous setting once dropped
+pub struct StatsCacheVerification(bool);
+
+impl Drop for StatsCacheVerification {
+    fn drop(&mut self) {
+        VERIFY_STATS_CACHE.with(|verify| verify.set(self.0));
+    }
 }
 
 
@@ -110,111 +202,74 @@ pub struct Character {
 /// covering the calculation of the *richer stats* of the game for each character.
 impl Stats {
     /// Returns the entity's maximum HP
-    /// This is based on:
     pub fn max_hp(&self) -> i64 {
-        let stat_factor = (self.grt + self.wil) * 2 // HP Focus stats
-            + self.str + self.cha; // Secondary HP stats
-
-        100 + stat_factor * 12
+        ruleset().max_hp.apply(self)
     }
 
     /// Returns the entity's maximum MP.
     pub fn max_mp(&self) -> i64 {
-        let stat_factor = (self.int + self.cha) * 2
-            + self.wil + self.dex;
-
-        stat_factor * 2
+        ruleset().max_mp.apply(self)
     }
 
     /// Returns the entity's maximum AP
     pub fn max_ap(&self) -> i64 {
-        let stat_factor = (self.dex + self.int + self.wil) * 2
-            + self.grt;
-
-        (stat_factor as f64 * 0.2f64) as i64
+        ruleset().max_ap.apply(self)
     }
 
     /// Returns the entity's TAP (amount of AP per turn)
     pub fn action_points(&self) -> i64 {
-        let stat_factor = (self.dex + self.int) * 2 // AP Focus Stats
-            + self.grt + self.wil; // Secondary AP Stats
-
-        // Running Counter of the entity's total AP
-        let mut total_ap = 1;
-
-        // Every X total stat_factor points, one additional AP is earned
-        total_ap += (stat_factor as f64 * 0.05).floor() as i64;
-
-        total_ap
+        ruleset().action_points.apply(self)
     }
 
     /// Returns the entity's MVE (maximum movement distance for a single turn)
     pub fn move_speed(&self) -> i64 {
-        let stat_factor: i64 = 0;
-        stat_factor * 5
+        ruleset().move_speed.apply(self)
     }
 
     /// Calculates the PDF based on stats.
     pub fn phys_defense(&self) -> i64 {
-        let stat_factor = self.grt + self.str;
-
-        (stat_factor as f64 * 1.2).floor() as i64
+        ruleset().phys_defense.apply(self)
     }
 
     /// Calculates the MDF based on stats.
     pub fn mag_defense(&self) -> i64 {
-        let stat_factor = self.wil + self.cha;
-
-        (stat_factor as f64 * 1.2).floor() as i64
+        ruleset().mag_defense.apply(self)
     }
 
     /// Calculates the MOB of this character
     pub fn mobility(&self) -> i64 {
-        let stat_factor = self.dex + self.int;
-
-        (stat_factor as f64 * 1.2).floor() as i64
+        ruleset().mobility.apply(self)
     }
 
     /// Calculates the HRG of this character
     pub fn health_regen(&self) -> i64 {
-        let stat_factor = self.grt*2 // Main Health Regen Stat
-            + self.wil + self.str; // Secondary Health Regen Stat
-
-        // Running Counter of Regen, ensure there is at least 1
-        let mut health_regen = 1;
-
-        health_regen += (stat_factor as f64 * 0.60).floor() as i64;
-
-        health_regen
+        ruleset().health_regen.apply(self)
     }
 
     /// Calculates the MRG of this character
     pub fn magic_regen(&self) -> i64 {
-        let stat_factor = self.wil*2
-            + self.cha + self.int;
-
-        // Running counter of magic regen, starting with a minimum of 1
-        let mut magic_regen = 1;
-
-        magic_regen += (stat_factor as f64 * 0.33).floor() as i64;
-
-        magic_regen
+        ruleset().magic_regen.apply(self)
     }
 
     /// Convenience function runs all calculations that convert the base stats into all
     /// Character game stats.
     pub fn to_game_stats(&self) -> GameStats {
+        self.to_game_stats_with(&ruleset())
+    }
+
+    /// Converts the base stats into all Character game stats, using the given `rules`
+    pub fn to_game_stats_with(&self, rules: &Ruleset) -> GameStats {
         GameStats {
-            mhp: self.max_hp(),
-            mmp: self.max_mp(),
-            tap: self.action_points(),
-            mve: self.move_speed(),
-            pdf: self.phys_defense(),
-            mdf: self.mag_defense(),
-            mob: self.mobility(),
-            hrg: self.health_regen(),
-            mrg: self.magic_regen(),
-            map: self.max_ap(),
+            mhp: rules.max_hp.apply(self),
+            mmp: rules.max_mp.apply(self),
+            tap: rules.action_points.apply(self),
+            mve: rules.move_speed.apply(self),
+            pdf: rules.phys_defense.apply(self),
+            mdf: rules.mag_defense.apply(self),
+            mob: rules.mobility.apply(self),
+            hrg: rules.health_regen.apply(self),
+            mrg: rules.magic_regen.apply(self),
+            map: rules.max_ap.apply(self),
         }
     }
 
@@ -235,9 +290,12 @@ impl Stats {
 
     /// Calculates the Maximum VIT a Characeter with these stats starts out with after Resting
     pub fn max_vit(&self) -> i64 {
-        let stat_factor = self.grt*4 + self.wil*3 + self.str + self.cha;
+        ruleset().max_vit.apply(self)
+    }
 
-        stat_factor * 15
+    /// Calculates the number of inventory slots a Character with these stats can carry
+    pub fn carry_slots(&self) -> usize {
+        ruleset().carry_slots.apply(self).max(1) as usize
     }
 
     /// Formats this stat block as a requirement string.
@@ -408,6 +466,41 @@ impl GameStats {
     pub fn mrg(&self) -> i64 {
         self.mrg
     }
+    /// Maximum AP
+    pub fn map(&self) -> i64 {
+        self.map
+    }
+
+    /// Returns the game stat described by `stat`, or `None` for base stats
+    fn game_stat_mut(&mut self, stat: &CharStat) -> Option<&mut i64> {
+        match stat {
+            CharStat::MHP(_) => Some(&mut self.mhp),
+            CharStat::MMP(_) => Some(&mut self.mmp),
+            CharStat::TAP(_) => Some(&mut self.tap),
+            CharStat::MVE(_) => Some(&mut self.mve),
+            CharStat::PDF(_) => Some(&mut self.pdf),
+            CharStat::MDF(_) => Some(&mut self.mdf),
+            CharStat::MOB(_) => Some(&mut self.mob),
+            CharStat::HRG(_) => Some(&mut self.hrg),
+            CharStat::MRG(_) => Some(&mut self.mrg),
+            _ => None,
+        }
+    }
+
+    /// Adds the value of `stat` to the respective game stat. Base stats are ignored.
+    pub fn add(&mut self, stat: &CharStat) {
+        if let Some(value) = self.game_stat_mut(stat) {
+            *value += stat.get_value();
+        }
+    }
+
+    /// Changes the respective game stat by the value of `stat` in percent (e.g. `MHP(15)` adds
+    /// 15% MHP). Base stats are ignored.
+    pub fn add_percentage(&mut self, stat: &CharStat) {
+        if let Some(value) = self.game_stat_mut(stat) {
+            *value = (*value as f64 * (1f64 + stat.get_value() as f64 / 100f64)).floor() as i64;
+        }
+    }
 }
 
 
@@ -432,6 +525,7 @@ pub enum CharUnit {
 }
 
 /// Represents a **linear stat** for any game stat both basic and specific game stats
+#[derive(Clone, Copy)]
 pub enum CharStat {
     // Base Stats
     DEX(i64),
@@ -581,6 +675,7 @@ impl Character {
     /// * Their base stats that define basic capabilities
     pub fn new(name: String, owner: Option<String>, base_stats: Stats) -> Self {
         let mut character = Character {
+            id: CharacterId::generate(),
             name: name,
             owner: owner,
             // By default, characters are part of no team
@@ -588,23 +683,34 @@ impl Character {
             base_stats: base_stats,
             // By default, characters have no equipment
             equipment: vec![],
+            // ... and carry nothing
+            inventory: Inventory::with_capacity(base_stats.carry_slots()),
+            // ... and know no spells
+            spellbook: vec![],
+            move_state: MoveState::new(),
+            // Everyone starts out in the front row
+            row: Row::Front,
+            summoned_by: None,
             // And no effects
             timed_effects: vec![],
+            ambient_effects: vec![],
             // These stats will be calculated right after building the base
             hp: 0,
-            mp: RefCell::new(0),
-            ap: RefCell::new(0),
+            mp: 0,
+            ap: 0,
+            mve: 0,
             /// Vitality (secondary stat that declines gradually)
             vit: 0,
             // Empty cache at the beginning
-            game_stats: None,
+            game_stats: Cell::new(None),
         };
 
         // Set the character's HP, MP, and secondary stats to max by default
         character.hp = character.base_stats.max_hp();
-        *character.mp.get_mut() = character.base_stats.max_mp();
-        *character.ap.get_mut() = character.base_stats.max_ap();
+        character.mp = character.base_stats.max_mp();
+        character.ap = character.base_stats.max_ap();
         character.vit = character.base_stats.max_vit();
+        character.mve = character.calculate_game_stats().mve();
 
         character
     }
@@ -628,12 +734,43 @@ impl Character {
     }
 
     /// Using all current effects affecting this Character, calculates the basic game stats of this
+    /// Character. The result is cached until anything it's calculated from changes.
     pub fn calculate_game_stats(&self) -> GameStats {
+        match self.game_stats.get() {
+            Some((cached_generation, cached)) if cached_generation == active_generation() => {
+                if cfg!(debug_assertions) && VERIFY_STATS_CACHE.with(|verify| verify.get()) {
+                    assert_eq!(cached, self.compute_game_stats(&ruleset()), "Stale GameStats cache of {}", self.name);
+                }
+                cached
+            }
+            _ => {
+                // The rules are fetched once for the whole calculation
+                let (generation, rules) = active_ruleset();
+                let game_stats = self.compute_game_stats(&rules);
+                self.game_stats.set(Some((generation, game_stats)));
+                game_stats
+            }
+        }
+    }
+
+    /// Drops the cached `GameStats`, so they are calculated freshly on their next use. Must be
+    /// called whenever base stats, equipment or effects change.
+    fn invalidate_stats(&mut self) {
+        self.game_stats.set(None);
+    }
+
+    /// Calculates the game stats of this Character from scratch, ignoring the cache
+    fn compute_game_stats(&self, rules: &Ruleset) -> GameStats {
         // Build the final 'current' game stats
-        let mut game_stats = self.calculate_current_stats().to_game_stats();
+        let mut game_stats = self.calculate_current_stats().to_game_stats_with(rules);
 
-        for effect in &self.all_current_effects()
-        {}
+        // Effects modify the derived game stats in resolution order, i.e. additive modifiers
+        // before multiplicative ones
+        let mut fx = self.all_current_effects();
+        fx.sort_by_key(|e| e.effect_order());
+        for effect in fx {
+            effect.apply_to_game_stats(&mut game_stats);
+        }
 
         game_stats
     }
@@ -643,22 +780,46 @@ impl Character {
     }
 
     pub fn mp(&self) -> i64 {
-        *self.mp.borrow()
+        self.mp
     }
 
     pub fn ap(&self) -> i64 {
-        *self.ap.borrow()
+        self.ap
+    }
+
+    pub fn mve(&self) -> i64 {
+        self.mve
+    }
+
+    /// A character is defeated once their HP drop to 0 or below. Defeated characters no longer
+    /// take part in combat.
+    pub fn is_defeated(&self) -> bool {
+        self.hp <= 0
+    }
+
+    /// Returns the ID of the character that currently taunts this character, if any
+    pub fn taunted_by(&self) -> Option<CharacterId> {
+        self.all_current_effects().into_iter().find_map(|e| e.taunted_by())
     }
 
     /// Convenience function returns the percentage of HP this character has currently.
     pub fn hp_to_max_hp_ratio(&self) -> f64 {
-        self.hp as f64 / self.calculate_current_stats().max_hp() as f64
+        self.hp as f64 / self.calculate_game_stats().mhp() as f64
+    }
+
+    pub fn id(&self) -> CharacterId {
+        self.id
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    /// Renames this Character, e.g. to tell apart characters of the same name in a context
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
     pub fn party(&self) -> &String {
         &self.party
     }
@@ -673,8 +834,101 @@ impl Character {
         self.party == *party_name
     }
 
+    /// Iterates all timed effects on this Character, with their remaining turns
+    pub fn iter_timed_effects(&self) -> core::slice::Iter<TimedEffect> {
+        self.timed_effects.iter()
+    }
+
+    /// Summarizes the timed effects on this Character by identity, as (ID, number of stacks,
+    /// remaining turns of the longest lasting stack)
+    pub fn summarize_timed_effects(&self) -> Vec<(String, usize, i64)> {
+        let mut summary: Vec<(crate::effects::EffectIdentity, usize, i64)> = vec![];
+        for timed in self.timed_effects.iter() {
+            let identity = timed.identity();
+            match summary.iter_mut().find(|(i, _, _)| *i == identity) {
+                Some((_, stacks, remaining)) => {
+                    *stacks += 1;
+                    *remaining = (*remaining).max(timed.remaining);
+                }
+                None => summary.push((identity, 1, timed.remaining)),
+            }
+        }
+        summary.into_iter().map(|(i, stacks, remaining)| (i.id, stacks, remaining)).collect()
+    }
+
+    /// Removes all timed effects selected by `selector` (only negative ones, if `negative_only`
+    /// is set) and returns them with their remaining turns
+    pub fn remove_timed_effects(&mut self, selector: &EffectSelector, negative_only: bool) -> Vec<TimedEffect> {
+        let (removed, kept) = self.timed_effects.drain(..)
+            .partition(|t| selector.matches(t) && (!negative_only || t.effect.is_negative()));
+        self.timed_effects = kept;
+        if !removed.is_empty() {
+            self.invalidate_stats();
+        }
+        removed
+    }
+
+    /// Extends all timed effects selected by `selector` by `turns`. Negative `turns` shorten the
+    /// effects, ending them once no turns remain.
+    pub fn extend_timed_effects(&mut self, selector: &EffectSelector, turns: i64) {
+        for timed in self.timed_effects.iter_mut() {
+            if selector.matches(timed) {
+                timed.remaining += turns;
+            }
+        }
+        // Only effects running out change the game stats
+        let before = self.timed_effects.len();
+        self.timed_effects.retain(|t| t.remaining > 0);
+        if self.timed_effects.len() != before {
+            self.invalidate_stats();
+        }
+    }
+
+    /// Replaces the effects of this Character's surroundings with `effects`
+    pub fn set_ambient_effects(&mut self, effects: Vec<Box<dyn Effect>>) {
+        self.ambient_effects = effects;
+        self.invalidate_stats();
+    }
+
+    pub fn get_row(&self) -> Row {
+        self.row
+    }
+
+    pub fn set_row(&mut self, row: Row) {
+        self.row = row;
+    }
+
+    /// Moves this Character to `row`, spending `ROW_MOVE_COST` of their movement left this turn.
+    /// Characters without enough movement left stay where they are.
+    pub fn reposition(&mut self, row: Row) {
+        if self.row != row && self.mve >= ROW_MOVE_COST {
+            self.mve -= ROW_MOVE_COST;
+            self.row = row;
+        }
+    }
+
+    /// Returns the ID of this character's summoner and the name of the summoning, if this
+    /// character was summoned
+    pub fn get_summoned_by(&self) -> Option<(CharacterId, &String)> {
+        self.summoned_by.as_ref().map(|(id, name)| (*id, name))
+    }
+
+    /// Marks this character as summoned by the character with the given `id`, through the
+    /// summoning named `name`
+    pub fn set_summoned_by(&mut self, id: CharacterId, name: String) {
+        self.summoned_by = Some((id, name));
+    }
+
+    /// Builds a reference pointing to this character
+    pub fn to_ref(&self) -> CharacterRef {
+        CharacterRef {
+            id: self.id,
+            name: self.name.clone(),
+        }
+    }
+
     pub fn as_target(&self) -> EntityPointer {
-        EntityPointer::Character(vec![self.name.clone()])
+        EntityPointer::Character(vec![self.to_ref()])
     }
 
     // -------------- List all ... --------------
@@ -685,17 +939,27 @@ impl Character {
         // Build a new vector to contain all effects to consider for this character at this time
         let mut effect_list = Vec::new();
 
-        for (effect, _) in &self.timed_effects {
+        for timed in &self.timed_effects {
+            effect_list.push(&timed.effect);
+        }
+
+        // Everyone is affected by their surroundings
+        for effect in &self.ambient_effects {
             effect_list.push(effect);
         }
 
-        // In addition, add all permanent effects from Equipment
-        for equipment in &self.equipment {
+        // In addition, add all permanent effects from (functional) Equipment
+        for equipment in self.functional_equipment() {
            for effect in equipment.get_passive_effects() {
                effect_list.push(effect);
            }
         }
 
+        // ... and from all active set bonuses
+        for (item_set, equipped) in self.equipped_sets() {
+            item_set.add_passive_effects(equipped, &mut effect_list);
+        }
+
         // Now that all effects are accounted for, sort this listing to ensure it's ordered in
         // resolution order (ascending by effect order number)
         // effect_list.sort_by_key(|e: &Box<&dyn Effect>| e.effect_order());
@@ -703,21 +967,79 @@ impl Character {
         effect_list
     }
 
-    /// Computes all available moves for this Character at this time.
-    fn all_current_moves(&self) -> Vec<&dyn Maneuver> {
-        todo!()
+    /// Computes all available moves for this Character at this time, in order of preference.
+    /// Consumables come first (as they only make themselves available when useful), then known
+    /// spells (as long as MP last), then moves from equipment (incl. weapon attacks). Characters
+    /// in the back row advance to the front row if nothing else is available. `BarehandedBlow`
+    /// comes last, as a last resort for characters in the front row.
+    fn all_current_moves(&self) -> Vec<Box<dyn Maneuver + '_>> {
+        let mut ret: Vec<Box<dyn Maneuver + '_>> = Vec::new();
+        for item in self.inventory.iter() {
+            if let Item::Consumable(c) = item {
+                if let ConsumableUsage::Maneuver = c.get_usage() {
+                    ret.push(Box::new(c));
+                }
+            }
+        }
+        for spell in self.spellbook.iter() {
+            ret.push(Box::new(spell));
+        }
+        for eq in self.functional_equipment() {
+            eq.add_moves(&mut ret)
+        }
+        for (item_set, equipped) in self.equipped_sets() {
+            item_set.add_moves(equipped, &mut ret);
+        }
+
+        ret.push(Box::new(Reposition(Row::Front)));
+        ret.push(Box::new(BarehandedBlow));
+
+        ret
+    }
+
+    /// Checks whether this Character can perform `maneuver` right now: It must not be on
+    /// cooldown, must reach from this Character's row, and must be available.
+    fn can_perform(&self, maneuver: &dyn Maneuver, context: &dyn WorldContext) -> bool {
+        !self.move_state.is_on_cooldown(&maneuver.name())
+            && can_perform(maneuver.range(), self)
+            && maneuver.is_available(self, context)
     }
 
     /// Computes all available reactions for this Character at this time
     fn all_current_reactions(&self) -> Vec<&dyn Reaction> {
         let mut ret = Vec::new();
-        for eq in self.equipment.iter() {
+        for eq in self.functional_equipment() {
             eq.add_reactions(&mut ret)
         }
+        for (item_set, equipped) in self.equipped_sets() {
+            item_set.add_reactions(equipped, &mut ret);
+        }
+        for item in self.inventory.iter() {
+            if let Item::Consumable(c) = item {
+                if let ConsumableUsage::LowHealth(_) = c.get_usage() {
+                    ret.push(c);
+                }
+            }
+        }
 
         ret
     }
 
+    /// Lists all item sets this Character wears at least one (unbroken) piece of, together with
+    /// the number of pieces equipped.
+    pub fn equipped_sets(&self) -> Vec<(&ItemSet, usize)> {
+        let mut sets: Vec<(&ItemSet, usize)> = Vec::new();
+        for item_set in self.functional_equipment().filter_map(|e| e.get_item_set()) {
+            // Sets are identified by their name
+            match sets.iter_mut().find(|(s, _)| s.name() == item_set.name()) {
+                Some((_, equipped)) => *equipped += 1,
+                None => sets.push((item_set, 1)),
+            }
+        }
+
+        sets
+    }
+
     // -------------- Forward Iterators --------------
 
     /// Allows 'safe' mutable iteration of this character's equipment for checks.
@@ -725,10 +1047,33 @@ impl Character {
         self.equipment.iter()
     }
 
+    /// Iterates all equipment that is not broken, i.e. that still provides its effects, moves
+    /// and reactions.
+    fn functional_equipment(&self) -> impl Iterator<Item=&Equipment> {
+        self.equipment.iter().filter(|e| !e.is_broken())
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
+    pub fn iter_spells(&self) -> core::slice::Iter<Spell> {
+        self.spellbook.iter()
+    }
+
+    pub fn move_state(&self) -> &MoveState {
+        &self.move_state
+    }
+
     // -------------- Modify --------------
 
-    /// Equips an (equipment) item, unless something prevents it.
-    pub fn equip(&mut self, equipment: Equipment) -> Result<(), String> {
+    /// Checks whether this character could equip `equipment` right now. Returns the reason as an
+    /// error if not.
+    pub fn can_equip(&self, equipment: &Equipment) -> Result<(), String> {
         // Check 1: Max Equipment Number: For now, it's just hard set to 3
         if self.equipment.len() >= 3 {
             return Err("Cannot equip more than 3 items.".to_string())
@@ -745,14 +1090,141 @@ impl Character {
             return Err(format!("Cannot equip more {}", equipment.get_eq_type()))
         }
 
+        Ok(())
+    }
+
+    /// Equips an (equipment) item, unless something prevents it.
+    pub fn equip(&mut self, equipment: Equipment) -> Result<(), String> {
+        self.can_equip(&equipment)?;
+
         // All Checks passed: Equipment should be added to character's equipment
         self.equipment.push(equipment);
+        self.invalidate_stats();
 
 
         // Communicate that the equipment process was successful
         Ok(())
     }
 
+    /// Makes the move named `name` unavailable to this character for the next `turns` turns
+    pub fn start_cooldown(&mut self, name: String, turns: i64) {
+        self.move_state.start_cooldown(name, turns);
+    }
+
+    /// Winds up this character's charge-up move named `name` by one turn
+    pub fn wind_up(&mut self, name: &str) {
+        self.move_state.wind_up(name);
+    }
+
+    /// Adds a `spell` to this character's spellbook. Spells learned earlier are preferred.
+    pub fn learn_spell(&mut self, spell: Spell) {
+        self.spellbook.push(spell);
+    }
+
+    /// Spends `amount` MP, unless this character can't afford it. Returns whether the MP were
+    /// spent.
+    pub fn spend_mp(&mut self, amount: i64) -> bool {
+        if self.mp < amount {
+            return false;
+        }
+        self.mp -= amount;
+        true
+    }
+
+    /// Spends `amount` AP, e.g. for a reaction that resolved. AP may drop below 0, which keeps
+    /// this character from reacting until they recovered.
+    pub fn spend_ap(&mut self, amount: i64) {
+        self.ap -= amount;
+    }
+
+    /// Uses up the consumable named `consumable_name` from this character's inventory. Returns
+    /// `false` if there's no such consumable (anymore).
+    pub fn consume_item(&mut self, consumable_name: &str) -> bool {
+        let position = self.inventory.iter().position(|i|
+            matches!(i, Item::Consumable(c) if c.get_name() == consumable_name));
+
+        match position {
+            Some(i) => self.inventory.remove(i).is_some(),
+            None => false,
+        }
+    }
+
+    /// Takes off the equipment at `index`, returning it (if there is any)
+    pub fn unequip(&mut self, index: usize) -> Option<Equipment> {
+        if index < self.equipment.len() {
+            self.invalidate_stats();
+            Some(self.equipment.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Swaps the equipment at `index` for `equipment` (e.g. when the item's definition was
+    /// reloaded), as long as this character could equip it in its place. Returns the previous
+    /// equipment, or the refused `equipment` along with the reason.
+    pub fn replace_equipment(&mut self, index: usize, equipment: Equipment) -> Result<Equipment, (Equipment, String)> {
+        if index >= self.equipment.len() {
+            return Err((equipment, format!("There is no equipment at {}.", index)));
+        }
+
+        // Checked without the previous equipment, as if it had been taken off first
+        let previous = self.equipment.remove(index);
+        self.invalidate_stats();
+        let checked = self.can_equip(&equipment);
+        match checked {
+            Ok(()) => {
+                self.equipment.insert(index, equipment);
+                self.invalidate_stats();
+                Ok(previous)
+            }
+            Err(e) => {
+                self.equipment.insert(index, previous);
+                self.invalidate_stats();
+                Err((equipment, e))
+            }
+        }
+    }
+
+    /// Wears down the equipped item at `index` by `amount` durability
+    pub fn wear_equipment(&mut self, index: usize, amount: i64) {
+        if let Some(eq) = self.equipment.get_mut(index) {
+            let was_broken = eq.is_broken();
+            eq.wear(amount);
+            // Broken equipment no longer provides its effects
+            if eq.is_broken() != was_broken {
+                self.invalidate_stats();
+            }
+        }
+    }
+
+    /// Restores up to `amount` durability on every equipped item
+    pub fn repair_equipment(&mut self, amount: i64) {
+        let broken = self.equipment.iter().filter(|e| e.is_broken()).count();
+        for eq in self.equipment.iter_mut() {
+            eq.repair(amount);
+        }
+        // Repaired equipment provides its effects again
+        if self.equipment.iter().filter(|e| e.is_broken()).count() != broken {
+            self.invalidate_stats();
+        }
+    }
+
+    /// Allocates the base stat change described by `stat` (e.g. when leveling up). Game stats
+    /// can't be allocated directly.
+    pub fn allocate_stat(&mut self, stat: CharStat) -> Result<(), String> {
+        match stat {
+            CharStat::STR(v) => self.base_stats.str += v,
+            CharStat::DEX(v) => self.base_stats.dex += v,
+            CharStat::GRT(v) => self.base_stats.grt += v,
+            CharStat::WIL(v) => self.base_stats.wil += v,
+            CharStat::CHA(v) => self.base_stats.cha += v,
+            CharStat::INT(v) => self.base_stats.int += v,
+            _ => return Err(format!("{} can't be allocated.", stat.get_stat_name())),
+        }
+        self.invalidate_stats();
+        Ok(())
+    }
+
     // -------------- (Text) Formatting helpers --------------
 
     /// Builds a string that represents this characters equipment best for the length provided
@@ -771,6 +1243,35 @@ impl Character {
 
         ret
     }
+
+    /// Describes all timed effects with their stack counts and remaining turns, e.g.
+    /// "+2 DEX x3 (2t)"
+    fn build_effect_description(&self, len: usize) -> String {
+        self.summarize_timed_effects().iter()
+            .map(|(id, stacks, remaining)| if *stacks > 1 {
+                format!("{} x{} ({}t)", id, stacks, remaining)
+            } else {
+                format!("{} ({}t)", id, remaining)
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+            .format_line(len, TextFormatting::Plain)
+    }
+
+    /// Builds a string that represents the progress on all sets this character wears, e.g.
+    /// `Stormguard 2/4`
+    fn build_set_description(&self, len: usize) -> String {
+        let sets = self.equipped_sets();
+        if sets.is_empty() {
+            return "<no sets>".to_string().format_line(len, TextFormatting::Plain);
+        }
+
+        sets.iter()
+            .map(|(s, equipped)| format!("{} {}/{}", s.name(), equipped, s.num_pieces()))
+            .collect::<Vec<String>>()
+            .join(", ")
+            .format_line(len, TextFormatting::Plain)
+    }
 }
 
 impl Actor for Character {
@@ -778,25 +1279,56 @@ impl Actor for Character {
         // REGEN: HP, MP, AP
         let stats = self.calculate_game_stats();
         self.hp = (self.hp + stats.hrg).min(stats.mhp);
-        let mut mp_ptr = self.mp.get_mut();
-        *mp_ptr = (*mp_ptr + stats.mrg).min(stats.mmp);
-        let mut ap_ptr = self.ap.get_mut();
-        *ap_ptr = (*ap_ptr + stats.tap).min(stats.map);
+        self.mp = (self.mp + stats.mrg).min(stats.mmp);
+        self.ap = (self.ap + stats.tap).min(stats.map);
+        self.mve = stats.mve;
     }
 
     fn post_turn(&mut self) {
         // Decrease the turn count of all timed effects on this Character
-        for (_, remaining_time) in &mut self.timed_effects {
-            *remaining_time -= 1;
+        for timed in &mut self.timed_effects {
+            timed.remaining -= 1;
         }
         // Filter out all effects that timed out
-        self.timed_effects.retain(|(_, remaining_time)| *remaining_time > 0);
+        let before = self.timed_effects.len();
+        self.timed_effects.retain(|t| t.remaining > 0);
+        if self.timed_effects.len() != before {
+            self.invalidate_stats();
+        }
+
+        // Progress cooldowns and charge-up moves
+        self.move_state.tick();
 
         // TODO: Check if 'stayalive requirements' are met (HP>0)
     }
 
-    fn next_move(&self) -> &dyn Maneuver {
-        &BarehandedBlow
+    fn next_move(&self, context: &dyn WorldContext) -> Box<dyn Maneuver + '_> {
+        let mut moves = self.all_current_moves();
+
+        // A charge-up move being wound up is continued, and released once fully wound up
+        if let Some((name, turns)) = self.move_state.get_charging() {
+            if let Some(i) = moves.iter().position(|m| m.name() == *name && self.can_perform(m.as_ref(), context)) {
+                let charged = moves.swap_remove(i);
+                if turns >= charged.charge_turns() {
+                    return charged;
+                }
+                return Box::new(WindUp(charged));
+            }
+        }
+
+        // Otherwise, go with the most preferred move available. Characters that can't perform
+        // anything (not even the last resort) wait for their next turn.
+        let next = match moves.into_iter().find(|m| self.can_perform(m.as_ref(), context)) {
+            Some(next) => next,
+            None => return Box::new(Wait),
+        };
+
+        // Charge-up moves need to be wound up first
+        if next.charge_turns() > 0 {
+            Box::new(WindUp(next))
+        } else {
+            next
+        }
     }
 
     fn apply_damage(&mut self, damage: &Damage) {
@@ -804,26 +1336,40 @@ impl Actor for Character {
         // We calculate the effective damage in this running counter
         let mut effective_damage = damage.amount();
 
-        // Before confirming the effective damage, process it through all effects
-        let fx = self.all_current_effects();
-
+        // Before confirming the effective damage, process it through all effects (in resolution
+        // order)
+        let mut fx = self.all_current_effects();
+        fx.sort_by_key(|e| e.effect_order());
 
         for effect in fx {
-            // TODO the error that broke me effect.on_damage_receive(self, damage_type, effective_damage);
+            effective_damage = effect.on_damage_receive(damage, effective_damage);
         }
 
         // First, Apply All Defenses to this damage
         let gamestats = self.calculate_game_stats();
 
         // Apply basic PHY / MAG defense
+        let rules = ruleset().damage;
         let defense_adjust = match damage.dmg_type() {
-            DamageType::PHY(_) => gamestats.pdf,
-            DamageType::MAG(_) => gamestats.mdf,
-            DamageType::ZAP(_) => gamestats.mdf/2,
+            DamageType::PHY(_) => (gamestats.pdf as f64 * rules.phys_defense_factor) as i64,
+            DamageType::MAG(_) => (gamestats.mdf as f64 * rules.mag_defense_factor) as i64,
+            DamageType::ZAP(_) => (gamestats.mdf as f64 * rules.zap_defense_factor) as i64,
             // ULT damage cannot be defended
             DamageType::ULT => 0,
         };
 
+        // Armour that helps absorbing damage through PDF / MDF wears down
+        if defense_adjust > 0 && effective_damage > 0 {
+            let mut broke = false;
+            for eq in self.equipment.iter_mut().filter(|e| e.get_eq_type().is_armour() && !e.is_broken()) {
+                eq.wear(1);
+                broke |= eq.is_broken();
+            }
+            if broke {
+                self.invalidate_stats();
+            }
+        }
+
         // Adjust damange by PHY / MAG defense (ensure too small damage don't go into negative)
         effective_damage = (effective_damage - defense_adjust).max(0);
 
@@ -837,7 +1383,7 @@ impl Actor for Character {
             }
             // ZAP Damage zaps MP instead of HP
             DamageType::ZAP(_) => {
-                *self.mp.get_mut() -= effective_damage;
+                self.mp -= effective_damage;
             }
         }
 
@@ -846,58 +1392,89 @@ impl Actor for Character {
     }
 
     fn apply_directly(&mut self, val: &CharUnit) {
+        // Units can be restored up to (but not beyond) their maximum
+        let stats = self.calculate_game_stats();
         match val {
             CharUnit::HP(v) => {
-                self.hp += *v;
+                self.hp = self.hp.saturating_add(*v).min(stats.mhp);
             }
             CharUnit::MP(v) => {
-                *self.mp.get_mut() = *v;
+                self.mp = self.mp.saturating_add(*v).min(stats.mmp);
             }
             CharUnit::AP(v) => {
-                *self.ap.get_mut() = *v;
+                self.ap = self.ap.saturating_add(*v).min(stats.map);
             }
             CharUnit::VIT(v) => {
-                self.vit += *v;
+                self.vit = self.vit.saturating_add(*v).min(self.calculate_current_stats().max_vit());
             }
         }
     }
 
-    /// Adds a new effect to this character for a certain `effect_duration` in turns
-    fn apply_timed_effect(&mut self, effect: Box<dyn Effect>, effect_duration: i64) {
-        self.timed_effects.push((effect, effect_duration));
+    /// Adds a new effect to this character for a certain `effect_duration` in turns, following
+    /// the effect's stacking policy towards effects with the same identity
+    fn apply_timed_effect(&mut self, effect: Box<dyn Effect>, effect_duration: i64, source: Option<CharacterId>) {
+        let timed = TimedEffect::new(effect, effect_duration, source);
+        let identity = timed.identity();
+        self.invalidate_stats();
+        let mut same: Vec<&mut TimedEffect> = self.timed_effects.iter_mut()
+            .filter(|t| t.identity() == identity)
+            .collect();
 
+        match timed.effect.stacking() {
+            StackingPolicy::Refresh if !same.is_empty() => {
+                same[0].remaining = same[0].remaining.max(effect_duration);
+            }
+            StackingPolicy::Intensity(max_stacks) if !same.is_empty() => {
+                for existing in same.iter_mut() {
+                    existing.remaining = existing.remaining.max(effect_duration);
+                }
+                if same.len() < max_stacks {
+                    self.timed_effects.push(timed);
+                }
+            }
+            StackingPolicy::Strongest if !same.is_empty() => {
+                let strength = timed.effect.strength();
+                if strength > same[0].effect.strength() {
+                    *same[0] = timed;
+                } else if strength == same[0].effect.strength() {
+                    same[0].remaining = same[0].remaining.max(effect_duration);
+                }
+            }
+            // Independent effects, and the first effect of each identity
+            _ => self.timed_effects.push(timed),
+        }
     }
 
     /// Called during combat action resolution. Called for every action played during combat,
     /// allowing characters to respond to actions as per their ability.
     ///
-    /// As responding to an action incurs a unique cost (AP), this function also includes logic
-    /// to ensure in-turn cost is paid, making use of the Interal Mutability Pattern through
-    /// the Character's special `RefCell` parameters.
-    fn respond_to_action(&self, context: &dyn WorldContext, action: &Action, reactions: &mut Vec<Action>) {
-        if *self.ap.borrow() < 0 {
-            // Once AP is below 0, character can no longer react
-            return;
-        }
+    /// As responding to an action incurs a unique cost (AP), each reaction's cost is shared by
+    /// its actions, and paid once. Costs still pending on the `ledger` are taken into account, so a
+    /// character never reacts beyond their means.
+    fn respond_to_action(&self, context: &dyn WorldContext, action: &Action, ledger: &CostLedger, reactions: &mut Vec<Action>) {
+        // Reactions made earlier on in this call aren't on the ledger yet
+        let mut pending = ledger.pending(self.id);
         for reaction in self.all_current_reactions() {
-            let mp_cost = reaction.mp_cost();
-            if mp_cost > 0 && *self.mp.borrow() < mp_cost {
+            if self.ap - pending.ap < 0 {
+                // Once AP is below 0, character can no longer react
+                return;
+            }
+            let cost = ActionCost { ap: reaction.ap_cost(), mp: reaction.mp_cost() };
+            if cost.mp > 0 && self.mp - pending.mp < cost.mp {
                 // This reaction costs is MP we cannot afford. Cancel this reaction
                 continue;
             }
-            if let Some(react) = reaction.react(&self, action, context) {
+            if let Some(mut react) = reaction.react(&self, action, context) {
                 // Reaction has yielded an actual response. We want to progress with this!
-                // 1. Pay the AP cost for this reaction
-                // This may put the character below 0 which will stop them from reacting until
-                // they recovered
-                *self.ap.borrow_mut() -= reaction.ap_cost();
-
-                // 3. Pay the MP cost (if applicable)
-                if mp_cost > 0 {
-                    *self.mp.borrow_mut() -= mp_cost;
+                // Its cost may put the character below 0 AP, which will stop them from reacting
+                // until they recovered
+                if !react.is_empty() {
+                    Action::share_cost(&mut react, cost);
+                    pending = pending + cost;
+                }
+                for action in react.iter_mut() {
+                    action.set_reaction(reaction.name());
                 }
-
-                // 2. Add Reactions that have been built
                 reactions.extend(react)
             }
         }
@@ -910,23 +1487,34 @@ impl InfoGrid for Character {
         // ~~~~~~~~~~~~ INDIVIDUAL STAT PRINTS ~~~~~~~~~~~~
         // HP Bar
         let print_hp = |c: &Character, f| {
-            text_util::render_bar_with_num("HP:", w, c.hp(), c.calculate_current_stats().max_hp(), BarStyle::DoubleLines, Some(('<', '>')), Some((&f, "hp", "Hitpoint Infos".to_string())))
+            text_util::render_bar_with_num("HP:", w, c.hp(), c.calculate_game_stats().mhp(), BarStyle::DoubleLines, Some(('<', '>')), Some((&f, "hp", "Hitpoint Infos".to_string())))
         };
 
         // Name
         let print_charname = |c: &Character, f| c.name().format_line(w, formatting);
         // MP Bar
-        let print_mp = |c: &Character, f| text_util::render_bar_with_num("MP:", w, c.mp(), c.calculate_current_stats().max_mp(), BarStyle::TwoChars('>', '-'), None, Some((&f, "mp", "MP Infos".to_string())));
+        let print_mp = |c: &Character, f| text_util::render_bar_with_num("MP:", w, c.mp(), c.calculate_game_stats().mmp(), BarStyle::TwoChars('>', '-'), None, Some((&f, "mp", "MP Infos".to_string())));
         // AP Bar
-        let print_ap = |c: &Character, f| text_util::render_bar_with_num("AP:", w, c.ap(), c.calculate_current_stats().max_ap(), BarStyle::TwoChars('!', '.'), None, Some((&f, "ap", "AP Infos".to_string())));
+        let print_ap = |c: &Character, f| text_util::render_bar_with_num("AP:", w, c.ap(), c.calculate_game_stats().map(), BarStyle::TwoChars('!', '.'), None, Some((&f, "ap", "AP Infos".to_string())));
+        // Set Progress
+        let print_sets = |c: &Character, f: TextFormatting| f.enrich_text(c.build_set_description(w), "set", None);
 
         // A progressive list of strategies to use when displaying the character line-by-line
-        let oneliner_strategies: Vec<(&dyn Fn(&Self, TextFormatting) -> String, &str)> = vec![
+        let mut oneliner_strategies: Vec<(&dyn Fn(&Self, TextFormatting) -> String, &str)> = vec![
             (&print_charname, "name"),
             (&print_hp, "hp"),
             (&print_mp, "mp"),
             (&print_ap, "ap"),
         ];
+        // Set progress is only worth a line for characters wearing set pieces
+        if !self.equipped_sets().is_empty() {
+            oneliner_strategies.push((&print_sets, "set"));
+        }
+        // ... as are timed effects for affected characters
+        let print_effects = |c: &Character, f: TextFormatting| f.enrich_text(c.build_effect_description(w), "effect", None);
+        if !self.timed_effects.is_empty() {
+            oneliner_strategies.push((&print_effects, "effect"));
+        }
 
         // Build Vector Lines
         let mut lines = Vec::new();
@@ -965,6 +1553,7 @@ mod tests {
     /// Basic Testcharacter to use
     fn test_character() -> Character {
         Character {
+            id: CharacterId::generate(),
             name: String::from("Lindtbert"),
             owner: None,
             party: "Superparty".to_string(),
@@ -977,15 +1566,100 @@ mod tests {
                 cha: 6,
             },
             equipment: vec![],
+            inventory: Inventory::with_capacity(5),
+            spellbook: vec![],
+            move_state: MoveState::new(),
+            row: Row::Front,
+            summoned_by: None,
             timed_effects: vec![],
+            ambient_effects: vec![],
             hp: 120,
-            mp: RefCell::new(50),
-            ap: RefCell::new(15),
+            mp: 50,
+            ap: 15,
+            mve: 5,
             vit: 200,
-            game_stats: None,
+            game_stats: Cell::new(None),
         }
     }
 
+    #[test]
+    fn test_set_bonus() {
+        use std::sync::Arc;
+        use crate::effects::StatAdditive;
+        use crate::equipment::{EquipmentType, SetBonus};
+
+        let mut character = test_character();
+        let str_pre = character.calculate_current_stats().str;
+
+        let mut stormguard = ItemSet::new("Stormguard".to_string(), 4);
+        let mut two_piece = SetBonus::new(2);
+        two_piece.add_passive_effect(Box::new(StatAdditive(CharStat::STR(5))));
+        stormguard.add_bonus(two_piece);
+        let stormguard = Arc::new(stormguard);
+
+        let build_piece = |name: &str, eq_type: EquipmentType| {
+            let mut eq = Equipment::new(name.to_string(), eq_type, Stats {
+                dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+            });
+            eq.set_item_set(Arc::clone(&stormguard));
+            eq
+        };
+
+        // One piece alone does not activate the bonus
+        character.equip(build_piece("Stormguard Helm", EquipmentType::Head)).unwrap();
+        assert_eq!(character.calculate_current_stats().str, str_pre);
+
+        // Two pieces do
+        character.equip(build_piece("Stormguard Plate", EquipmentType::Chest)).unwrap();
+        assert_eq!(character.calculate_current_stats().str, str_pre + 5);
+
+        let lines = character.display(20, 5, TextFormatting::Plain);
+        assert_eq!(lines[4].trim_end(), "Stormguard 2/4");
+    }
+
+    #[test]
+    fn test_stats_cache() {
+        use crate::effects::{StatAdditive, StatPercentage};
+        use crate::equipment::EquipmentType;
+
+        let _verification = verify_stats_cache();
+        let mut character = test_character();
+        let mhp = character.calculate_game_stats().mhp();
+
+        // Every change to what game stats are calculated from drops the cache
+        let mut armour = Equipment::new("Plate".to_string(), EquipmentType::Chest, Stats {
+            str: 0, dex: 0, grt: 0, wil: 0, int: 0, cha: 0,
+        });
+        armour.add_passive_effect(Box::new(StatPercentage(CharStat::MHP(50))));
+        character.equip(armour).unwrap();
+        assert_eq!(character.calculate_game_stats().mhp(), (mhp as f64 * 1.5).floor() as i64);
+
+        character.apply_timed_effect(Box::new(StatAdditive(CharStat::GRT(5))), 1, None);
+        assert!(character.calculate_game_stats().mhp() > (mhp as f64 * 1.5).floor() as i64);
+        character.post_turn();
+        assert_eq!(character.calculate_game_stats().mhp(), (mhp as f64 * 1.5).floor() as i64);
+
+        character.unequip(0).unwrap();
+        character.allocate_stat(CharStat::WIL(1)).unwrap();
+        assert_eq!(character.calculate_game_stats().mhp(), mhp + 24);
+        assert!(character.allocate_stat(CharStat::MHP(1)).is_err());
+
+        // Wear only drops the cache once the equipment breaks
+        let mut ring = Equipment::new("Ring".to_string(), EquipmentType::Ring, Stats {
+            str: 0, dex: 0, grt: 0, wil: 0, int: 0, cha: 0,
+        });
+        ring.add_passive_effect(Box::new(StatPercentage(CharStat::MHP(50))));
+        ring.set_max_durability(2);
+        let index = character.iter_equipment().count();
+        character.equip(ring).unwrap();
+        character.calculate_game_stats();
+        character.wear_equipment(index, 1);
+        assert!(character.game_stats.get().is_some());
+        character.wear_equipment(index, 1);
+        assert!(character.game_stats.get().is_none());
+        assert_eq!(character.calculate_game_stats().mhp(), mhp + 24);
+    }
+
     #[test]
     fn it_works() {
         let character = test_character();
@@ -1,12 +1,24 @@
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
+use std::ops::Add;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::io::stdout;
-use crate::characters::{CharUnit, Character, Stats};
-use crate::effects::Effect;
+use crate::battlefield::{is_covered, Row, COVER_FACTOR};
+use crate::characters::{CharUnit, Character, CharacterId, CharacterRef, Stats};
+use crate::effects::{Effect, EffectSelector};
+use crate::environment::Environment;
+use crate::equipment::Equipment;
+use crate::escape::{flee_contest, CombatOutcome, EscapePolicy, SurrenderConsequence};
+use crate::inventory::{Inventory, Item};
+use crate::loot::LootGenerator;
 use crate::player::PlayerInput;
+use crate::random::SeededRng;
+use crate::summons::Summoning;
 use crate::world::{TurnLogger, WorldContext};
 use crate::mov::{Maneuver, Counter};
 use crate::text::{InfoGrid, InfoLine, MakesWords, TextFormatting};
+use crate::threat::{ThreatTable, HEAL_THREAT_FACTOR, TAUNT_THREAT};
 
 ///
 /// Simulates combat between Characters. Each character's **party** affiliation defines the Teams,
@@ -16,24 +28,397 @@ pub struct Combat {
     /// Participants are of different **parties**, as defined by each character's `party` field.
     /// Combat continues until one party remains.
     participants: Vec<Character>,
+    /// Position of each participant in `participants`, by ID
+    index: HashMap<CharacterId, usize>,
+    /// IDs of all participants that have been defeated during this combat
+    defeated: HashSet<CharacterId>,
+    /// If set, every defeated participant drops loot generated by this generator
+    loot_generator: Option<LootGenerator>,
+    /// Loot dropped by defeated participants that has not been claimed by a winning party yet
+    dropped_loot: Vec<Equipment>,
+    /// Each party's stash, holding the loot the party won
+    stashes: HashMap<String, Inventory>,
+    /// Threat between all participants, informing their target selection
+    threat: ThreatTable,
+    /// Characters that left combat by fleeing, surrendering or retreating
+    holding: Vec<Character>,
+    /// How this combat ended, once it did
+    outcome: Option<CombatOutcome>,
+    /// Source of all randomness in this combat (e.g. flee contests)
+    rng: SeededRng,
+    /// Each party's policy on escaping combat on their own
+    escape_policies: BTreeMap<String, EscapePolicy>,
+    /// What surrendering parties lose to the party they surrender to
+    surrender_consequences: Vec<SurrenderConsequence>,
+    /// Number of turns processed so far
+    turn: u64,
+    /// IDs of all summoned participants, with the number of turns they have left (if limited).
+    /// Summoned participants drop no loot.
+    summoned: BTreeMap<CharacterId, Option<i64>>,
+    /// Characters arriving as reinforcements at the start of the given turn
+    reinforcements: Vec<(u64, Character)>,
+    /// The environment this combat takes place in, acting after all participants every turn
+    environment: Environment,
 }
 
 impl Combat {
 
     pub fn from_participants(participants: Vec<Character>) -> Self {
-        Combat { participants }
+        let mut combat = Combat {
+            participants: vec![],
+            index: HashMap::new(),
+            defeated: HashSet::new(),
+            loot_generator: None,
+            dropped_loot: vec![],
+            stashes: HashMap::new(),
+            threat: ThreatTable::new(),
+            holding: vec![],
+            outcome: None,
+            // Unless seeded, every combat rolls differently
+            rng: SeededRng::from_entropy(),
+            escape_policies: BTreeMap::new(),
+            // By default, surrendering costs half of all currency
+            surrender_consequences: vec![SurrenderConsequence::ForfeitCurrency(0.5)],
+            turn: 0,
+            summoned: BTreeMap::new(),
+            reinforcements: vec![],
+            // By default, nothing about the surroundings matters
+            environment: Environment::new("Open Field".to_string()),
+        };
+        for character in participants {
+            combat.join(character);
+        }
+        combat
+    }
+
+    /// Sets the environment this combat takes place in, affecting all participants right away
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+        for character in self.participants.iter_mut() {
+            character.set_ambient_effects(self.environment.global_effects());
+        }
+    }
+
+    /// Schedules `character` to join this combat at the start of the given `turn` (counting
+    /// from 1), in time to act in that turn
+    pub fn schedule_reinforcement(&mut self, turn: u64, character: Character) {
+        self.reinforcements.push((turn, character));
+    }
+
+    /// Adds `newcomer` to the participants. Newcomers whose name is already taken are numbered
+    /// (e.g. "Imp 2"), so they can be told apart in narration. Returns the newcomer's ID.
+    fn join(&mut self, mut newcomer: Character) -> CharacterId {
+        let base = newcomer.name().clone();
+        let mut name = base.clone();
+        let mut number = 1;
+        while self.get_character(&name).is_some() || self.holding.iter().any(|c| *c.name() == name) {
+            number += 1;
+            name = format!("{} {}", base, number);
+        }
+
+        newcomer.set_name(name);
+        newcomer.set_ambient_effects(self.environment.global_effects());
+        // Newcomers are appended, so they go last among participants with equal MOB
+        let id = newcomer.id();
+        self.index.insert(id, self.participants.len());
+        self.participants.push(newcomer);
+        id
     }
 
-    /// Builds a turn order, i.e. a Vector that orders all participants MOB stat
-    fn build_turn_order(&self) -> Vec<String> {
-        // Build a char list with Name - MOB
-        let mut char_list: Vec<(&String, i64)>  = self.participants.iter()
-            .map(|x| (x.name(), x.calculate_current_stats().mobility() ))
+    /// Rebuilds the index of participant positions, e.g. after a participant left
+    fn reindex(&mut self) {
+        self.index = self.participants.iter().enumerate().map(|(i, c)| (c.id(), i)).collect();
+    }
+
+    /// Lets all summoned participants that took part in the round with the given `turn_order`
+    /// age by one turn. Those whose time is up leave combat.
+    fn age_summons(&mut self, turn_order: &Vec<CharacterId>) {
+        for id in turn_order {
+            if let Some(Some(turns)) = self.summoned.get_mut(id) {
+                *turns -= 1;
+            }
+        }
+
+        let expired: Vec<CharacterId> = self.summoned.iter()
+            .filter(|(_, turns)| turns.map_or(false, |t| t <= 0))
+            .map(|(id, _)| *id)
             .collect();
+        for id in expired {
+            self.summoned.remove(&id);
+            self.leave_combat(id);
+        }
+    }
+
+    /// Seeds all random rolls of this combat, so they can be reproduced
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SeededRng::from_seed(seed);
+    }
+
+    /// Makes the party named `party` escape combat on their own, as described by `policy`
+    pub fn set_escape_policy(&mut self, party: String, policy: EscapePolicy) {
+        self.escape_policies.insert(party, policy);
+    }
+
+    pub fn set_surrender_consequences(&mut self, consequences: Vec<SurrenderConsequence>) {
+        self.surrender_consequences = consequences;
+    }
 
-        // Sort list by Mobility, map to String list only.
-        char_list.sort_by_key(|(n, m)| *m);
-        char_list.iter().map(|(n, m)| n.clone().clone()).collect()
+    /// Returns the number of turns processed so far
+    pub fn get_turn(&self) -> u64 {
+        self.turn
+    }
+
+    /// Returns how this combat ended, or `None` while it's still going on
+    pub fn get_outcome(&self) -> Option<&CombatOutcome> {
+        self.outcome.as_ref()
+    }
+
+    /// Iterates all characters that left combat without being defeated
+    pub fn iter_holding(&self) -> core::slice::Iter<Character> {
+        self.holding.iter()
+    }
+
+    /// Takes all characters that left combat out of the holding area, e.g. to hand them to their
+    /// next context
+    pub fn take_holding(&mut self) -> Vec<Character> {
+        self.holding.drain(..).collect()
+    }
+
+    /// Takes the participant with the given `id` out of combat
+    fn leave_combat(&mut self, id: CharacterId) -> Option<Character> {
+        let i = *self.index.get(&id)?;
+        let mut character = self.participants.remove(i);
+        self.reindex();
+        // Leaving combat means leaving its environment
+        character.set_ambient_effects(vec![]);
+        Some(character)
+    }
+
+    /// Ends this combat with the given `outcome`, if only one party remains standing
+    fn end_with(&mut self, outcome: CombatOutcome) {
+        if self.outcome.is_none() && self.remaining_parties().len() <= 1 {
+            self.outcome = Some(outcome);
+        }
+    }
+
+    /// Lists the IDs of all standing participants of the party named `party`
+    fn standing_members(&self, party: &String) -> Vec<CharacterId> {
+        self.participants.iter()
+            .filter(|c| c.party_check(party) && !c.is_defeated())
+            .map(|c| c.id())
+            .collect()
+    }
+
+    /// The character with the given `id` retreats from combat into the holding area
+    pub fn retreat(&mut self, id: CharacterId) -> Result<String, String> {
+        let name = match self.get_character_by_id(id) {
+            Some(c) if !c.is_defeated() => c.name().clone(),
+            Some(c) => return Err(format!("{} can't retreat.", c.name())),
+            None => return Err(format!("{} isn't part of this combat.", id)),
+        };
+
+        let character = self.leave_combat(id).unwrap();
+        let party = character.party().clone();
+        self.holding.push(character);
+
+        if self.standing_members(&party).is_empty() {
+            self.end_with(CombatOutcome::Retreated(party));
+            self.process_defeats();
+        }
+        Ok(format!("{} retreated.", name))
+    }
+
+    /// The party named `party` tries to flee from combat. They succeed if their slowest member
+    /// wins a MOB contest against the fastest opponent.
+    pub fn flee(&mut self, party: &String) -> Result<String, String> {
+        let members = self.standing_members(party);
+        if members.is_empty() {
+            return Err(format!("Nobody of {} left to flee.", party));
+        }
+
+        let fled = {
+            let fleeing: Vec<&Character> = self.participants.iter()
+                .filter(|c| members.contains(&c.id()))
+                .collect();
+            let chasers: Vec<&Character> = self.participants.iter()
+                .filter(|c| !c.party_check(party) && !c.is_defeated())
+                .collect();
+            flee_contest(&fleeing, &chasers, &mut self.rng)
+        };
+        if !fled {
+            return Ok(format!("{} failed to flee.", party));
+        }
+
+        for id in members {
+            let character = self.leave_combat(id).unwrap();
+            self.holding.push(character);
+        }
+        self.end_with(CombatOutcome::Fled(party.clone()));
+        self.process_defeats();
+
+        Ok(format!("{} fled.", party))
+    }
+
+    /// The party named `party` surrenders, leaving combat into the holding area. Surrender
+    /// consequences go to the stash of the remaining opponent party. While several opponent
+    /// parties remain, there's nobody to surrender to, so there are no consequences.
+    pub fn surrender(&mut self, party: &String) -> Result<String, String> {
+        let members = self.standing_members(party);
+        if members.is_empty() {
+            return Err(format!("Nobody of {} left to surrender.", party));
+        }
+        let opponents: Vec<&String> = self.remaining_parties().into_iter().filter(|p| *p != party).collect();
+        let winner = match opponents[..] {
+            [winner] => Some(winner.clone()),
+            _ => None,
+        };
+
+        for id in members {
+            let mut character = self.leave_combat(id).unwrap();
+
+            if let Some(winner) = &winner {
+                let stash = self.stashes.entry(winner.clone()).or_insert_with(Inventory::unlimited);
+                for consequence in self.surrender_consequences.iter() {
+                    match consequence {
+                        SurrenderConsequence::ForfeitCurrency(share) => {
+                            let amount = (character.inventory().currency() as f64 * share).floor() as i64;
+                            if character.inventory_mut().take_currency(amount).is_ok() {
+                                stash.add_currency(amount).ok();
+                            }
+                        }
+                        SurrenderConsequence::ForfeitItems => stash.absorb(character.inventory_mut()),
+                        SurrenderConsequence::Exhaustion => {
+                            character.apply_directly(&CharUnit::AP(-character.ap()));
+                            character.apply_directly(&CharUnit::MP(-character.mp()));
+                        }
+                    }
+                }
+            }
+
+            self.holding.push(character);
+        }
+        self.end_with(CombatOutcome::Surrendered(party.clone()));
+        self.process_defeats();
+
+        Ok(format!("{} surrendered.", party))
+    }
+
+    /// Lets all parties with an `EscapePolicy` escape combat as their policy dictates
+    fn apply_escape_policies(&mut self) {
+        let policies: Vec<(String, EscapePolicy)> = self.escape_policies.iter()
+            .map(|(p, policy)| (p.clone(), *policy))
+            .collect();
+
+        for (party, policy) in policies {
+            let members = self.standing_members(&party);
+            if members.is_empty() || self.outcome.is_some() {
+                continue;
+            }
+
+            // Single characters retreat first
+            if let Some(threshold) = policy.retreat_below {
+                for id in members {
+                    if self.get_character_by_id(id).unwrap().hp_to_max_hp_ratio() < threshold {
+                        self.retreat(id).ok();
+                    }
+                }
+            }
+
+            // The remaining party decides based on their average condition
+            let ratios: Vec<f64> = self.standing_members(&party).iter()
+                .map(|id| self.get_character_by_id(*id).unwrap().hp_to_max_hp_ratio())
+                .collect();
+            if ratios.is_empty() {
+                continue;
+            }
+            let average = ratios.iter().sum::<f64>() / ratios.len() as f64;
+
+            if policy.surrender_below.map_or(false, |t| average < t) {
+                self.surrender(&party).ok();
+            } else if policy.flee_below.map_or(false, |t| average < t) {
+                self.flee(&party).ok();
+            }
+        }
+    }
+
+    pub fn get_threat_table(&self) -> &ThreatTable {
+        &self.threat
+    }
+
+    /// Makes defeated participants drop loot from the given `loot_generator`
+    pub fn set_loot_generator(&mut self, loot_generator: LootGenerator) {
+        self.loot_generator = Some(loot_generator);
+    }
+
+    /// Returns all loot the party named `party` won in this combat so far
+    pub fn get_stash(&self, party: &str) -> Option<&Inventory> {
+        self.stashes.get(party)
+    }
+
+    /// Takes the stash of the party named `party` out of this combat, e.g. to hand the loot to
+    /// the party's next context
+    pub fn take_stash(&mut self, party: &str) -> Option<Inventory> {
+        self.stashes.remove(party)
+    }
+
+    /// Lists the names of all parties with at least one participant still standing
+    pub fn remaining_parties(&self) -> Vec<&String> {
+        self.participants.iter().filter(|c| !c.is_defeated()).fold(vec![], |mut acc, c| {
+            if !acc.contains(&c.party()) {
+                acc.push(c.party());
+            }
+            acc
+        })
+    }
+
+    /// Checks for newly defeated participants and lets them drop loot. Once only one party
+    /// remains, that party claims all dropped loot into its stash.
+    fn process_defeats(&mut self) {
+        for character in self.participants.iter().filter(|c| c.is_defeated()) {
+            if !self.defeated.insert(character.id()) {
+                continue;
+            }
+
+            // Summoned participants vanish without a trace
+            if self.summoned.contains_key(&character.id()) {
+                continue;
+            }
+
+            if let Some(loot) = self.loot_generator.as_mut().and_then(|g| g.generate()) {
+                self.dropped_loot.push(loot);
+            }
+        }
+
+        // Once only one party remains, it wins and collects all dropped loot
+        let winner = match self.remaining_parties()[..] {
+            [winner] => winner.clone(),
+            _ => return,
+        };
+        if self.outcome.is_none() {
+            self.outcome = Some(CombatOutcome::Victory(winner.clone()));
+        }
+        if !self.dropped_loot.is_empty() {
+            let stash = self.stashes.entry(winner).or_insert_with(Inventory::unlimited);
+            for loot in self.dropped_loot.drain(..) {
+                // Stashes have no slot limit
+                stash.add(Item::Equipment(loot)).ok();
+            }
+        }
+    }
+
+    /// Builds a turn order, i.e. a Vector that orders all participants MOB stat.
+    /// Defeated participants are no longer part of the turn order. Participants with equal MOB
+    /// keep the order in which they joined combat.
+    fn build_turn_order(&self) -> Vec<CharacterId> {
+        // Build a char list with ID - MOB
+        let mut char_list: Vec<(CharacterId, i64)>  = self.participants.iter()
+            .filter(|x| !x.is_defeated())
+            .map(|x| (x.id(), x.calculate_game_stats().mob() ))
+            .collect();
+
+        // Sort list by Mobility, map to ID list only.
+        char_list.sort_by_key(|(_, m)| *m);
+        char_list.iter().map(|(n, _)| *n).collect()
     }
 
 }
@@ -43,24 +428,56 @@ impl WorldContext for Combat {
 
     fn process_turn(&mut self, mut logger: Option<&mut dyn TurnLogger>) -> Result<(), String> {
 
+        // Parties may want to get out before things get worse
+        self.apply_escape_policies();
+
+        // Once combat ended, no more turns are processed
+        if self.outcome.is_some() {
+            return Ok(());
+        }
+        self.turn += 1;
+
+        // Reinforcements arrive before the turn order is built, so they get to act right away
+        let turn = self.turn;
+        let (arriving, pending): (Vec<_>, Vec<_>) = self.reinforcements.drain(..)
+            .partition(|(t, _)| *t <= turn);
+        self.reinforcements = pending;
+        for (_, character) in arriving {
+            self.join(character);
+        }
+
         // Build Turn Order for this round
 
-        let mut turn_order: Vec<String> = self.build_turn_order();
+        let turn_order: Vec<CharacterId> = self.build_turn_order();
 
         // Before Maneuvers of the round are started, run `pre_turn`
-        for char in &mut turn_order {
-            let char = self.get_character_mut(char).unwrap();
+        for char in &turn_order {
+            let char = self.get_character_by_id_mut(*char).unwrap();
             char.pre_turn();
         }
 
         // In turn order, process
         for char in &turn_order {
+            // Characters defeated earlier this turn no longer get to act
+            if self.get_character_by_id(*char).unwrap().is_defeated() {
+                continue;
+            }
+
             // Build a new stack to process this maneuver
             let mut maneuver_stack = ActionStack::new();
 
-            if let char = self.get_character(&char).unwrap() {
-                let next_move = char.next_move();
-                let actions = next_move.execute(char, self);
+            if let Some(character) = self.get_character_by_id(*char) {
+                // The selected move borrows from its character, so it's dropped right after
+                // execution
+                let (mut actions, name, cooldown) = {
+                    let maneuver = character.next_move(self);
+                    (maneuver.execute(character, self), maneuver.name(), maneuver.cooldown())
+                };
+
+                // Used moves go on cooldown once they resolve, together with paying their cost
+                if cooldown > 0 {
+                    Action::share_cooldown(&mut actions, name, cooldown);
+                }
 
                 // Initalizes the Move Stack using the action provided by the move
                 maneuver_stack.build(actions, self);
@@ -84,19 +501,46 @@ impl WorldContext for Combat {
             maneuver_stack.resolve(self);
         }
 
+        // The environment acts last in every round
+        let mut rng = self.rng.clone();
+        let actions = self.environment.act(self.turn, self, &mut rng);
+        self.rng = rng;
+        if !actions.is_empty() {
+            let mut environment_stack = ActionStack::new();
+            environment_stack.build(actions, self);
+            if let Some(l) = logger {
+                l.maneuver_stack(&environment_stack);
+            }
+            environment_stack.resolve(self);
+        }
+
         // After Maneuvers of the round are finished, run `post_turn`
-        for char in &mut turn_order {
-            let char = self.get_character_mut(char).unwrap();
+        for char in &turn_order {
+            let char = self.get_character_by_id_mut(*char).unwrap();
             char.post_turn();
         }
 
+        // Summons joining this turn haven't been part of the turn order, so they don't age yet
+        self.age_summons(&turn_order);
+
+        // Handle anyone who has been defeated this turn
+        self.process_defeats();
 
         // Finished turn
         Ok(())
     }
 
     fn process_player_input(&mut self, input: &PlayerInput) -> Result<String, String> {
-        todo!()
+        if self.outcome.is_some() {
+            return Err("Combat is over.".to_string());
+        }
+
+        match input {
+            PlayerInput::Flee { party } => self.flee(party),
+            PlayerInput::Surrender { party } => self.surrender(party),
+            PlayerInput::Retreat { character } => self.retreat(*character),
+            _ => Err("Not possible during combat.".to_string()),
+        }
     }
 
     fn iter_characters(&self) -> core::slice::Iter<Character> {
@@ -107,24 +551,89 @@ impl WorldContext for Combat {
         self.participants.iter_mut()
     }
 
-    fn request_reactions(&mut self, action: &Action) -> Vec<Action> {
+    /// Participants are looked up by their position in `participants`, without scanning
+    fn get_character_by_id(&self, id: CharacterId) -> Option<&Character> {
+        self.index.get(&id).map(|i| &self.participants[*i])
+    }
+
+    fn get_character_by_id_mut(&mut self, id: CharacterId) -> Option<&mut Character> {
+        self.index.get(&id).map(|i| &mut self.participants[*i])
+    }
+
+    fn request_reactions(&mut self, action: &Action, ledger: &CostLedger) -> Vec<Action> {
         // Return vector
         let mut reactions = Vec::new();
-        let mut turn_order: Vec<String> = self.build_turn_order();
+        let mut turn_order: Vec<CharacterId> = self.build_turn_order();
         // Reactions happen in reverse turn order (most agile char gets to decide last
         turn_order.reverse();
 
 
-        // Mutable iteration (characters usually discount AP for reactions)
-        // -> Fill up `reactions`, forwarding internal updates to each `Actor`s implementation
+        // Every character checks their reactions against what they can still afford
+        // -> Fill up `reactions`, each carrying its cost
 
         for character in &turn_order {
-            let character = self.get_character(character).unwrap();
-            character.respond_to_action(self, action, &mut reactions);
+            let character = self.get_character_by_id(*character).unwrap();
+            character.respond_to_action(self, action, ledger, &mut reactions);
         }
 
         reactions
     }
+
+    /// Tracks the threat generated by every resolved action
+    fn record_action(&mut self, action: &Action) {
+        let (sources, targets) = match (action.get_source(), action.get_target()) {
+            (EntityPointer::Character(s), EntityPointer::Character(t)) => (s, t),
+            _ => return,
+        };
+
+        for source in sources.iter().map(|s| s.id()) {
+            match action.get_effect() {
+                // Damage threatens its targets
+                ActionEffect::Attack(_) => for target in targets.iter().map(|t| t.id()) {
+                    let amount = action.damage_for(target).map_or(0, |d| d.amount());
+                    self.threat.add_threat(source, target, amount);
+                }
+                // Healing threatens all of the healer's opponents
+                ActionEffect::Heal(CharUnit::HP(amount)) if *amount > 0 => {
+                    let party = match self.get_character_by_id(source) {
+                        Some(c) => c.party().clone(),
+                        None => continue,
+                    };
+                    let opponents: Vec<CharacterId> = self.participants.iter()
+                        .filter(|c| !c.party_check(&party))
+                        .map(|c| c.id())
+                        .collect();
+                    for opponent in opponents {
+                        self.threat.add_threat(source, opponent, (*amount as f64 * HEAL_THREAT_FACTOR) as i64);
+                    }
+                }
+                // Taunts threaten their targets a lot
+                ActionEffect::GiveTimedEffect(effect, _) if effect.taunted_by().is_some() => for target in targets {
+                    self.threat.add_threat(source, target.id(), TAUNT_THREAT);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn get_threat(&self, source: CharacterId, target: CharacterId) -> i64 {
+        self.threat.get_threat(source, target)
+    }
+
+    fn get_environment(&self) -> Option<&Environment> {
+        Some(&self.environment)
+    }
+
+    /// Newcomers join as summoned participants and act from the next turn on
+    fn add_participant(&mut self, newcomer: Character, lifetime: Option<i64>) -> Result<CharacterId, String> {
+        if self.outcome.is_some() {
+            return Err(format!("{} arrived too late, combat is over.", newcomer.name()));
+        }
+
+        let id = self.join(newcomer);
+        self.summoned.insert(id, lifetime);
+        Ok(id)
+    }
 }
 
 
@@ -142,6 +651,27 @@ pub struct Action {
     /// Will be set when the action is added to the stack.
     stack_target: Option<EntityPointer>,
 
+    /// Position of the source's equipment used to perform this action (e.g. a weapon), if any.
+    /// Equipment wears down as its actions resolve.
+    equipment: Option<usize>,
+
+    /// Name of the source's consumable used up by this action, if any.
+    consumable: Option<String>,
+
+    /// AP and MP the source pays once this action resolves (e.g. for spells).
+    cost: ActionCost,
+
+    /// Cost shared with the other actions of the same maneuver or reaction, if any (see
+    /// `Action::share_cost`)
+    shared_cost: Option<SharedCost>,
+
+    /// Name of the reaction this action stems from, if any
+    reaction: Option<String>,
+
+    /// Damage factors applying to individual targets of this action only (e.g. when one of
+    /// several targets counters), by target ID
+    target_adjustments: Vec<(CharacterId, f64)>,
+
 
 
 }
@@ -157,6 +687,12 @@ impl Action {
             target,
             // The action starts out **without a position on the stack**.
             stack_target: None,
+            equipment: None,
+            consumable: None,
+            cost: ActionCost::default(),
+            shared_cost: None,
+            reaction: None,
+            target_adjustments: vec![],
         }
     }
 
@@ -172,10 +708,94 @@ impl Action {
         self.effect = effect;
     }
 
+    /// Marks this action as performed with the source's equipment at position `index`
+    pub fn set_equipment(&mut self, index: usize) {
+        self.equipment = Some(index);
+    }
+
+    /// Marks this action as using up the source's consumable named `consumable`
+    pub fn set_consumable(&mut self, consumable: String) {
+        self.consumable = Some(consumable);
+    }
+
+    /// Sets the MP the source pays once this action resolves
+    pub fn set_mp_cost(&mut self, mp_cost: i64) {
+        self.cost.mp = mp_cost;
+    }
+
+    pub fn get_cost(&self) -> ActionCost {
+        self.cost
+    }
+
+    /// Makes all `actions` share one `cost` (e.g. the MP of an area spell, or a reaction's AP).
+    /// Their source pays it once, as soon as the first of them resolves. It's only refunded if
+    /// all of them got canceled.
+    pub fn share_cost(actions: &mut [Action], cost: ActionCost) {
+        let shared = SharedCost {
+            id: NEXT_SHARED_COST.fetch_add(1, Ordering::Relaxed),
+            cost,
+            cooldown: None,
+        };
+        for action in actions {
+            action.shared_cost = Some(shared.clone());
+        }
+    }
+
+    /// Puts the source's move named `name` on cooldown for `turns` turns once the first of
+    /// `actions` resolves, along with paying the cost they share (if any)
+    pub fn share_cooldown(actions: &mut [Action], name: String, turns: i64) {
+        if actions.first().is_some_and(|a| a.shared_cost.is_none()) {
+            Action::share_cost(actions, ActionCost::default());
+        }
+        for action in actions {
+            if let Some(shared) = &mut action.shared_cost {
+                shared.cooldown = Some((name.clone(), turns));
+            }
+        }
+    }
+
+    /// Returns the cost this action shares with others, if any
+    pub fn get_shared_cost(&self) -> Option<ActionCost> {
+        self.shared_cost.as_ref().map(|s| s.cost)
+    }
+
+    /// Marks this action as part of the reaction named `reaction`
+    pub fn set_reaction(&mut self, reaction: String) {
+        self.reaction = Some(reaction);
+    }
+
+    /// Returns the name of the reaction this action stems from, if it's a reaction at all
+    pub fn get_reaction(&self) -> Option<&String> {
+        self.reaction.as_ref()
+    }
+
     pub fn get_target(&self) -> &EntityPointer {
         &self.target
     }
 
+    /// Adjusts the damage of this action multiplicatively by `factor` for the target character
+    /// with the given `id` only
+    pub fn adjust_damage_for(&mut self, id: CharacterId, factor: f64) {
+        self.target_adjustments.push((id, factor));
+    }
+
+    /// Calculates the damage the target character with the given `id` takes from this action.
+    /// Group attacks are split among their targets, and damage adjustments for individual
+    /// targets are applied. Returns `None` if this action doesn't attack that character.
+    pub fn damage_for(&self, id: CharacterId) -> Option<Damage> {
+        let damage = match (&self.effect, &self.target) {
+            (ActionEffect::Attack(d), EntityPointer::Character(targets)) if targets.iter().any(|t| t.id() == id) =>
+                d.split_for(targets.len()),
+            _ => return None,
+        };
+
+        let factor = self.target_adjustments.iter()
+            .filter(|(t, _)| *t == id)
+            .fold(1f64, |acc, (_, f)| acc * f);
+
+        Some(Damage(damage.0, (damage.1 as f64 * factor).floor() as i64))
+    }
+
     /// Updates the `target` of this action
     pub fn set_target(&mut self, target: EntityPointer) {
         self.target = target;
@@ -191,11 +811,20 @@ impl Action {
         }
     }
 
+    /// Returns the ID of the (first) character this action stems from, if it stems from
+    /// characters at all
+    fn source_id(&self) -> Option<CharacterId> {
+        match &self.source {
+            EntityPointer::Character(source) => source.first().map(|s| s.id()),
+            _ => None,
+        }
+    }
+
     /// Convenience function checks action target and returns `true` when this action is targetting
-    /// characters and the given `name` is a match.
-    pub fn targets_character(&self, name: &String) -> bool {
+    /// characters and the given `id` is a match.
+    pub fn targets_character(&self, id: CharacterId) -> bool {
         if let EntityPointer::Character(chars) = &self.target {
-            chars.iter().any(|s| s == name)
+            chars.iter().any(|s| s.id() == id)
         } else {
             false
         }
@@ -212,8 +841,31 @@ impl Action {
     fn resolve_on_chars(&self, context: &mut dyn WorldContext) -> Result<(), String> {
         match &self.target {
             EntityPointer::Character(c) => {
-                for character in context.find_characters_mut(&|char: &Character| c.contains(char.name())) {
-                    self.effect.apply_to_character(character)
+                for target in c.iter().map(|t| t.id()) {
+                    // Targets in the back row are covered by their front row
+                    let covered = match context.get_character_by_id(target) {
+                        Some(character) => is_covered(character, &*context),
+                        None => continue,
+                    };
+                    let character = context.get_character_by_id_mut(target).unwrap();
+
+                    // Attacks deal each target their share of the damage
+                    match self.damage_for(target) {
+                        Some(Damage(dt, amount)) if covered =>
+                            character.apply_damage(&Damage(dt, (amount as f64 * COVER_FACTOR).floor() as i64)),
+                        Some(damage) => character.apply_damage(&damage),
+                        None => self.effect.apply_to_character(character, self.source_id()),
+                    }
+                }
+
+                // Weapons wear down with every attack that resolves
+                if let (ActionEffect::Attack(_), Some(equipment), EntityPointer::Character(source))
+                    = (&self.effect, &self.equipment, &self.source) {
+                    for id in source.iter().map(|s| s.id()) {
+                        if let Some(character) = context.get_character_by_id_mut(id) {
+                            character.wear_equipment(*equipment, 1);
+                        }
+                    }
                 }
             }
             // Action's are expected
@@ -224,6 +876,109 @@ impl Action {
         Ok(())
     }
 
+    /// Makes the source character pay for this action: Uses up its consumable, AP and MP (if
+    /// any). Returns `false` if the source can't pay anymore (e.g. the consumable was used by an
+    /// earlier reaction or MP were spent elsewhere), in which case this action must not resolve.
+    fn pay_costs(&self, context: &mut dyn WorldContext) -> bool {
+        // Canceled actions never pay, which refunds e.g. the MP of canceled spells
+        if let ActionEffect::Canceled = self.effect {
+            return true;
+        }
+
+        self.charge(self.cost, self.consumable.as_ref(), context)
+    }
+
+    /// Makes the source character pay `cost` and use up its `consumable` (if any). Returns
+    /// `false` if the source can't pay.
+    fn charge(&self, cost: ActionCost, consumable: Option<&String>, context: &mut dyn WorldContext) -> bool {
+        let source = match &self.source {
+            EntityPointer::Character(source) => source,
+            _ => return true,
+        };
+
+        let mut paid = true;
+        for id in source.iter().map(|s| s.id()) {
+            let character = match context.get_character_by_id_mut(id) {
+                Some(character) => character,
+                None => continue,
+            };
+            // MP and AP are checked first, so no consumable is wasted on an unaffordable action.
+            // Reactions may overdraw AP, which are then missing from the next turn.
+            if (cost.mp > 0 && character.mp() < cost.mp)
+                || (cost.ap > 0 && character.ap() < cost.ap && self.reaction.is_none()) {
+                paid = false;
+                continue;
+            }
+            if let Some(consumable) = consumable {
+                if !character.consume_item(consumable) {
+                    paid = false;
+                    continue;
+                }
+            }
+            character.spend_mp(cost.mp);
+            character.spend_ap(cost.ap);
+        }
+
+        paid
+    }
+
+    /// Resolves this action on the environment of the world `context`, e.g. calling summoned
+    /// characters into it
+    fn resolve_on_environment(&self, context: &mut dyn WorldContext) -> Result<(), String> {
+        if let ActionEffect::Summon(summoning) = &self.effect {
+            let newcomer = match self.source.get_character(&*context) {
+                Some(summoner) => summoning.build(summoner),
+                None => return Err("Summoner not found".to_string()),
+            };
+            // Summons arriving after combat ended just fizzle
+            context.add_participant(newcomer, summoning.get_lifetime()).ok();
+        }
+        Ok(())
+    }
+
+    /// Resolves this action on the effects it targets, held by characters of the world
+    /// `context`. Stolen effects are handed to the (first) source character. Effects can't be
+    /// stolen by a thief that isn't part of the `context` (any more), so they stay where they are.
+    fn resolve_on_effects(&self, context: &mut dyn WorldContext) -> Result<(), String> {
+        let (holders, selector) = match &self.target {
+            EntityPointer::Effect(holder, selector) => match holder.as_ref() {
+                EntityPointer::Character(holders) => (holders, selector),
+                // As of now, only characters hold effects
+                _ => return Ok(()),
+            },
+            _ => return Err("Action doesn't target effects".to_string()),
+        };
+        if let ActionEffect::Steal = self.effect {
+            if self.source_id().and_then(|thief| context.get_character_by_id(thief)).is_none() {
+                return Ok(());
+            }
+        }
+
+        let mut stolen = vec![];
+        for id in holders.iter().map(|h| h.id()) {
+            let character = match context.get_character_by_id_mut(id) {
+                Some(character) => character,
+                None => continue,
+            };
+            match &self.effect {
+                ActionEffect::Dispel => { character.remove_timed_effects(selector, false); }
+                ActionEffect::Purge => { character.remove_timed_effects(selector, true); }
+                ActionEffect::Steal => stolen.extend(character.remove_timed_effects(selector, false)),
+                ActionEffect::ExtendEffect(turns) => character.extend_timed_effects(selector, *turns),
+                _ => {}
+            }
+        }
+
+        if let Some(thief) = self.source_id() {
+            if let Some(thief) = context.get_character_by_id_mut(thief) {
+                for timed in stolen {
+                    thief.apply_timed_effect(timed.effect, timed.remaining, timed.source);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn resolve_on_action(&self, action: &mut Action) -> Result<(), String> {
         self.effect.apply_to_action(action);
         Ok(())
@@ -275,6 +1030,12 @@ pub struct ActionStack {
     /// resolved in stack order, i.e. last in first out, giving the last reaction to what's
     /// happning the first right of resolution.
     stack: Vec<Action>,
+
+    /// Costs of all actions on this stack, reserved for their sources until the actions resolve
+    ledger: CostLedger,
+
+    /// Shared costs of the actions on this stack (see `Action::share_cost`), by their ID
+    shared_costs: HashMap<u64, SharedCostState>,
 }
 
 impl ActionStack {
@@ -282,9 +1043,16 @@ impl ActionStack {
     fn new() -> Self {
         ActionStack {
             stack: Vec::new(),
+            ledger: CostLedger::new(),
+            shared_costs: HashMap::new(),
         }
     }
 
+    /// Iterates all actions on this stack, in the order they were added
+    pub fn iter_actions(&self) -> core::slice::Iter<Action> {
+        self.stack.iter()
+    }
+
     /// "Narrates" the stack briefly by summarizing the each effect:
     fn brief_narration(&self) -> String {
         let mut narration = String::new();
@@ -316,14 +1084,24 @@ impl ActionStack {
         // (used for possible actions that directly target other actions rather than characters)
         action.set_stack_location(EntityPointer::Action(self.stack.len()));
 
+        // Reserve the action's cost, so its source doesn't spend it on reactions. Shared costs
+        // are reserved only once.
+        self.ledger.reserve(action.get_source(), action.get_cost());
+        if let Some(shared) = &action.shared_cost {
+            if !self.shared_costs.contains_key(&shared.id) {
+                self.ledger.reserve(action.get_source(), shared.cost);
+            }
+            self.shared_costs.entry(shared.id).or_default().unresolved += 1;
+        }
+
         // Solicit reactions
-        let reactions = context.request_reactions(&action);
+        let reactions = context.request_reactions(&action, &self.ledger);
 
         // Confirm the action to stack (consumes action)
         self.stack.push(action);
 
-        // Every reaction is assumed to have resources (like AP) paid for, so they all should
-        // be added to the stack
+        // Every reaction is assumed to be affordable (its cost is reserved once it's added), so
+        // they all should be added to the stack
         for reaction in reactions {
             self.add_action(reaction, context);
         }
@@ -339,6 +1117,10 @@ impl ActionStack {
     /// This process **consumes this instance** with all contained actions as they are enacted
     /// on the world `context` provided..
     fn resolve(&mut self, context: &mut dyn WorldContext) {
+        // Nothing to resolve (e.g. a move without any valid targets)
+        if self.stack.is_empty() {
+            return;
+        }
 
         // Track Actions that need to be resolved on other actions in this listing
         let mut targeting_actions: Vec<(usize, Action)> = Vec::new();
@@ -354,19 +1136,37 @@ impl ActionStack {
                 a.resolve_on_action(&mut action).unwrap();
             }
 
+            // ~~ Costs ~~
+            // The reserved cost is either committed (paid) now, or rolled back if the action got
+            // canceled. Actions only resolve if their source can still pay for them.
+            self.ledger.release(action.get_source(), action.get_cost());
+            if !action.pay_costs(context) {
+                action.set_effect(ActionEffect::Canceled);
+            }
+            if let Some(shared) = action.shared_cost.clone() {
+                self.settle_shared_cost(&mut action, shared, context);
+            }
+
             // ~~ Main Resolution ~~
             // Fundamentally, handle each action based on what it's targeting.
             match action.get_target() {
                 // If this action targets a character, resolve it directly
-                EntityPointer::Character(chars) => action.resolve_on_chars(context).expect("Issue resolving Action"),
+                EntityPointer::Character(_) => {
+                    action.resolve_on_chars(context).expect("Issue resolving Action");
+                    context.record_action(&action);
+                }
                 // If this action targets another action, add it to the 'side stack' that tracks
                 // actions to still enact on other actions
                 EntityPointer::Action(i) => targeting_actions.push((*i, action)),
 
-                // As of now, effects and the environment cannot / should not be selected as
-                // targets for `ActionEffects`
-                EntityPointer::Effect(_, _) => {}
-                EntityPointer::Environment => {}
+                // Actions targeting the environment change the battlefield itself (e.g. summons)
+                EntityPointer::Environment => {
+                    action.resolve_on_environment(context).expect("Issue resolving Action");
+                }
+                // Actions targeting effects manipulate them on their holders
+                EntityPointer::Effect(_, _) => {
+                    action.resolve_on_effects(context).expect("Issue resolving Action");
+                }
             }
 
             // Exclude the final iteration step 0 as the format remains usize for easy targeting
@@ -378,6 +1178,34 @@ impl ActionStack {
         }
     }
 
+    /// Settles the `shared` cost of `action`: The first action sharing it that resolves pays it
+    /// (and starts the cooldown, if any), and the others only resolve if it was paid. Once all of
+    /// them got canceled, it's released.
+    fn settle_shared_cost(&mut self, action: &mut Action, shared: SharedCost, context: &mut dyn WorldContext) {
+        let state = self.shared_costs.entry(shared.id).or_default();
+        state.unresolved = state.unresolved.saturating_sub(1);
+        let canceled = matches!(action.get_effect(), ActionEffect::Canceled);
+        match state.paid {
+            None if !canceled => {
+                self.ledger.release(action.get_source(), shared.cost);
+                let paid = action.charge(shared.cost, None, context);
+                state.paid = Some(paid);
+                if !paid {
+                    action.set_effect(ActionEffect::Canceled);
+                } else if let (Some((name, turns)), EntityPointer::Character(sources)) = (shared.cooldown, action.get_source()) {
+                    for id in sources.iter().map(|s| s.id()) {
+                        if let Some(character) = context.get_character_by_id_mut(id) {
+                            character.start_cooldown(name.clone(), turns);
+                        }
+                    }
+                }
+            }
+            None if state.unresolved == 0 => self.ledger.release(action.get_source(), shared.cost),
+            Some(false) => action.set_effect(ActionEffect::Canceled),
+            _ => {}
+        }
+    }
+
 }
 
 /// Action Stacks can express 'what's happening' in words.
@@ -394,6 +1222,89 @@ impl MakesWords for ActionStack {
     }
 }
 
+/// AP and MP paid by an action's source once the action resolves
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct ActionCost {
+    pub ap: i64,
+    pub mp: i64,
+}
+
+impl Add for ActionCost {
+    type Output = ActionCost;
+
+    fn add(self, other: ActionCost) -> ActionCost {
+        ActionCost {
+            ap: self.ap + other.ap,
+            mp: self.mp + other.mp,
+        }
+    }
+}
+
+/// The next ID of a cost shared by several actions
+static NEXT_SHARED_COST: AtomicU64 = AtomicU64::new(1);
+
+/// A cost shared by several actions, identified by its `id` (see `Action::share_cost`). Paying
+/// it may put the move the actions stem from on cooldown (by name, for a number of turns).
+#[derive(Clone, Debug)]
+struct SharedCost {
+    id: u64,
+    cost: ActionCost,
+    cooldown: Option<(String, i64)>,
+}
+
+/// How far a shared cost on an `ActionStack` is settled: How many of its actions are yet to
+/// resolve, and whether it was `paid` (if it was charged at all yet)
+#[derive(Default)]
+struct SharedCostState {
+    unresolved: usize,
+    paid: Option<bool>,
+}
+
+/// Tracks the costs of all actions on an `ActionStack` that haven't been paid yet, by source
+/// character. This is each character's **reaction budget**: While the stack is built, characters
+/// only react with what they can afford on top of their pending costs. Once an action resolves,
+/// its cost is released from the ledger and either committed (paid by the source), or rolled
+/// back if the action got canceled.
+pub struct CostLedger {
+    pending: HashMap<CharacterId, ActionCost>,
+}
+
+impl CostLedger {
+    pub fn new() -> CostLedger {
+        CostLedger {
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Returns the costs the character with the given `id` has yet to pay
+    pub fn pending(&self, id: CharacterId) -> ActionCost {
+        self.pending.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Reserves `cost` for the `source` character(s) of an action
+    fn reserve(&mut self, source: &EntityPointer, cost: ActionCost) {
+        self.book(source, cost, 1);
+    }
+
+    /// Releases `cost` reserved for the `source` character(s) of an action
+    fn release(&mut self, source: &EntityPointer, cost: ActionCost) {
+        self.book(source, cost, -1);
+    }
+
+    fn book(&mut self, source: &EntityPointer, cost: ActionCost, sign: i64) {
+        if cost == ActionCost::default() {
+            return;
+        }
+        if let EntityPointer::Character(sources) = source {
+            for source in sources {
+                let pending = self.pending.entry(source.id()).or_default();
+                pending.ap += sign * cost.ap;
+                pending.mp += sign * cost.mp;
+            }
+        }
+    }
+}
+
 
 /// This describes the atomic effects that are resolved on the Action Stack during turn resolution.
 ///
@@ -427,9 +1338,35 @@ pub enum ActionEffect {
     AdjustDamageAbs(i64),
     /// Adjust damage of target `Attack` multiplicatively
     AdjustDamageMul(f64),
+    /// Adjust damage of target `Attack` multiplicatively, only for the given target character
+    AdjustDamageMulFor(CharacterId, f64),
     /// Changes the target of the target action on the stack
     ChangeTarget(EntityPointer),
 
+    // ~~~~~~~~~~~~~~~~~~~ Positioning  ~~~~~~~~~~~~~~~~~~~
+    /// Moves the target character(s) to the given row of their party's formation
+    Reposition(Row),
+
+    // ~~~~~~~~~~~~~~~~~~~ Move Timing  ~~~~~~~~~~~~~~~~~~~
+    /// Winds up the target character's charge-up move with the given name by one turn
+    WindUp(String),
+
+    // ~~~~~~~~~~~~~~~~~~~ Targeting Effects  ~~~~~~~~~~~~~~~~~~~
+    /// Removes the target effect(s) from their holder
+    Dispel,
+    /// Removes the negative ones among the target effect(s) from their holder
+    Purge,
+    /// Moves the target effect(s) from their holder to the source character, keeping their
+    /// remaining duration
+    Steal,
+    /// Extends the duration of the target effect(s) by the given number of turns. Negative
+    /// values shorten it.
+    ExtendEffect(i64),
+
+    // ~~~~~~~~~~~~~~~~~~~ Targeting the Environment  ~~~~~~~~~~~~~~~~~~~
+    /// Calls a new character into combat on the source's side
+    Summon(Summoning),
+
     
 
 }
@@ -446,9 +1383,16 @@ impl ActionEffect {
             ActionEffect::Cancel => "CCL",
             ActionEffect::Canceled => "XXX",
             ActionEffect::AdjustDamageAbs(_) => "ADA",
-            ActionEffect::AdjustDamageMul(_) => "ADM",
+            ActionEffect::AdjustDamageMul(_) | ActionEffect::AdjustDamageMulFor(_, _) => "ADM",
             ActionEffect::ChangeTarget(_) => "CHT",
-            ActionEffect::Heal(_) => "HEA"
+            ActionEffect::Heal(_) => "HEA",
+            ActionEffect::WindUp(_) => "WND",
+            ActionEffect::Reposition(_) => "MOV",
+            ActionEffect::Summon(_) => "SUM",
+            ActionEffect::Dispel => "DSP",
+            ActionEffect::Purge => "PRG",
+            ActionEffect::Steal => "STL",
+            ActionEffect::ExtendEffect(_) => "EXT",
         }
     }
 
@@ -465,9 +1409,18 @@ impl ActionEffect {
             ActionEffect::AdjustDamageAbs(d) => if *d > 0 {
                 formatting.to_words("increases the damage of".to_string(), "", None) } else {
                 formatting.to_words("decreases the damage of".to_string(), "", None) },
-            ActionEffect::AdjustDamageMul(f) => if *f > 1f64 { formatting.to_words("increases the damage of".to_string(), "", None) } else { formatting.to_words("decreases the damage of".to_string(), "", None) },
+            ActionEffect::AdjustDamageMul(f) | ActionEffect::AdjustDamageMulFor(_, f) => if *f > 1f64 { formatting.to_words("increases the damage of".to_string(), "", None) } else { formatting.to_words("decreases the damage of".to_string(), "", None) },
             ActionEffect::ChangeTarget(_) => formatting.to_words("changes the target of".to_string(), "", None),
             ActionEffect::Heal(_) => formatting.to_words("heals".to_string(), "", None),
+            ActionEffect::WindUp(_) => formatting.to_words("readies".to_string(), "", None),
+            ActionEffect::Reposition(_) => formatting.to_words("moves".to_string(), "", None),
+            ActionEffect::Summon(_) => formatting.to_words("calls upon".to_string(), "", None),
+            ActionEffect::Dispel => formatting.to_words("dispels".to_string(), "", None),
+            ActionEffect::Purge => formatting.to_words("purges all harm from".to_string(), "", None),
+            ActionEffect::Steal => formatting.to_words("steals".to_string(), "", None),
+            ActionEffect::ExtendEffect(t) => if *t > 0 {
+                formatting.to_words("extends".to_string(), "", None) } else {
+                formatting.to_words("shortens".to_string(), "", None) },
         }
     }
 
@@ -483,9 +1436,14 @@ impl ActionEffect {
             ActionEffect::Cancel => vec![],
             ActionEffect::Canceled => vec![],
             ActionEffect::AdjustDamageAbs(_) | ActionEffect::AdjustDamageMul(_)
-                => vec![("by".to_string(), 2)],
+                | ActionEffect::AdjustDamageMulFor(_, _) => vec![("by".to_string(), 2)],
             ActionEffect::ChangeTarget(_) => vec![("to".to_string(), 2)],
             ActionEffect::Heal(_) => vec![("for".to_string(), 3)],
+            ActionEffect::WindUp(_) => vec![("for".to_string(), 3)],
+            ActionEffect::Reposition(_) => vec![("to the".to_string(), 6)],
+            ActionEffect::Summon(_) => vec![("to summon".to_string(), 9)],
+            ActionEffect::Dispel | ActionEffect::Purge | ActionEffect::Steal => vec![],
+            ActionEffect::ExtendEffect(_) => vec![("by".to_string(), 2)],
         }
     }
 
@@ -500,29 +1458,40 @@ impl ActionEffect {
             ActionEffect::Cancel => vec![],
             ActionEffect::Canceled => vec![],
             ActionEffect::AdjustDamageAbs(a) => formatting.to_words(a.format_line(5, formatting), "adjust", None),
-            ActionEffect::AdjustDamageMul(m) => {
+            ActionEffect::AdjustDamageMul(m) | ActionEffect::AdjustDamageMulFor(_, m) => {
                 // Calc percentages
                 let percentage_points = ((1f64-m) * 100f64).floor() as i64;
                 formatting.to_words(format!("{}{}%", if *m < 0f64 {"-"} else {""}, percentage_points), "adjust", None)
             }
             ActionEffect::ChangeTarget(t) => t.format_words(formatting),
             ActionEffect::Heal(unit) => unit.format_words(formatting),
+            ActionEffect::WindUp(name) => formatting.to_words(name.clone(), "", None),
+            ActionEffect::Reposition(row) => formatting.to_words(row.to_string(), "", None),
+            ActionEffect::Summon(s) => formatting.to_words(s.get_name().clone(), "char-target", None),
+            ActionEffect::Dispel | ActionEffect::Purge | ActionEffect::Steal => vec![],
+            ActionEffect::ExtendEffect(t) => formatting.to_words(format!("{} turns", t.abs()), "effect", None),
         }
     }
 
     // ~~~~~~~~~~~~~~~~ FUNCTIONALITY ~~~~~~~~~~~~~~~~
 
-    /// Applies this action to a given `character`. For effects targeting actions, nothing happens.
-    fn apply_to_character(&self, character: &mut Character) {
+    /// Applies this action to a given `character`, on behalf of the character `source` (if any).
+    /// For effects targeting actions, nothing happens.
+    fn apply_to_character(&self, character: &mut Character, source: Option<CharacterId>) {
         match self {
             ActionEffect::Attack(d) => character.apply_damage(d),
-            ActionEffect::GiveTimedEffect(e, t) => (),
+            ActionEffect::GiveTimedEffect(e, t) => character.apply_timed_effect(e.clone_box(), *t, source),
             ActionEffect::Cancel => {}
             ActionEffect::Canceled => {}
             ActionEffect::AdjustDamageAbs(_) => {}
             ActionEffect::AdjustDamageMul(_) => {}
+            ActionEffect::AdjustDamageMulFor(_, _) => {}
             ActionEffect::ChangeTarget(_) => {}
             ActionEffect::Heal(v) => character.apply_directly(v),
+            ActionEffect::WindUp(name) => character.wind_up(name),
+            ActionEffect::Reposition(row) => character.reposition(*row),
+            ActionEffect::Summon(_) => {}
+            ActionEffect::Dispel | ActionEffect::Purge | ActionEffect::Steal | ActionEffect::ExtendEffect(_) => {}
         }
 
     }
@@ -543,12 +1512,17 @@ impl ActionEffect {
                     action.set_effect(ActionEffect::Attack(Damage(*dt, (*da as f64*f).floor() as i64)))
                 }
             }
+            ActionEffect::AdjustDamageMulFor(id, f) => action.adjust_damage_for(*id, *f),
             ActionEffect::ChangeTarget(t) => {
                 // To maintain both this target ownership and the (required) ownership
                 // for the targeted action's newly set target, we must clone this value's Target
                 action.set_target(t.clone());
             }
             ActionEffect::Heal(_) => {}
+            ActionEffect::WindUp(_) => {}
+            ActionEffect::Reposition(_) => {}
+            ActionEffect::Summon(_) => {}
+            ActionEffect::Dispel | ActionEffect::Purge | ActionEffect::Steal | ActionEffect::ExtendEffect(_) => {}
         }
     }
 }
@@ -563,15 +1537,15 @@ impl ActionEffect {
 #[derive(Clone)]
 pub enum EntityPointer {
     /// Specifies one or many character targets from the encounter as target(s)
-    Character(Vec<String>),
+    Character(Vec<CharacterRef>),
     /// Specifies a single action on the action stack
     Action(usize),
     /// Specifies an effect by two key characteristics:
     /// 1. Effect Source, formatted as an `EntityPointer`. While all types are possible, only
     /// pointers for entities that (can) hold effects (like `Character` or `Environment`).
     /// This value must be wrapped in a `Box` to allow recursion.
-    /// 2. Effect Name
-    Effect(Box<EntityPointer>, String),
+    /// 2. Selector of the targeted effects of that source
+    Effect(Box<EntityPointer>, EffectSelector),
     /// Describes the general environment (e.g. as a source of e.g. Heat) as a unique entity
     Environment
 }
@@ -589,7 +1563,7 @@ impl EntityPointer {
 
     /// Returns the number of individual targets are contained in this target. Single-target
     /// returns `1` and multi-attacks return larger numbers.
-    fn num_entities(&self) -> usize {
+    pub fn num_entities(&self) -> usize {
         match self {
             EntityPointer::Character(targets) => targets.len(),
             EntityPointer::Action(_) => 1,
@@ -601,13 +1575,7 @@ impl EntityPointer {
     /// If possible, returns a reference Main Character that's targeted from `context`
     fn get_character<'a>(&self, context: &'a dyn WorldContext) -> Option<&'a Character> {
         match self {
-            EntityPointer::Character(name) => {
-                if let name = name.first().unwrap() {
-                    context.get_character(name)
-                } else {
-                    None
-                }
-            }
+            EntityPointer::Character(chars) => context.get_character_by_id(chars.first()?.id()),
             // Action Targets do not have Character objectives
             _ => None,
         }
@@ -619,15 +1587,18 @@ impl InfoLine for EntityPointer {
         match self {
             EntityPointer::Character(c) => {
                 if c.len() == 1 {
-                    c.first().unwrap().to_string()
+                    c.first().unwrap().name().to_string()
                 } else {
                     let res = c.iter().fold(String::new(), |mut acc, c|
-                        if acc.is_empty() { c.to_string() } else {acc + ", " + c});
+                        if acc.is_empty() { c.name().to_string() } else {acc + ", " + c.name()});
                     format!("the group of {}", res)
                 }
             },
             EntityPointer::Action(a) => "a previous action".to_string(),
-            EntityPointer::Effect(_, name) => format!("an effect ({})", name),
+            EntityPointer::Effect(holder, EffectSelector::All) =>
+                format!("the effects of {}", holder.format_line(len, formatting)),
+            EntityPointer::Effect(holder, EffectSelector::Only(identity)) =>
+                format!("{} of {}", identity.id, holder.format_line(len, formatting)),
             EntityPointer::Environment => "the environment".to_string(),
         }
     }
@@ -640,10 +1611,10 @@ impl MakesWords for EntityPointer {
             EntityPointer::Character(c) => {
                 if c.len() == 1 {
                     // Default case: Just the one character's name
-                    formatting.to_words(c.first().unwrap().to_string(), "char-target", None)
+                    formatting.to_words(c.first().unwrap().name().to_string(), "char-target", None)
                 } else {
                     let chars = c.iter().fold(String::new(), |mut acc, c|
-                        if acc.is_empty() { c.to_string() } else {acc + ", " + c});
+                        if acc.is_empty() { c.name().to_string() } else {acc + ", " + c.name()});
                     formatting.to_words(chars + " as a group", "char-target", None)
 
                 }
@@ -651,8 +1622,14 @@ impl MakesWords for EntityPointer {
             EntityPointer::Action(_) => {
                 formatting.to_words("a previous action".to_string(), "action-target", None)
             }
-            EntityPointer::Effect(_, _) => {
-                formatting.to_words("an effect".to_string(), "effect-target", None)
+            EntityPointer::Effect(holder, selector) => {
+                // Names the effect(s), followed by their holder
+                let mut words = match selector {
+                    EffectSelector::All => formatting.to_words("the effects of".to_string(), "effect-target", None),
+                    EffectSelector::Only(identity) => formatting.to_words(format!("{} of", identity.id), "effect-target", None),
+                };
+                words.extend(holder.format_words(formatting));
+                words
             }
             EntityPointer::Environment => {
                 formatting.to_words("the environment".to_string(), "environment-target", None)
@@ -664,9 +1641,10 @@ impl MakesWords for EntityPointer {
 impl Display for EntityPointer {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            EntityPointer::Character(c) => write!(f, "{}", c.iter().fold(String::new(), |a, b| a + " " + b)),
+            EntityPointer::Character(c) => write!(f, "{}", c.iter().fold(String::new(), |a, b| a + " " + b.name())),
             EntityPointer::Action(i) => write!(f, "prev: {}", i),
-            EntityPointer::Effect(source, name) => write!(f, "effect: {}", name),
+            EntityPointer::Effect(_, EffectSelector::All) => write!(f, "effect: all"),
+            EntityPointer::Effect(_, EffectSelector::Only(identity)) => write!(f, "effect: {}", identity.id),
             EntityPointer::Environment => write!(f, "the environment"),
         }
     }
@@ -681,8 +1659,8 @@ pub trait Actor {
     fn post_turn(&mut self);
 
     /// Called during turn resolution when this Actor is asked to select the move they want to
-    /// make during this turn
-    fn next_move(&self) -> &dyn Maneuver;
+    /// make during this turn. The world `context` can be used to inform the decision.
+    fn next_move(&self, context: &dyn WorldContext) -> Box<dyn Maneuver + '_>;
 
     fn apply_damage(&mut self, damage: &Damage);
 
@@ -691,7 +1669,7 @@ pub trait Actor {
     fn apply_directly(&mut self, val: &CharUnit);
 
     /// Adds a new effect to this character for a certain `effect_duration` in turns
-    fn apply_timed_effect(&mut self, effect: Box<dyn Effect>, effect_duration: i64);
+    fn apply_timed_effect(&mut self, effect: Box<dyn Effect>, effect_duration: i64, source: Option<CharacterId>);
 
     /// Called during action resolution. Any actor can react to any action placed in the context,
     /// the implementation of when/how to react is up to each individual `Actor`.
@@ -700,17 +1678,18 @@ pub trait Actor {
     ///
     /// # Parameters
     ///
-    /// * `self`: Mutable reference to allow updating internal state (e.g. discounting AP)
+    /// * `self`: Immutable reference, as costs are only paid once the reactions resolve
     /// * `context`: The World Context. Used for any checks and confirmations
     /// * `action`: The action that is currently being added to the stack.
+    /// * `ledger`: The costs of all actions already on the stack that are yet to be paid.
     /// * `reactions`: A mutable reference to all Reactions to the given `action`. This can be
     /// non-empty and include previous actions which should be left as-is. Reactions can be added
-    /// via `reactions.push`. Each entity is expected to do the required checks and self updates
-    /// (e.g. a `Character` discounting AP),
+    /// via `reactions.push`. Each entity is expected to do the required checks and attach the
+    /// reaction's cost to its actions (e.g. a `Character`'s AP, see `Action::share_cost`),
     /// as **all actions added to `reactions` are expected/guaranteed to be included on the stack,
     /// but could still be reacted to by other participants, as each reaction will invoke its own
     /// `respond_to_action` opportunity
-    fn respond_to_action(&self, context: &dyn WorldContext, action: &Action, reactions: &mut Vec<Action>);
+    fn respond_to_action(&self, context: &dyn WorldContext, action: &Action, ledger: &CostLedger, reactions: &mut Vec<Action>);
 }
 
 /// A very simple struct, Damage is described by it's type and amount
@@ -726,6 +1705,16 @@ impl Damage {
     pub fn amount(&self) -> i64 {
         self.1
     }
+
+    /// Splits this damage for each target of an attack hitting `group_size` targets at once.
+    /// Every target takes the damage divided by the square root of the group size, so hitting
+    /// more targets deals more damage in total, but less to each target.
+    pub fn split_for(&self, group_size: usize) -> Damage {
+        if group_size <= 1 {
+            return *self;
+        }
+        Damage(self.0, (self.1 as f64 / (group_size as f64).sqrt()).floor() as i64)
+    }
 }
 
 impl MakesWords for Damage {
@@ -772,7 +1761,7 @@ pub enum DamageType {
 impl DamageType {
     /// Returns the specific subtype of damage it this resistance protects from
     /// Can return "Any" when any resistance of the given Damage type (PHY or MAG) is affected
-    fn get_subtype_name(&self) -> &'static str {
+    pub fn get_subtype_name(&self) -> &'static str {
         match self {
             DamageType::PHY(t) => t,
             DamageType::MAG(t) => t,
@@ -782,7 +1771,7 @@ impl DamageType {
     }
 
     /// Returns the main damage type name
-    fn get_damage_type_name(&self) -> &'static str {
+    pub fn get_damage_type_name(&self) -> &'static str {
         match self {
             DamageType::PHY(_) => "PHY",
             DamageType::MAG(_) => "MAG",
@@ -791,6 +1780,29 @@ impl DamageType {
         }
     }
 
+    /// Checks whether this damage type (e.g. of a counter or a resistance) covers the `incoming`
+    /// damage type:
+    ///
+    /// * An empty subtype (e.g. `DamageType::PHY("")`) covers any subtype of its main type
+    /// * `DamageType::ULT` covers any incoming damage
+    /// * Incoming `ULT` damage is never covered, as it cannot be defended
+    pub fn covers(&self, incoming: &DamageType) -> bool {
+        // ULT Damage is unblockable
+        if let DamageType::ULT = incoming {
+            return false;
+        }
+
+        match self {
+            // ULT -> Generic coverage of any damage
+            DamageType::ULT => true,
+            _ => {
+                self.get_damage_type_name() == incoming.get_damage_type_name()
+                    && (self.get_subtype_name().is_empty()
+                        || self.get_subtype_name() == incoming.get_subtype_name())
+            }
+        }
+    }
+
     fn verb(&self, formatting: TextFormatting) -> Vec<(String, usize)> {
         match self {
             DamageType::PHY(t) => {
@@ -841,8 +1853,9 @@ impl Display for DamageType {
 
 #[cfg(test)]
 mod tests {
+    use crate::battlefield::{Range, ROW_MOVE_COST};
     use crate::combat::{ Actor};
-    use crate::equipment::{Equipment, EquipmentType};
+    use crate::equipment::{Equipment, EquipmentType, StatScaling, WeaponStats};
     use crate::mov::Counter;
     use crate::text::{InfoGrid, TextFormatting};
     use super::*;
@@ -870,11 +1883,7 @@ mod tests {
         }
         // conjoin both groups into one encounter list
         party.extend(baddies);
-        let mut combat = Combat {
-            participants: party,
-        };
-
-        combat
+        Combat::from_participants(party)
     }
 
     #[test]
@@ -909,6 +1918,577 @@ mod tests {
         assert!(hp_pre > combat.get_character(&charname).unwrap().hp());
     }
 
+    #[test]
+    fn test_weapon_attack() {
+        let mut combat = build_combat();
+
+        let sword = Equipment::weapon("Sword".to_string(), Stats {
+            dex: 0,
+            str: 0,
+            grt: 0,
+            wil: 0,
+            cha: 0,
+            int: 0,
+        }, WeaponStats {
+            base_damage: 50,
+            damage_type: DamageType::PHY("Slash"),
+            scaling: StatScaling { str: 2.0, ..Default::default() },
+            target_count: 1,
+            range: Range::Melee,
+        });
+
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().equip(sword).unwrap();
+
+        let baddie = "Baddie".to_string();
+        let hp_pre = combat.get_character(&baddie).unwrap().hp();
+
+        {
+            let lindtbert = combat.get_character(&"Lindtbert".to_string()).unwrap();
+            let next_move = lindtbert.next_move(&combat);
+            assert_eq!(next_move.name(), "Sword");
+
+            // Weapon deals its base damage plus scaled STR
+            let actions = next_move.execute(lindtbert, &combat);
+            assert_eq!(actions.len(), 1);
+            match actions[0].get_effect() {
+                ActionEffect::Attack(d) => assert_eq!(d.amount(), 50 + 3 * 2),
+                _ => panic!("Weapon Attack should attack"),
+            }
+            assert!(actions[0].targets_character(combat.get_character(&baddie).unwrap().id()));
+        }
+
+        combat.process_turn(None).unwrap();
+        assert!(combat.get_character(&baddie).unwrap().hp() < hp_pre);
+    }
+
+    #[test]
+    fn test_weapon_wear() {
+        let mut combat = build_combat();
+
+        let mut sword = Equipment::weapon("Sword".to_string(), Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        }, WeaponStats {
+            base_damage: 10,
+            damage_type: DamageType::PHY("Slash"),
+            scaling: StatScaling::default(),
+            target_count: 1,
+            range: Range::Melee,
+        });
+        sword.set_max_durability(1);
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().equip(sword).unwrap();
+
+        // A single resolved attack breaks the sword
+        combat.process_turn(None).unwrap();
+
+        let lindtbert = combat.get_character(&"Lindtbert".to_string()).unwrap();
+        assert!(lindtbert.iter_equipment().next().unwrap().is_broken());
+        assert_eq!(lindtbert.next_move(&combat).name(), "Barehanded Blow");
+    }
+
+    #[test]
+    fn test_barehanded_blow_target() {
+        use crate::mov::BarehandedBlow;
+
+        let mut combat = build_group_combat();
+        combat.get_character_mut(&"Baddie C".to_string()).unwrap().apply_directly(&CharUnit::HP(-50));
+
+        // Without threat to go by, the blow goes to the most weakened opponent
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        let blow = BarehandedBlow.execute(lindtbert, &combat);
+        assert_eq!(blow[0].get_target().get_character(&combat).unwrap().name(), "Baddie C");
+    }
+
+    #[test]
+    fn test_loot_drop() {
+        use crate::equipment::EquipmentType;
+        use crate::loot::{ItemBase, LootGenerator};
+
+        let mut combat = build_combat();
+        let mut generator = LootGenerator::new(99);
+        generator.add_item_base(1, ItemBase::new("Leather Cap".to_string(), EquipmentType::Head, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        }));
+        combat.set_loot_generator(generator);
+
+        // Knock out the baddie
+        combat.get_character_mut(&"Baddie".to_string()).unwrap().apply_directly(&CharUnit::HP(-10_000));
+        combat.process_turn(None).unwrap();
+
+        assert_eq!(combat.remaining_parties(), vec!["Best Friends"]);
+        assert_eq!(combat.get_stash("Best Friends").unwrap().len(), 1);
+        assert!(combat.get_stash("Baddies!").is_none());
+    }
+
+    #[test]
+    fn test_canceled_spell_refund() {
+        let mut combat = build_combat();
+        let lindtbert = combat.get_character(&"Lindtbert".to_string()).unwrap();
+        let baddie = combat.get_character("Baddie").unwrap().as_target();
+        let mp_pre = lindtbert.mp();
+
+        let mut spell = Action::from_source(lindtbert.as_target(),
+                                            ActionEffect::Attack(Damage(DamageType::MAG("Fire"), 10)),
+                                            baddie.clone());
+        spell.set_mp_cost(10);
+        let cancel = Action::from_source(baddie, ActionEffect::Cancel, EntityPointer::Action(0));
+
+        let mut stack = ActionStack::new();
+        stack.build(vec![spell, cancel], &mut combat);
+        stack.resolve(&mut combat);
+
+        // The spell never resolved, so its MP were never spent
+        assert_eq!(combat.get_character(&"Lindtbert".to_string()).unwrap().mp(), mp_pre);
+    }
+
+    #[test]
+    fn test_unaffordable_action() {
+        let mut combat = build_combat();
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        let (ap_pre, hp_pre) = (lindtbert.ap(), combat.get_character("Baddie").unwrap().hp());
+
+        // An action costing more AP than its source has left is refused, nothing is spent
+        let mut hit = vec![Action::from_source(lindtbert.as_target(),
+                                               ActionEffect::Attack(Damage(DamageType::PHY("Slash"), 10)),
+                                               combat.get_character("Baddie").unwrap().as_target())];
+        Action::share_cost(&mut hit, ActionCost { ap: ap_pre + 1, mp: 0 });
+        let mut stack = ActionStack::new();
+        stack.build(hit, &mut combat);
+        stack.resolve(&mut combat);
+        assert_eq!(combat.get_character("Lindtbert").unwrap().ap(), ap_pre);
+        assert_eq!(combat.get_character("Baddie").unwrap().hp(), hp_pre);
+    }
+
+    #[test]
+    fn test_shared_cost() {
+        let mut combat = build_combat();
+        let mp_pre = combat.get_character("Lindtbert").unwrap().mp();
+        let hits = |combat: &Combat| {
+            let lindtbert = combat.get_character("Lindtbert").unwrap().as_target();
+            let baddie = combat.get_character("Baddie").unwrap().as_target();
+            let mut hits: Vec<Action> = (0..2).map(|_| Action::from_source(lindtbert.clone(),
+                ActionEffect::Attack(Damage(DamageType::MAG("Fire"), 1)), baddie.clone())).collect();
+            Action::share_cost(&mut hits, ActionCost { ap: 0, mp: 10 });
+            hits
+        };
+        let cancel = |combat: &Combat, i: usize| Action::from_source(
+            combat.get_character("Baddie").unwrap().as_target(), ActionEffect::Cancel, EntityPointer::Action(i));
+
+        // Both hits are paid for once, even if the first one got canceled
+        let mut actions = hits(&combat);
+        actions.push(cancel(&combat, 0));
+        let mut stack = ActionStack::new();
+        stack.build(actions, &mut combat);
+        stack.resolve(&mut combat);
+        assert_eq!(combat.get_character("Lindtbert").unwrap().mp(), mp_pre - 10);
+
+        // Only if all of them got canceled, the cost is refunded
+        let mut actions = hits(&combat);
+        actions.extend([cancel(&combat, 0), cancel(&combat, 1)]);
+        let mut stack = ActionStack::new();
+        stack.build(actions, &mut combat);
+        stack.resolve(&mut combat);
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        assert_eq!(lindtbert.mp(), mp_pre - 10);
+        assert_eq!(stack.ledger.pending(lindtbert.id()).mp, 0);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        use crate::spells::Spell;
+
+        let mut combat = build_combat();
+        let mut bolt = Spell::bolt("Firebolt".to_string(), 1, 1, DamageType::MAG("Fire"));
+        bolt.set_cooldown(2);
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().learn_spell(bolt);
+
+        let mut used = vec![];
+        for _ in 0..4 {
+            let lindtbert = combat.get_character(&"Lindtbert".to_string()).unwrap();
+            used.push(lindtbert.next_move(&combat).name());
+            combat.process_turn(None).unwrap();
+        }
+
+        assert_eq!(used, vec!["Firebolt", "Barehanded Blow", "Barehanded Blow", "Firebolt"]);
+
+        // Canceled moves don't go on cooldown
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        let mut bolt = vec![Action::from_source(lindtbert.as_target(),
+                                                ActionEffect::Attack(Damage(DamageType::MAG("Fire"), 1)),
+                                                combat.get_character("Baddie").unwrap().as_target())];
+        Action::share_cooldown(&mut bolt, "Frostbolt".to_string(), 2);
+        bolt.push(Action::from_source(combat.get_character("Baddie").unwrap().as_target(),
+                                      ActionEffect::Cancel, EntityPointer::Action(0)));
+        let mut stack = ActionStack::new();
+        stack.build(bolt, &mut combat);
+        stack.resolve(&mut combat);
+        assert!(!combat.get_character("Lindtbert").unwrap().move_state().is_on_cooldown("Frostbolt"));
+    }
+
+    #[test]
+    fn test_charge_up() {
+        use crate::mov::Interrupt;
+        use crate::spells::Spell;
+
+        let mut combat = build_combat();
+        let mut meteor = Spell::bolt("Meteor".to_string(), 1, 50, DamageType::MAG("Fire"));
+        meteor.set_charge_turns(1);
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().learn_spell(meteor);
+
+        // Winding up takes a turn, the meteor hits the turn after
+        let baddie_hp = combat.get_character(&"Baddie".to_string()).unwrap().hp();
+        combat.process_turn(None).unwrap();
+        let lindtbert = combat.get_character(&"Lindtbert".to_string()).unwrap();
+        assert_eq!(lindtbert.move_state().get_charging().unwrap().0, "Meteor");
+        assert_eq!(lindtbert.next_move(&combat).name(), "Meteor");
+        combat.process_turn(None).unwrap();
+        assert!(combat.get_character(&"Baddie".to_string()).unwrap().hp() < baddie_hp - 50);
+
+        // An interrupting opponent keeps the meteor from ever being released
+        let mut ring = Equipment::new("Interrupt Ring".to_string(), EquipmentType::Ring, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        });
+        ring.add_reaction(Box::new(Interrupt));
+        combat.get_character_mut(&"Baddie".to_string()).unwrap().equip(ring).unwrap();
+
+        combat.process_turn(None).unwrap();
+        let lindtbert = combat.get_character(&"Lindtbert".to_string()).unwrap();
+        assert!(lindtbert.move_state().get_charging().is_none());
+        assert_eq!(lindtbert.next_move(&combat).name(), "Meteor (winding up)");
+    }
+
+    #[test]
+    fn test_threat_and_taunt() {
+        use crate::effects::Taunted;
+        use crate::mov::{select_opponents, Guard};
+
+        let mut tank = test_character("Tank".to_string());
+        tank.set_party("Best Friends".to_string());
+        let mut shield = Equipment::new("Guard Shield".to_string(), EquipmentType::Ring, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        });
+        shield.add_reaction(Box::new(Guard::new(0.5)));
+        tank.equip(shield).unwrap();
+
+        let mut participants = vec![tank];
+        participants.extend(build_combat().participants);
+        let mut combat = Combat::from_participants(participants);
+
+        // Lindtbert is weakened: The baddie goes for him, and the tank guards him
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().apply_directly(&CharUnit::HP(-300));
+        let baddie = combat.get_character("Baddie").unwrap();
+        assert_eq!(select_opponents(baddie, &combat, 1, Range::Ranged)[0].name(), "Lindtbert");
+
+        let attack = Action::from_source(baddie.as_target(),
+                                         ActionEffect::Attack(Damage(DamageType::ULT, 10)),
+                                         combat.get_character("Lindtbert").unwrap().as_target());
+        let mut stack = ActionStack::new();
+        stack.build(vec![attack], &mut combat);
+        stack.resolve(&mut combat);
+
+        let id = |name: &str| combat.get_character(name).unwrap().id();
+        assert_eq!(combat.get_threat_table().get_threat(id("Baddie"), id("Tank")), 10);
+        assert_eq!(combat.get_threat_table().get_threat(id("Baddie"), id("Lindtbert")), 0);
+
+        // Once taunted, the baddie has to target the tank
+        let tank = combat.get_character("Tank").unwrap().to_ref();
+        combat.get_character_mut(&"Baddie".to_string()).unwrap()
+            .apply_timed_effect(Box::new(Taunted(tank)), 2, None);
+        let baddie = combat.get_character("Baddie").unwrap();
+        assert_eq!(select_opponents(baddie, &combat, 1, Range::Ranged)[0].name(), "Tank");
+    }
+
+    /// Lindtbert versus a party of four baddies
+    fn build_group_combat() -> Combat {
+        let mut participants = build_combat().participants;
+        for name in ["Baddie B", "Baddie C", "Baddie D"] {
+            let mut baddie = test_character(name.to_string());
+            baddie.set_party("Baddies!".to_string());
+            participants.push(baddie);
+        }
+        Combat::from_participants(participants)
+    }
+
+    #[test]
+    fn test_area_attack() {
+        use crate::mov::{AreaAttack, AreaOfEffect};
+
+        let mut combat = build_group_combat();
+        let id = |combat: &Combat, name: &str| combat.get_character(name).unwrap().id();
+        let attack = |combat: &Combat, area: AreaOfEffect, range: Range|
+            AreaAttack::new("Area".to_string(), Damage(DamageType::ULT, 100), StatScaling::default(), area, range)
+                .execute(combat.get_character("Lindtbert").unwrap(), combat);
+
+        // Everyone is at full HP, so the first baddie is the primary target
+        let cleave = attack(&combat, AreaOfEffect::Cleave, Range::Melee);
+        assert_eq!(cleave.len(), 1);
+        assert_eq!(cleave[0].get_target().num_entities(), 2);
+        assert_eq!(cleave[0].damage_for(id(&combat, "Baddie")).unwrap().amount(), 70);
+
+        let party = attack(&combat, AreaOfEffect::Party, Range::Ranged);
+        assert_eq!(party[0].get_target().num_entities(), 4);
+        assert_eq!(party[0].damage_for(id(&combat, "Baddie D")).unwrap().amount(), 50);
+
+        // Melee attacks don't reach into the back row
+        for name in ["Baddie C", "Baddie D"] {
+            combat.get_character_mut(&name.to_string()).unwrap().set_row(Row::Back);
+        }
+        let party = attack(&combat, AreaOfEffect::Party, Range::Melee);
+        assert_eq!(party[0].get_target().num_entities(), 2);
+        assert!(party[0].damage_for(id(&combat, "Baddie D")).is_none());
+
+        let chain = attack(&combat, AreaOfEffect::Chain(2, 0.5), Range::Ranged);
+        let amounts: Vec<i64> = chain.iter().map(|a| match a.get_effect() {
+            ActionEffect::Attack(d) => d.amount(),
+            _ => 0,
+        }).collect();
+        assert_eq!(amounts, vec![100, 50, 25]);
+    }
+
+    #[test]
+    fn test_group_counter() {
+        let mut combat = build_group_combat();
+        let mut eq = Equipment::new("Counter Ring".to_string(), EquipmentType::Ring, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        });
+        eq.add_reaction(Box::new(Counter::new(DamageType::PHY(""), 0f64, 0f64)));
+        combat.get_character_mut(&"Baddie".to_string()).unwrap().equip(eq).unwrap();
+
+        let hp_pre = combat.get_character("Baddie B").unwrap().hp();
+        let attack = Action::from_source(
+            combat.get_character("Lindtbert").unwrap().as_target(),
+            ActionEffect::Attack(Damage(DamageType::PHY("Strike"), 100)),
+            EntityPointer::Character(vec![combat.get_character("Baddie").unwrap().to_ref(),
+                                          combat.get_character("Baddie B").unwrap().to_ref()]));
+        let mut stack = ActionStack::new();
+        stack.build(vec![attack], &mut combat);
+        stack.resolve(&mut combat);
+
+        // Only the countering baddie is spared, the other one takes their share
+        let baddie = combat.get_character("Baddie").unwrap();
+        assert_eq!(baddie.hp(), baddie.calculate_game_stats().mhp());
+        assert!(combat.get_character("Baddie B").unwrap().hp() < hp_pre);
+    }
+
+    #[test]
+    fn test_formation() {
+        use crate::mov::select_opponents;
+
+        let mut mage = test_character("Mage".to_string());
+        mage.set_party("Best Friends".to_string());
+        mage.set_row(Row::Back);
+        mage.apply_directly(&CharUnit::HP(-300));
+        let mut participants = vec![mage];
+        participants.extend(build_combat().participants);
+        let mut combat = Combat::from_participants(participants);
+
+        // The weakened mage is out of melee reach, but not out of range
+        let baddie = combat.get_character("Baddie").unwrap();
+        assert_eq!(select_opponents(baddie, &combat, 2, Range::Melee).len(), 1);
+        assert_eq!(select_opponents(baddie, &combat, 1, Range::Ranged)[0].name(), "Mage");
+
+        // Attacks on the back row are softened by cover
+        let hit_mage = |combat: &mut Combat| {
+            let hp_pre = combat.get_character("Mage").unwrap().hp();
+            let attack = Action::from_source(combat.get_character("Baddie").unwrap().as_target(),
+                                             ActionEffect::Attack(Damage(DamageType::ULT, 50)),
+                                             combat.get_character("Mage").unwrap().as_target());
+            let mut stack = ActionStack::new();
+            stack.build(vec![attack], combat);
+            stack.resolve(combat);
+            hp_pre - combat.get_character("Mage").unwrap().hp()
+        };
+        assert_eq!(hit_mage(&mut combat), 35);
+
+        // Once the front row falls, the back row is exposed
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().apply_directly(&CharUnit::HP(-10_000));
+        assert_eq!(hit_mage(&mut combat), 50);
+        let baddie = combat.get_character("Baddie").unwrap();
+        assert_eq!(select_opponents(baddie, &combat, 1, Range::Melee)[0].name(), "Mage");
+    }
+
+    #[test]
+    fn test_row_moves() {
+        let mut mage = test_character("Mage".to_string());
+        mage.set_party("Best Friends".to_string());
+        mage.set_row(Row::Back);
+        let mut participants = vec![mage];
+        participants.extend(build_combat().participants);
+        let mut combat = Combat::from_participants(participants);
+
+        // Without a ranged move, the mage advances to the front row, spending MVE
+        let mage = combat.get_character("Mage").unwrap();
+        let mve = mage.mve();
+        let actions = {
+            let advance = mage.next_move(&combat);
+            assert_eq!(advance.name(), "Advance");
+            advance.execute(mage, &combat)
+        };
+        let mut stack = ActionStack::new();
+        stack.build(actions, &mut combat);
+        stack.resolve(&mut combat);
+        let mage = combat.get_character_mut(&"Mage".to_string()).unwrap();
+        assert_eq!(mage.get_row(), Row::Front);
+        assert_eq!(mage.mve(), mve - ROW_MOVE_COST);
+
+        // Once out of MVE, the mage can't get to the front row and can't punch from the back row
+        while mage.mve() >= ROW_MOVE_COST {
+            mage.reposition(mage.get_row().other());
+        }
+        mage.set_row(Row::Back);
+        let mage = combat.get_character("Mage").unwrap();
+        assert_eq!(mage.next_move(&combat).name(), "Wait");
+    }
+
+    #[test]
+    fn test_steal_by_identity() {
+        use crate::characters::CharStat;
+        use crate::effects::StatAdditive;
+        use crate::spells::{Spell, SpellEffect};
+
+        let mut shaman = test_character("Shaman".to_string());
+        shaman.set_party("Baddies!".to_string());
+        let mut participants = build_combat().participants;
+        participants.push(shaman);
+        let mut combat = Combat::from_participants(participants);
+        let shaman = combat.get_character("Shaman").unwrap().id();
+        let baddie = combat.get_character_mut(&"Baddie".to_string()).unwrap();
+        baddie.apply_timed_effect(Box::new(StatAdditive(CharStat::STR(5))), 3, None);
+        baddie.apply_timed_effect(Box::new(StatAdditive(CharStat::STR(5))), 3, Some(shaman));
+
+        let pilfer = Spell::manipulation("Pilfer".to_string(), 0, SpellEffect::Steal);
+        let resolve = |combat: &mut Combat, actions: Vec<Action>| {
+            let mut stack = ActionStack::new();
+            stack.build(actions, combat);
+            stack.resolve(combat);
+        };
+        let buffs = |combat: &Combat, name: &str| combat.get_character(name).unwrap().iter_timed_effects().count();
+
+        // Of two buffs looking alike, only the one from the targeted source is stolen
+        let actions = pilfer.execute(combat.get_character("Lindtbert").unwrap(), &combat);
+        resolve(&mut combat, actions);
+        assert_eq!(buffs(&combat, "Baddie"), 1);
+        assert_eq!(buffs(&combat, "Lindtbert"), 1);
+
+        // A thief who left before the steal resolves can't take the buff, so it stays
+        let actions = pilfer.execute(combat.get_character("Lindtbert").unwrap(), &combat);
+        let thief = combat.get_character("Lindtbert").unwrap().id();
+        combat.retreat(thief).unwrap();
+        resolve(&mut combat, actions);
+        assert_eq!(buffs(&combat, "Baddie"), 1);
+    }
+
+    #[test]
+    fn test_effect_targeting() {
+        use crate::characters::CharStat;
+        use crate::effects::{EffectIdentity, StatAdditive, Taunted};
+
+        let mut combat = build_combat();
+        let baddie = combat.get_character("Baddie").unwrap().to_ref();
+        {
+            let lindtbert = combat.get_character_mut(&"Lindtbert".to_string()).unwrap();
+            lindtbert.apply_timed_effect(Box::new(StatAdditive(CharStat::DEX(2))), 3, None);
+            lindtbert.apply_timed_effect(Box::new(Taunted(baddie)), 3, None);
+        }
+        let lindtbert = combat.get_character("Lindtbert").unwrap().as_target();
+        let all_effects = EntityPointer::Effect(Box::new(lindtbert.clone()), EffectSelector::All);
+        let buff = EntityPointer::Effect(Box::new(lindtbert.clone()), EffectSelector::Only(EffectIdentity {
+            id: "+2 DEX".to_string(),
+            source: None,
+        }));
+
+        let purge = Action::from_source(lindtbert.clone(), ActionEffect::Purge, all_effects);
+        let extend = Action::from_source(lindtbert.clone(), ActionEffect::ExtendEffect(2), buff);
+        assert_eq!(purge.format_words(TextFormatting::Plain).iter().map(|(w, _)| w.clone()).collect::<Vec<_>>().join(" "),
+                   "Lindtbert purges all harm from the effects of Lindtbert");
+        assert_eq!(extend.format_words(TextFormatting::Plain).iter().map(|(w, _)| w.clone()).collect::<Vec<_>>().join(" "),
+                   "Lindtbert extends +2 DEX of Lindtbert by 2 turns");
+
+        let mut stack = ActionStack::new();
+        stack.build(vec![purge, extend], &mut combat);
+        stack.resolve(&mut combat);
+
+        // Only the taunt was purged, the buff got extended
+        let effects: Vec<(String, i64)> = combat.get_character("Lindtbert").unwrap().iter_timed_effects()
+            .map(|t| (t.effect.describe(), t.remaining)).collect();
+        assert_eq!(effects, vec![("+2 DEX".to_string(), 5)]);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut participants = build_combat().participants;
+        let goblins: Vec<Character> = (0..3).map(|_| {
+            let mut goblin = test_character("Goblin".to_string());
+            goblin.set_party("Baddies!".to_string());
+            goblin
+        }).collect();
+        let ids: Vec<CharacterId> = goblins.iter().map(|g| g.id()).collect();
+        participants.extend(goblins);
+        let mut combat = Combat::from_participants(participants);
+
+        // Every goblin is addressed on its own, and told apart by name in narration
+        let names: Vec<&String> = ids.iter().map(|id| combat.get_character_by_id(*id).unwrap().name()).collect();
+        assert_eq!(names, vec!["Goblin", "Goblin 2", "Goblin 3"]);
+
+        let attack = Action::from_source(combat.get_character("Lindtbert").unwrap().as_target(),
+                                         ActionEffect::Attack(Damage(DamageType::ULT, 50)),
+                                         combat.get_character_by_id(ids[1]).unwrap().as_target());
+        let mut stack = ActionStack::new();
+        stack.build(vec![attack], &mut combat);
+        stack.resolve(&mut combat);
+
+        let hp: Vec<i64> = ids.iter().map(|id| combat.get_character_by_id(*id).unwrap().hp()).collect();
+        assert!(hp[1] < hp[0] && hp[0] == hp[2]);
+    }
+
+    #[test]
+    fn test_reaction_costs() {
+        let mut combat = build_combat();
+        let mut eq = Equipment::new("Counter Ring".to_string(), EquipmentType::Ring, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        });
+        eq.add_reaction(Box::new(Counter::new(DamageType::PHY(""), 0f64, 0f64)));
+        combat.get_character_mut(&"Lindtbert".to_string()).unwrap().equip(eq).unwrap();
+        let ap_pre = combat.get_character("Lindtbert").unwrap().ap();
+
+        let attack = |combat: &Combat| Action::from_source(combat.get_character("Baddie").unwrap().as_target(),
+                                                           ActionEffect::Attack(Damage(DamageType::PHY("Slash"), 10)),
+                                                           combat.get_character("Lindtbert").unwrap().as_target());
+
+        // The counter's AP are only reserved while the stack is built ...
+        let mut stack = ActionStack::new();
+        stack.build(vec![attack(&combat)], &mut combat);
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        assert_eq!(stack.ledger.pending(lindtbert.id()).ap, 3);
+        assert_eq!(lindtbert.ap(), ap_pre);
+
+        // ... and paid once it resolves
+        stack.resolve(&mut combat);
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        assert_eq!(stack.ledger.pending(lindtbert.id()).ap, 0);
+        assert_eq!(lindtbert.ap(), ap_pre - 3);
+
+        // A canceled counter is rolled back
+        let cancel = Action::from_source(combat.get_character("Baddie").unwrap().as_target(),
+                                         ActionEffect::Cancel, EntityPointer::Action(1));
+        let mut stack = ActionStack::new();
+        stack.build(vec![attack(&combat), cancel], &mut combat);
+        stack.resolve(&mut combat);
+        assert_eq!(combat.get_character("Lindtbert").unwrap().ap(), ap_pre - 3);
+    }
+
+    #[test]
+    fn test_combat_on_worker_thread() {
+        let mut combat = build_combat();
+        let combat = std::thread::spawn(move || {
+            for _ in 0..5 {
+                combat.process_turn(None).unwrap();
+            }
+            combat
+        }).join().unwrap();
+
+        assert!(combat.turn > 0);
+    }
+
     #[test]
     fn test_reaction() {
         let mut combat = build_combat();
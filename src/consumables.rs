@@ -0,0 +1,257 @@
+//! Contains consumable items, such as potions, bombs, scrolls and antidotes.
+//!
+//! Consumables are carried in a character's `Inventory` and are **used up once their action
+//! resolves**. Depending on their `ConsumableUsage`, they are either used as the character's
+//! `Maneuver` or as a `Reaction` to incoming attacks.
+
+use crate::characters::{CharUnit, Character, CharacterRef};
+use crate::combat::{Action, ActionEffect, Damage, DamageType, EntityPointer};
+use crate::effects::{DamageResistance, Effect};
+use crate::mov::{Maneuver, Move, Reaction};
+use crate::text::{InfoLine, TextFormatting};
+use crate::world::WorldContext;
+
+/// Describes what using a consumable does
+pub enum ConsumableEffect {
+    /// Restores a unit of the user (e.g. a healing potion)
+    Heal(CharUnit),
+    /// Damages all opponents (e.g. a bomb)
+    Attack(Damage),
+    /// Gives the user a timed effect for a number of turns (e.g. a scroll)
+    GiveTimedEffect(Box<dyn Effect>, i64),
+}
+
+impl ConsumableEffect {
+    /// Builds a fresh `ActionEffect` that represents this effect on the stack
+    fn to_action_effect(&self) -> ActionEffect {
+        match self {
+            ConsumableEffect::Heal(unit) => ActionEffect::Heal(*unit),
+            ConsumableEffect::Attack(damage) => ActionEffect::Attack(*damage),
+            ConsumableEffect::GiveTimedEffect(effect, turns) => ActionEffect::GiveTimedEffect(effect.clone_box(), *turns),
+        }
+    }
+}
+
+/// Describes when a consumable is used
+pub enum ConsumableUsage {
+    /// The consumable is used as its owner's maneuver
+    Maneuver,
+    /// The consumable is used as a reaction, once an incoming attack would bring its owner's HP
+    /// below the given HP to Max HP ratio
+    LowHealth(f64),
+}
+
+/// A single-use item
+pub struct Consumable {
+    /// Name of the consumable
+    name: String,
+    /// What happens when using the consumable
+    effect: ConsumableEffect,
+    /// When the consumable is used
+    usage: ConsumableUsage,
+}
+
+impl Consumable {
+
+    // ~~~~~~~~~~~~~~ Constructors ~~~~~~~~~~~~~~
+
+    pub fn new(name: String, effect: ConsumableEffect, usage: ConsumableUsage) -> Consumable {
+        Consumable {
+            name,
+            effect,
+            usage,
+        }
+    }
+
+    /// A potion restores `amount` HP when drunk as a maneuver
+    pub fn potion(name: String, amount: i64) -> Consumable {
+        Consumable::new(name, ConsumableEffect::Heal(CharUnit::HP(amount)), ConsumableUsage::Maneuver)
+    }
+
+    /// An emergency potion restores `amount` HP as a reaction, right before an attack would bring
+    /// its owner below `hp_ratio` (e.g. `0.25`)
+    pub fn emergency_potion(name: String, amount: i64, hp_ratio: f64) -> Consumable {
+        Consumable::new(name, ConsumableEffect::Heal(CharUnit::HP(amount)), ConsumableUsage::LowHealth(hp_ratio))
+    }
+
+    /// A bomb damages all opponents
+    pub fn bomb(name: String, damage: Damage) -> Consumable {
+        Consumable::new(name, ConsumableEffect::Attack(damage), ConsumableUsage::Maneuver)
+    }
+
+    /// A scroll gives its reader an `effect` for a number of `turns`
+    pub fn scroll(name: String, effect: Box<dyn Effect>, turns: i64) -> Consumable {
+        Consumable::new(name, ConsumableEffect::GiveTimedEffect(effect, turns), ConsumableUsage::Maneuver)
+    }
+
+    /// An antidote makes its user immune to poison for a number of `turns`
+    pub fn antidote(name: String, turns: i64) -> Consumable {
+        Consumable::new(name,
+                        ConsumableEffect::GiveTimedEffect(Box::new(DamageResistance(DamageType::MAG("Poison"), 1f64)), turns),
+                        ConsumableUsage::Maneuver)
+    }
+
+    // ~~~~~~~~~~~~~~ Getters ~~~~~~~~~~~~~~
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_usage(&self) -> &ConsumableUsage {
+        &self.usage
+    }
+
+    /// Builds the action of `character` using this consumable. The action is marked with this
+    /// consumable, so it's used up when the action resolves.
+    fn build_action(&self, character: &Character, context: &dyn WorldContext) -> Option<Action> {
+        let target = match self.effect {
+            // Attacks hit all opponents still standing
+            ConsumableEffect::Attack(_) => {
+                let targets: Vec<CharacterRef> = context.find_characters(
+                    &|c: &Character| !c.party_check(character.party()) && !c.is_defeated())
+                    .iter().map(|c| c.to_ref()).collect();
+                if targets.is_empty() {
+                    return None;
+                }
+                EntityPointer::Character(targets)
+            }
+            // Anything else affects the user
+            ConsumableEffect::Heal(_) | ConsumableEffect::GiveTimedEffect(_, _) => character.as_target(),
+        };
+
+        let mut action = Action::from_source(character.as_target(), self.effect.to_action_effect(), target);
+        action.set_consumable(self.name.clone());
+        Some(action)
+    }
+}
+
+impl Move for Consumable {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn describe(&self) -> String {
+        let what = match &self.effect {
+            ConsumableEffect::Heal(unit) => format!("Restores {}", unit.format_line(8, TextFormatting::Plain).trim()),
+            ConsumableEffect::Attack(damage) => format!("Deals {} to all opponents", damage),
+            ConsumableEffect::GiveTimedEffect(effect, turns) => format!("Gives {} for {} turns", effect.describe(), turns),
+        };
+        format!("{}. Single use.", what)
+    }
+}
+
+impl Maneuver for Consumable {
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        self.build_action(character, context).into_iter().collect()
+    }
+
+    /// Consumables are not wasted: Restoring consumables are only used once the restored unit
+    /// dropped below half.
+    fn is_available(&self, character: &Character, _: &dyn WorldContext) -> bool {
+        if !matches!(self.usage, ConsumableUsage::Maneuver) {
+            return false;
+        }
+
+        match self.effect {
+            ConsumableEffect::Heal(CharUnit::HP(_)) => character.hp_to_max_hp_ratio() < 0.5,
+            ConsumableEffect::Heal(CharUnit::MP(_)) => character.mp() * 2 < character.calculate_game_stats().mmp(),
+            ConsumableEffect::Heal(CharUnit::AP(_)) => character.ap() * 2 < character.calculate_game_stats().map(),
+            _ => true,
+        }
+    }
+}
+
+impl Reaction for Consumable {
+    /// Using an item in a pinch is quick
+    fn ap_cost(&self) -> i64 {
+        1
+    }
+
+    fn react(&self, character: &Character, action: &Action, context: &dyn WorldContext) -> Option<Vec<Action>> {
+        let threshold = match self.usage {
+            ConsumableUsage::LowHealth(t) => t,
+            ConsumableUsage::Maneuver => return None,
+        };
+
+        // Requirement 1: Only affecting actions that target me as a character directly
+        if !action.targets_character(character.id()) {
+            return None
+        }
+
+        // Requirement 2: Only reacting to incoming attacks that would bring me below the threshold
+        if let Some(damage) = action.damage_for(character.id()) {
+            let max_hp = character.calculate_game_stats().mhp();
+            if ((character.hp() - damage.amount()) as f64 / max_hp as f64) < threshold {
+                return self.build_action(character, context).map(|a| vec![a]);
+            }
+        }
+
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::Stats;
+    use crate::combat::{Actor, Combat, CostLedger};
+    use crate::inventory::Item;
+    use super::*;
+
+    fn test_character(name: &str, party: &str) -> Character {
+        let mut character = Character::new(name.to_string(), None, Stats {
+            str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+        character.set_party(party.to_string());
+        character
+    }
+
+    #[test]
+    fn test_potion() {
+        let mut lindtbert = test_character("Lindtbert", "Best Friends");
+        lindtbert.inventory_mut().add(Item::Consumable(Consumable::potion("Potion".to_string(), 50))).ok();
+
+        let mut combat = Combat::from_participants(vec![lindtbert, test_character("Baddie", "Baddies!")]);
+
+        // At full HP, the potion is not worth drinking
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        assert_ne!(lindtbert.next_move(&combat).name(), "Potion");
+
+        let lindtbert = combat.get_character_mut(&"Lindtbert".to_string()).unwrap();
+        lindtbert.apply_damage(&Damage(DamageType::ULT, lindtbert.hp() - 10));
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        assert_eq!(lindtbert.next_move(&combat).name(), "Potion");
+
+        let hp_pre = combat.get_character("Lindtbert").unwrap().hp();
+        combat.process_turn(None).unwrap();
+
+        // The potion has been used up, and Lindtbert healed
+        let lindtbert = combat.get_character("Lindtbert").unwrap();
+        assert!(lindtbert.inventory().is_empty());
+        assert!(lindtbert.hp() > hp_pre);
+    }
+
+    #[test]
+    fn test_emergency_potion() {
+        let lindtbert = test_character("Lindtbert", "Best Friends");
+        let mut potion_holder = test_character("Baddie", "Baddies!");
+        potion_holder.inventory_mut().add(Item::Consumable(
+            Consumable::emergency_potion("Emergency Potion".to_string(), 1000, 0.25))).ok();
+
+        // An attack that would bring the holder far below 25% triggers the potion
+        let lethal = Action::from_source(lindtbert.as_target(),
+                                         ActionEffect::Attack(Damage(DamageType::ULT, potion_holder.hp() - 1)),
+                                         potion_holder.as_target());
+        let harmless = Action::from_source(lindtbert.as_target(),
+                                           ActionEffect::Attack(Damage(DamageType::ULT, 1)),
+                                           potion_holder.as_target());
+
+        let combat = Combat::from_participants(vec![lindtbert]);
+        let mut reactions = vec![];
+        potion_holder.respond_to_action(&combat, &harmless, &CostLedger::new(), &mut reactions);
+        assert!(reactions.is_empty());
+        potion_holder.respond_to_action(&combat, &lethal, &CostLedger::new(), &mut reactions);
+        assert_eq!(reactions.len(), 1);
+        assert!(matches!(reactions[0].get_effect(), ActionEffect::Heal(CharUnit::HP(1000))));
+    }
+}
@@ -0,0 +1,1004 @@
+//! Contains content packs, which bundle game content (items, abilities, enemies, archetypes and
+//! rules) in plain text files, so designers can change content without recompiling - or even
+//! restarting - the game.
+//!
+//! Every pack is a directory within the content directory:
+//!
+//! ```text
+//! content/
+//!   base/
+//!     pack.txt         # name = Base, version = 1.0.0
+//!     rules.txt        # Ruleset values (see `ruleset`)
+//!     fire.script      # Script abilities (see `scripting`), any number of *.script files
+//!     items.txt
+//!     archetypes.txt
+//!     enemies.txt
+//!   fire_expansion/
+//!     pack.txt         # name = Fire Expansion, version = 0.2.0, depends = Base >= 1.0
+//!     items.txt
+//! ```
+//!
+//! Items, archetypes and enemies are defined in sections named after them:
+//!
+//! ```text
+//! [Ember Blade]
+//! type = weapon
+//! requires = str 3, dex 2
+//! damage = PHY Slash 6
+//! scaling = str 0.5
+//! bonus = grt 2
+//! reactions = Thorns
+//!
+//! [Goblin]            # in archetypes.txt
+//! stats = dex 4, str 3, grt 3, wil 1, cha 1, int 1
+//! equipment = Ember Blade
+//!
+//! [Goblin Chief]      # in enemies.txt
+//! archetype = Goblin
+//! party = Goblins
+//! stats = str 6
+//! ```
+//!
+//! Packs are loaded after the packs they depend on. Whatever a later pack defines overrides
+//! earlier definitions of the same name, and its rules are applied on top of earlier rules.
+//!
+//! All packs are loaded and validated as a whole before any of them is used, so a broken pack
+//! never replaces working content. A running game calls `ContentLibrary::reload_if_changed`
+//! between turns to pick up changed packs: Script abilities take effect right away, and
+//! `ContentLibrary::refresh` updates the pack items characters already have equipped.
+//!
+//! Loading packs never activates their rules by itself. A `World` run with a library (see
+//! `World::set_content`) plays by its rules, and does all of the above between turns with
+//! `World::reload_content`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::battlefield::{Range, Row};
+use crate::characters::{Character, CharStat, Stats};
+use crate::combat::DamageType;
+use crate::effects::StatAdditive;
+use crate::equipment::{Equipment, EquipmentType, Rarity, StatScaling, WeaponStats};
+use crate::inventory::Item;
+use crate::ruleset::Ruleset;
+use crate::scripting::{intern, ScriptRegistry};
+use crate::world::WorldContext;
+
+/// File describing a pack, which makes a directory a pack
+const MANIFEST_FILE: &str = "pack.txt";
+
+// ~~~~~~~~~~~~~~~~~~~ Pack Manifests ~~~~~~~~~~~~~~~~~~~
+
+/// Version of a content pack, as in `major.minor.patch`
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// Parses a version like `1.2.3`. Minor and patch version may be left out.
+    pub fn parse(text: &str) -> Result<Version, String> {
+        let parts = text.trim().split('.')
+            .map(|p| p.parse::<u64>().map_err(|_| format!("Invalid version {}.", text.trim())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if parts.len() > 3 {
+            return Err(format!("Invalid version {}.", text.trim()));
+        }
+
+        Ok(Version {
+            major: parts[0],
+            minor: parts.get(1).copied().unwrap_or(0),
+            patch: parts.get(2).copied().unwrap_or(0),
+        })
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A pack another pack depends on, optionally with the minimum version required
+#[derive(Clone, PartialEq, Debug)]
+pub struct Dependency {
+    pub pack: String,
+    pub min_version: Option<Version>,
+}
+
+impl Dependency {
+    /// Parses a dependency like `Base` or `Base >= 1.2`
+    pub fn parse(text: &str) -> Result<Dependency, String> {
+        let (pack, min_version) = match text.split_once(">=") {
+            Some((pack, version)) => (pack, Some(Version::parse(version)?)),
+            None => (text, None),
+        };
+        match pack.trim() {
+            "" => Err(format!("Invalid dependency {}.", text.trim())),
+            pack => Ok(Dependency { pack: pack.to_string(), min_version }),
+        }
+    }
+
+    /// Checks whether the pack `manifest` satisfies this dependency
+    pub fn is_met_by(&self, manifest: &PackManifest) -> bool {
+        manifest.name == self.pack && self.min_version.is_none_or(|v| manifest.version >= v)
+    }
+}
+
+/// Describes a content pack, as read from its `pack.txt`
+#[derive(Clone, Debug)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: Version,
+    pub depends: Vec<Dependency>,
+    /// Directory the pack was loaded from
+    pub directory: PathBuf,
+}
+
+impl PackManifest {
+    /// Reads the manifest of the pack in `directory`
+    pub fn load(directory: &Path) -> Result<PackManifest, String> {
+        let text = fs::read_to_string(directory.join(MANIFEST_FILE))
+            .map_err(|e| format!("Could not read {}: {}", MANIFEST_FILE, e))?;
+        let mut name = None;
+        let mut version = None;
+        let mut depends = vec![];
+
+        for (line, key, value) in entries(&text)? {
+            let error = |e: String| format!("Line {}: {}", line, e);
+            match key.as_str() {
+                "name" => name = Some(value),
+                "version" => version = Some(Version::parse(&value).map_err(error)?),
+                "depends" => for dependency in split_list(&value) {
+                    depends.push(Dependency::parse(dependency).map_err(error)?);
+                },
+                _ => return Err(error(format!("Unknown key {}.", key))),
+            }
+        }
+
+        Ok(PackManifest {
+            name: name.ok_or(format!("{} is missing the pack's name.", MANIFEST_FILE))?,
+            version: version.ok_or(format!("{} is missing the pack's version.", MANIFEST_FILE))?,
+            depends,
+            directory: directory.to_path_buf(),
+        })
+    }
+}
+
+impl Display for PackManifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// Orders `packs` so every pack comes after the packs it depends on. Otherwise, packs are ordered
+/// by name, so the load order doesn't depend on the file system.
+fn order_packs(mut packs: Vec<PackManifest>) -> Result<Vec<PackManifest>, Vec<String>> {
+    let mut problems = vec![];
+    for pack in packs.iter() {
+        for dependency in pack.depends.iter() {
+            match packs.iter().find(|p| p.name == dependency.pack) {
+                None => problems.push(format!("{} depends on {}, which is missing.", pack.name, dependency.pack)),
+                Some(found) if !dependency.is_met_by(found) => problems.push(format!(
+                    "{} depends on {} {} or later, but found {}.", pack.name, dependency.pack,
+                    dependency.min_version.unwrap(), found.version)),
+                _ => {}
+            }
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut ordered: Vec<PackManifest> = vec![];
+    while !packs.is_empty() {
+        let next = packs.iter().position(|p|
+            p.depends.iter().all(|d| ordered.iter().any(|o| o.name == d.pack)));
+        match next {
+            Some(i) => ordered.push(packs.remove(i)),
+            None => {
+                let names: Vec<&str> = packs.iter().map(|p| p.name.as_str()).collect();
+                return Err(vec![format!("Packs {} depend on each other.", names.join(", "))]);
+            }
+        }
+    }
+
+    Ok(ordered)
+}
+
+// ~~~~~~~~~~~~~~~~~~~ File Format ~~~~~~~~~~~~~~~~~~~
+
+/// A section of a content file: the name of what it defines, and its `key = value` entries
+struct Section {
+    name: String,
+    line: usize,
+    entries: Vec<(usize, String, String)>,
+}
+
+/// Reads the `key = value` entries of `text`, with their line numbers. `#` starts a comment.
+fn entries(text: &str) -> Result<Vec<(usize, String, String)>, String> {
+    let mut entries = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once('=')
+            .ok_or(format!("Line {}: Expected 'key = value'.", i + 1))?;
+        entries.push((i + 1, key.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(entries)
+}
+
+/// Reads the sections of `text`, in the same format as rulesets
+fn sections(text: &str) -> Result<Vec<Section>, String> {
+    let mut sections: Vec<Section> = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push(Section {
+                name: line[1..line.len() - 1].trim().to_string(),
+                line: i + 1,
+                entries: vec![],
+            });
+            continue;
+        }
+
+        let (key, value) = line.split_once('=')
+            .ok_or(format!("Line {}: Expected 'key = value'.", i + 1))?;
+        sections.last_mut()
+            .ok_or(format!("Line {}: Values must be in a section.", i + 1))?
+            .entries.push((i + 1, key.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(sections)
+}
+
+/// Splits a comma separated list, leaving out empty entries
+fn split_list(value: &str) -> Vec<&str> {
+    value.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect()
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value {}.", value))
+}
+
+/// Parses a list of stats with their values, like `str 3, dex 2`
+fn parse_stat_list<T: std::str::FromStr>(value: &str) -> Result<Vec<(String, T)>, String> {
+    split_list(value).into_iter().map(|entry| {
+        let (stat, amount) = entry.split_once(' ').ok_or(format!("Expected 'stat value', got {}.", entry))?;
+        Ok((stat.to_lowercase(), parse_value(amount.trim())?))
+    }).collect()
+}
+
+/// Sets the base stat `name` of `stats` to `value`
+fn set_stat(stats: &mut Stats, name: &str, value: i64) -> Result<(), String> {
+    match name {
+        "dex" => stats.dex = value,
+        "str" => stats.str = value,
+        "grt" => stats.grt = value,
+        "wil" => stats.wil = value,
+        "cha" => stats.cha = value,
+        "int" => stats.int = value,
+        _ => return Err(format!("Unknown stat {}.", name)),
+    }
+    Ok(())
+}
+
+/// Parses stats like `str 3, dex 2`. Stats left out are 0.
+fn parse_stats(value: &str) -> Result<Stats, String> {
+    let mut stats = Stats { dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0 };
+    for (stat, amount) in parse_stat_list(value)? {
+        set_stat(&mut stats, &stat, amount)?;
+    }
+    Ok(stats)
+}
+
+/// Parses a stat bonus like `grt 2` or `mhp 20`
+fn parse_bonus(stat: &str, amount: i64) -> Result<CharStat, String> {
+    Ok(match stat {
+        "dex" => CharStat::DEX(amount),
+        "str" => CharStat::STR(amount),
+        "grt" => CharStat::GRT(amount),
+        "wil" => CharStat::WIL(amount),
+        "cha" => CharStat::CHA(amount),
+        "int" => CharStat::INT(amount),
+        "mhp" => CharStat::MHP(amount),
+        "mmp" => CharStat::MMP(amount),
+        "tap" => CharStat::TAP(amount),
+        "mve" => CharStat::MVE(amount),
+        "pdf" => CharStat::PDF(amount),
+        "mdf" => CharStat::MDF(amount),
+        "mob" => CharStat::MOB(amount),
+        "hrg" => CharStat::HRG(amount),
+        "mrg" => CharStat::MRG(amount),
+        _ => return Err(format!("Unknown stat {}.", stat)),
+    })
+}
+
+/// Parses damage like `PHY Slash 6` into its type and amount
+fn parse_damage(value: &str) -> Result<(DamageType, i64), String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    match parts.as_slice() {
+        ["ULT", amount] => Ok((DamageType::ULT, parse_value(amount)?)),
+        [damage_type, subtype, amount] => {
+            let subtype = intern(subtype)?;
+            let damage_type = match *damage_type {
+                "PHY" => DamageType::PHY(subtype),
+                "MAG" => DamageType::MAG(subtype),
+                "ZAP" => DamageType::ZAP(subtype),
+                _ => return Err(format!("Unknown damage type {}.", damage_type)),
+            };
+            Ok((damage_type, parse_value(amount)?))
+        }
+        _ => Err(format!("Expected 'type subtype amount', got {}.", value)),
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Definitions ~~~~~~~~~~~~~~~~~~~
+
+/// Describes an item, which is built into `Equipment` when needed
+struct ItemDef {
+    pack: String,
+    eq_type: EquipmentType,
+    rarity: Rarity,
+    requirements: Stats,
+    durability: i64,
+    weapon_stats: Option<WeaponStats>,
+    bonuses: Vec<CharStat>,
+    maneuvers: Vec<String>,
+    reactions: Vec<String>,
+    effects: Vec<String>,
+}
+
+impl ItemDef {
+    fn parse(section: &Section, pack: &str) -> Result<ItemDef, String> {
+        let mut item = ItemDef {
+            pack: pack.to_string(),
+            eq_type: EquipmentType::Accessory,
+            rarity: Rarity::Common,
+            requirements: parse_stats("")?,
+            durability: 0,
+            weapon_stats: None,
+            bonuses: vec![],
+            maneuvers: vec![],
+            reactions: vec![],
+            effects: vec![],
+        };
+        let mut has_type = false;
+        let mut damage = None;
+        let mut scaling = StatScaling::default();
+        let mut target_count = 1;
+        let mut range = Range::Melee;
+
+        for (line, key, value) in section.entries.iter() {
+            let list = || split_list(value).into_iter().map(|v| v.to_string()).collect();
+            let parsed: Result<(), String> = (|| {
+                match key.as_str() {
+                    "type" => {
+                        has_type = true;
+                        item.eq_type = match value.to_lowercase().as_str() {
+                            "weapon" => EquipmentType::Weapon,
+                            "head" => EquipmentType::Head,
+                            "chest" => EquipmentType::Chest,
+                            "arms" => EquipmentType::Arms,
+                            "hands" => EquipmentType::Hands,
+                            "feet" => EquipmentType::Feet,
+                            "ring" => EquipmentType::Ring,
+                            "accessory" => EquipmentType::Accessory,
+                            _ => return Err(format!("Unknown equipment type {}.", value)),
+                        }
+                    }
+                    "rarity" => item.rarity = Rarity::ALL.iter().copied()
+                        .find(|r| r.to_string().eq_ignore_ascii_case(value))
+                        .ok_or(format!("Unknown rarity {}.", value))?,
+                    "requires" => item.requirements = parse_stats(value)?,
+                    "durability" => item.durability = parse_value(value)?,
+                    "damage" => damage = Some(parse_damage(value)?),
+                    "scaling" => for (stat, factor) in parse_stat_list::<f64>(value)? {
+                        match stat.as_str() {
+                            "dex" => scaling.dex = factor,
+                            "str" => scaling.str = factor,
+                            "grt" => scaling.grt = factor,
+                            "wil" => scaling.wil = factor,
+                            "cha" => scaling.cha = factor,
+                            "int" => scaling.int = factor,
+                            _ => return Err(format!("Unknown stat {}.", stat)),
+                        }
+                    },
+                    "targets" => target_count = parse_value(value)?,
+                    "range" => range = match value.to_lowercase().as_str() {
+                        "melee" => Range::Melee,
+                        "ranged" => Range::Ranged,
+                        _ => return Err(format!("Unknown range {}.", value)),
+                    },
+                    "bonus" => for (stat, amount) in parse_stat_list(value)? {
+                        item.bonuses.push(parse_bonus(&stat, amount)?);
+                    },
+                    "maneuvers" => item.maneuvers = list(),
+                    "reactions" => item.reactions = list(),
+                    "effects" => item.effects = list(),
+                    _ => return Err(format!("Unknown key {}.", key)),
+                }
+                Ok(())
+            })();
+            parsed.map_err(|e| format!("Line {}: {}", line, e))?;
+        }
+
+        if !has_type {
+            return Err(format!("Line {}: Item {} has no type.", section.line, section.name));
+        }
+        item.weapon_stats = damage.map(|(damage_type, base_damage)| WeaponStats {
+            base_damage,
+            damage_type,
+            scaling,
+            target_count,
+            range,
+        });
+        Ok(item)
+    }
+
+    /// Builds the item named `name`, with its abilities taken from `scripts`
+    fn build(&self, name: &str, scripts: &ScriptRegistry) -> Result<Equipment, String> {
+        let mut equipment = Equipment::new(name.to_string(), self.eq_type, self.requirements);
+        equipment.set_origin(self.pack.clone(), name.to_string());
+        if let Some(weapon_stats) = self.weapon_stats {
+            equipment.set_weapon_stats(weapon_stats);
+        }
+        equipment.set_rarity(self.rarity);
+        equipment.set_max_durability(self.durability);
+        for bonus in self.bonuses.iter() {
+            equipment.add_passive_effect(Box::new(StatAdditive(*bonus)));
+        }
+        for maneuver in self.maneuvers.iter() {
+            equipment.add_move(scripts.maneuver(maneuver)?);
+        }
+        for reaction in self.reactions.iter() {
+            equipment.add_reaction(scripts.reaction(reaction)?);
+        }
+        for effect in self.effects.iter() {
+            equipment.add_passive_effect(scripts.effect(effect)?);
+        }
+        Ok(equipment)
+    }
+}
+
+/// Describes a kind of character: their stats, and what they are equipped with
+struct ArchetypeDef {
+    pack: String,
+    stats: Stats,
+    equipment: Vec<String>,
+    row: Row,
+}
+
+impl ArchetypeDef {
+    fn parse(section: &Section, pack: &str) -> Result<ArchetypeDef, String> {
+        let mut archetype = ArchetypeDef {
+            pack: pack.to_string(),
+            stats: parse_stats("")?,
+            equipment: vec![],
+            row: Row::Front,
+        };
+        for (line, key, value) in section.entries.iter() {
+            let error = |e: String| format!("Line {}: {}", line, e);
+            match key.as_str() {
+                "stats" => archetype.stats = parse_stats(value).map_err(error)?,
+                "equipment" => archetype.equipment = split_list(value).into_iter().map(|v| v.to_string()).collect(),
+                "row" => archetype.row = match value.to_lowercase().as_str() {
+                    "front" => Row::Front,
+                    "back" => Row::Back,
+                    _ => return Err(error(format!("Unknown row {}.", value))),
+                },
+                _ => return Err(error(format!("Unknown key {}.", key))),
+            }
+        }
+        Ok(archetype)
+    }
+}
+
+/// Describes an enemy: an archetype, with changes to its stats and additional equipment
+struct EnemyDef {
+    pack: String,
+    archetype: String,
+    party: String,
+    stats: Vec<(String, i64)>,
+    equipment: Vec<String>,
+}
+
+impl EnemyDef {
+    fn parse(section: &Section, pack: &str) -> Result<EnemyDef, String> {
+        let mut archetype = None;
+        let mut enemy = EnemyDef {
+            pack: pack.to_string(),
+            archetype: String::new(),
+            party: "Enemies".to_string(),
+            stats: vec![],
+            equipment: vec![],
+        };
+        for (line, key, value) in section.entries.iter() {
+            let error = |e: String| format!("Line {}: {}", line, e);
+            match key.as_str() {
+                "archetype" => archetype = Some(value.clone()),
+                "party" => enemy.party = value.clone(),
+                "stats" => {
+                    enemy.stats = parse_stat_list(value).map_err(error)?;
+                    let mut check = parse_stats("")?;
+                    for (stat, amount) in enemy.stats.iter() {
+                        set_stat(&mut check, stat, *amount).map_err(error)?;
+                    }
+                }
+                "equipment" => enemy.equipment = split_list(value).into_iter().map(|v| v.to_string()).collect(),
+                _ => return Err(error(format!("Unknown key {}.", key))),
+            }
+        }
+        enemy.archetype = archetype.ok_or(format!("Line {}: Enemy {} has no archetype.", section.line, section.name))?;
+        Ok(enemy)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Content ~~~~~~~~~~~~~~~~~~~
+
+/// All content defined by a set of packs
+#[derive(Default)]
+struct Content {
+    packs: Vec<PackManifest>,
+    ruleset: Ruleset,
+    items: HashMap<String, ItemDef>,
+    archetypes: HashMap<String, ArchetypeDef>,
+    enemies: HashMap<String, EnemyDef>,
+}
+
+impl Content {
+    fn item(&self, name: &str, scripts: &ScriptRegistry) -> Result<Equipment, String> {
+        self.items.get(name).ok_or(format!("There is no item {}.", name))?.build(name, scripts)
+    }
+
+    fn enemy(&self, name: &str, scripts: &ScriptRegistry) -> Result<Character, String> {
+        let enemy = self.enemies.get(name).ok_or(format!("There is no enemy {}.", name))?;
+        let archetype = self.archetypes.get(&enemy.archetype)
+            .ok_or(format!("There is no archetype {}.", enemy.archetype))?;
+
+        let mut stats = archetype.stats;
+        for (stat, amount) in enemy.stats.iter() {
+            set_stat(&mut stats, stat, *amount)?;
+        }
+        let mut character = Character::new(name.to_string(), None, stats);
+        character.set_party(enemy.party.clone());
+        character.set_row(archetype.row);
+        for item in archetype.equipment.iter().chain(enemy.equipment.iter()) {
+            character.equip(self.item(item, scripts)?)
+                .map_err(|e| format!("{} can't equip {}: {}", name, item, e))?;
+        }
+        Ok(character)
+    }
+
+    /// Loads all packs within `root` into new content, with their abilities registered with a
+    /// new registry. Returns notes on what was loaded, or all problems found.
+    fn load(root: &Path) -> Result<(Content, ScriptRegistry, Vec<String>), String> {
+        let mut problems = vec![];
+        let mut manifests: Vec<PackManifest> = vec![];
+
+        let directories = fs::read_dir(root)
+            .map_err(|e| format!("Could not read content directory {}: {}", root.display(), e))?;
+        let mut directories: Vec<PathBuf> = directories.filter_map(|e| e.ok()).map(|e| e.path())
+            .filter(|p| p.join(MANIFEST_FILE).is_file())
+            .collect();
+        directories.sort();
+        for directory in directories {
+            match PackManifest::load(&directory) {
+                Ok(manifest) if manifests.iter().any(|m| m.name == manifest.name) =>
+                    problems.push(format!("{}: Pack {} exists twice.", directory.display(), manifest.name)),
+                Ok(manifest) => manifests.push(manifest),
+                Err(e) => problems.push(format!("{}: {}", directory.display(), e)),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+        let packs = order_packs(manifests).map_err(|p| p.join("\n"))?;
+
+        let mut content = Content::default();
+        let scripts = ScriptRegistry::new();
+        let mut notes = vec![];
+        let mut abilities: HashMap<String, String> = HashMap::new();
+
+        for pack in packs.iter() {
+            notes.push(format!("Loaded {}.", pack));
+            let file = |name: &str| pack.directory.join(name);
+            let read = |path: &Path| -> Result<Option<String>, String> {
+                match path.is_file() {
+                    true => fs::read_to_string(path).map(Some).map_err(|e| format!("Could not read: {}", e)),
+                    false => Ok(None),
+                }
+            };
+            let mut problem = |path: &Path, e: String| problems.push(format!("{}: {}", path.display(), e));
+
+            // Rules are layered on top of each other
+            match read(&file("rules.txt")) {
+                Ok(Some(text)) => if let Err(e) = content.ruleset.apply(&text) {
+                    problem(&file("rules.txt"), e);
+                },
+                Ok(None) => {}
+                Err(e) => problem(&file("rules.txt"), e),
+            }
+
+            let mut script_files: Vec<PathBuf> = fs::read_dir(&pack.directory).into_iter().flatten()
+                .filter_map(|e| e.ok()).map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "script"))
+                .collect();
+            script_files.sort();
+            for path in script_files {
+                let loaded = read(&path).and_then(|text| scripts.load_str(&text.unwrap_or_default()));
+                match loaded {
+                    Ok(names) => for name in names {
+                        if let Some(previous) = abilities.insert(name.clone(), pack.name.clone()) {
+                            if previous != pack.name {
+                                notes.push(format!("{} overrides ability {} of {}.", pack.name, name, previous));
+                            }
+                        }
+                    },
+                    Err(e) => problem(&path, e),
+                }
+            }
+
+            // Loads the file `name` defining content of the given `kind` (e.g. "item")
+            let mut load_sections = |name: &str, kind: &str, define: &mut dyn FnMut(&Section) -> Result<Option<String>, String>| {
+                let path = file(name);
+                let sections = match read(&path).and_then(|t| sections(&t.unwrap_or_default())) {
+                    Ok(sections) => sections,
+                    Err(e) => return problem(&path, e),
+                };
+                let mut defined = HashSet::new();
+                for section in sections.iter() {
+                    if !defined.insert(section.name.clone()) {
+                        problem(&path, format!("Line {}: {} is defined twice.", section.line, section.name));
+                        continue;
+                    }
+                    match define(section) {
+                        Ok(Some(previous)) if previous != pack.name => notes.push(format!(
+                            "{} overrides {} {} of {}.", pack.name, kind, section.name, previous)),
+                        Ok(_) => {}
+                        Err(e) => problem(&path, e),
+                    }
+                }
+            };
+            load_sections("items.txt", "item", &mut |s| Ok(content.items.insert(s.name.clone(), ItemDef::parse(s, &pack.name)?)
+                .map(|previous| previous.pack)));
+            load_sections("archetypes.txt", "archetype", &mut |s| Ok(content.archetypes.insert(s.name.clone(), ArchetypeDef::parse(s, &pack.name)?)
+                .map(|previous| previous.pack)));
+            load_sections("enemies.txt", "enemy", &mut |s| Ok(content.enemies.insert(s.name.clone(), EnemyDef::parse(s, &pack.name)?)
+                .map(|previous| previous.pack)));
+        }
+
+        // Only check references once everything could be read, to not report follow-up problems
+        if problems.is_empty() {
+            problems.extend(content.validate(&scripts));
+        }
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+        content.packs = packs;
+        Ok((content, scripts, notes))
+    }
+
+    /// Checks that all items, archetypes and enemies can be built. Returns the problems found.
+    fn validate(&self, scripts: &ScriptRegistry) -> Vec<String> {
+        let mut problems = vec![];
+        let sorted = |names: Vec<&String>| {
+            let mut names: Vec<String> = names.into_iter().cloned().collect();
+            names.sort();
+            names
+        };
+
+        for name in sorted(self.items.keys().collect()) {
+            if let Err(e) = self.item(&name, scripts) {
+                problems.push(format!("{}: Item {}: {}", self.items[&name].pack, name, e));
+            }
+        }
+        for name in sorted(self.archetypes.keys().collect()) {
+            let archetype = &self.archetypes[&name];
+            for item in archetype.equipment.iter().filter(|i| !self.items.contains_key(*i)) {
+                problems.push(format!("{}: Archetype {}: There is no item {}.", archetype.pack, name, item));
+            }
+        }
+        for name in sorted(self.enemies.keys().collect()) {
+            if let Err(e) = self.enemy(&name, scripts) {
+                problems.push(format!("{}: Enemy {}: {}", self.enemies[&name].pack, name, e));
+            }
+        }
+        problems
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Library ~~~~~~~~~~~~~~~~~~~
+
+/// Identifies the state of all files in the content directory, to notice changes
+type Fingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+/// Adds the state of all files below `path` to `fingerprint`
+fn take_fingerprint(path: &Path, fingerprint: &mut Fingerprint) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            take_fingerprint(&entry, fingerprint);
+        } else if let Ok(metadata) = entry.metadata() {
+            fingerprint.push((entry, metadata.len(), metadata.modified().ok()));
+        }
+    }
+}
+
+/// Provides the content of all packs in a content directory, and keeps it up to date as packs
+/// change
+pub struct ContentLibrary {
+    root: PathBuf,
+    content: Content,
+    scripts: ScriptRegistry,
+    fingerprint: Fingerprint,
+}
+
+impl ContentLibrary {
+    /// Builds an empty library for the packs in the directory `root`. Call `load` to load them.
+    pub fn new(root: &str) -> ContentLibrary {
+        ContentLibrary {
+            root: PathBuf::from(root),
+            content: Content::default(),
+            scripts: ScriptRegistry::new(),
+            fingerprint: vec![],
+        }
+    }
+
+    /// Loads all packs, replacing the current content. Returns notes on what was loaded (e.g.
+    /// overrides). The packs' rules aren't activated (see `get_ruleset`).
+    ///
+    /// If any pack has a problem, nothing changes and all problems are returned, one per line.
+    pub fn load(&mut self) -> Result<Vec<String>, String> {
+        let mut current = vec![];
+        take_fingerprint(&self.root, &mut current);
+        self.fingerprint = current;
+
+        let (content, scripts, notes) = Content::load(&self.root)?;
+        self.scripts.replace_with(&scripts);
+        self.content = content;
+        Ok(notes)
+    }
+
+    /// Reloads all packs if any file in the content directory changed since they were last
+    /// loaded. Meant to be called between turns. Returns `None` if nothing changed, and the
+    /// result of `load` otherwise.
+    pub fn reload_if_changed(&mut self) -> Option<Result<Vec<String>, String>> {
+        let mut current = vec![];
+        take_fingerprint(&self.root, &mut current);
+        match current == self.fingerprint {
+            true => None,
+            false => Some(self.load()),
+        }
+    }
+
+    /// Rebuilds the items built from packs that characters of `context` have equipped from
+    /// their current definitions, keeping their wear. Other equipment is left as it is.
+    ///
+    /// Items a character can't equip anymore (e.g. their requirements were raised) are moved to
+    /// their inventory instead. Returns a note for each of these, and for each item that had to
+    /// stay equipped as it was, since there was no room for it.
+    pub fn refresh(&self, context: &mut dyn WorldContext) -> Vec<String> {
+        let mut notes = vec![];
+        for character in context.iter_characters_mut() {
+            let rebuilt: Vec<(usize, Equipment)> = character.iter_equipment().enumerate()
+                .filter_map(|(i, old)| {
+                    let (pack, definition) = old.get_origin()?;
+                    if !self.content.packs.iter().any(|p| p.name == *pack) {
+                        return None;
+                    }
+                    let mut item = self.item(definition).ok()?;
+                    item.wear(old.get_max_durability() - old.get_durability());
+                    Some((i, item))
+                })
+                .collect();
+            // Later items first, so taking off an item doesn't move those still to be rebuilt
+            for (i, item) in rebuilt.into_iter().rev() {
+                let (item, reason) = match character.replace_equipment(i, item) {
+                    Ok(_) => continue,
+                    Err(refused) => refused,
+                };
+                let name = item.get_name().clone();
+                if character.inventory().has_space() {
+                    character.unequip(i);
+                    character.inventory_mut().add(Item::Equipment(item)).ok();
+                    notes.push(format!("{} can't equip {} anymore ({}), so it was moved to their inventory.",
+                                       character.name(), name, reason));
+                } else {
+                    notes.push(format!("{} can't equip {} anymore ({}), but has no room to take it off.",
+                                       character.name(), name, reason));
+                }
+            }
+        }
+        notes
+    }
+
+    /// Builds the item `name`
+    pub fn item(&self, name: &str) -> Result<Equipment, String> {
+        self.content.item(name, &self.scripts)
+    }
+
+    /// Builds the enemy `name`, equipped as defined
+    pub fn enemy(&self, name: &str) -> Result<Character, String> {
+        self.content.enemy(name, &self.scripts)
+    }
+
+    /// Returns the registry of all script abilities. Abilities taken from it always run their
+    /// latest definition.
+    pub fn scripts(&self) -> &ScriptRegistry {
+        &self.scripts
+    }
+
+    /// Returns the rules defined by the packs, e.g. to activate them with `set_ruleset` or
+    /// `with_ruleset`
+    pub fn get_ruleset(&self) -> &Ruleset {
+        &self.content.ruleset
+    }
+
+    /// Iterates all loaded packs, in load order
+    pub fn iter_packs(&self) -> core::slice::Iter<PackManifest> {
+        self.content.packs.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use crate::combat::Combat;
+    use super::*;
+
+    /// Writes `files` into the pack directory `pack` below `root`
+    fn write_pack(root: &Path, pack: &str, files: &[(&str, &str)]) {
+        let directory = root.join(pack);
+        fs::create_dir_all(&directory).unwrap();
+        for (name, text) in files {
+            fs::write(directory.join(name), text).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_version_order() {
+        assert!(Version::parse("1.10").unwrap() > Version::parse("1.9.3").unwrap());
+        assert_eq!(Version::parse("2").unwrap().to_string(), "2.0.0");
+        assert!(Version::parse("1.x").is_err());
+        let dependency = Dependency::parse("Base >= 1.2").unwrap();
+        assert_eq!(dependency.pack, "Base");
+        assert_eq!(dependency.min_version, Some(Version::parse("1.2").unwrap()));
+    }
+
+    #[test]
+    fn test_content_packs() {
+        let root = env::temp_dir().join(format!("content_packs_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write_pack(&root, "base", &[
+            ("pack.txt", "name = Base\nversion = 1.0.0"),
+            ("rules.txt", "[max_hp]\nbase = 50"),
+            ("abilities.script", "reaction \"Thorns\" {\n  react { if action.is_attack { adjust_damage(0.5) } }\n}"),
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 2\ndurability = 10\n\n\
+                [Thorny Ring]\ntype = ring\nreactions = Thorns\nbonus = grt 1"),
+            ("archetypes.txt", "[Goblin]\nstats = dex 4, str 3, grt 3\nequipment = Rusty Sword"),
+            ("enemies.txt", "[Goblin Chief]\narchetype = Goblin\nparty = Goblins\nstats = str 6\nequipment = Thorny Ring"),
+        ]);
+        // Sorts before Base by name, but depends on it
+        write_pack(&root, "a_expansion", &[
+            ("pack.txt", "name = Expansion\nversion = 0.1\ndepends = Base >= 1.0"),
+            ("rules.txt", "[max_hp]\nfactor = 1"),
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 5\ndurability = 10"),
+            ("enemies.txt", "[Goblin Chief]\narchetype = Goblin\nparty = Goblins\nstats = str 6\nequipment = Thorny Ring"),
+        ]);
+
+        let mut library = ContentLibrary::new(root.to_str().unwrap());
+        let notes = library.load().unwrap();
+        assert!(notes.contains(&"Expansion overrides item Rusty Sword of Base.".to_string()));
+        assert!(notes.contains(&"Expansion overrides enemy Goblin Chief of Base.".to_string()));
+        assert_eq!(library.iter_packs().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["Base", "Expansion"]);
+        // Rules are layered: Base sets the base, Expansion the factor
+        assert_eq!(library.get_ruleset().max_hp.base, 50);
+        assert_eq!(library.get_ruleset().max_hp.factor, 1f64);
+
+        let chief = library.enemy("Goblin Chief").unwrap();
+        assert_eq!(chief.calculate_current_stats().str, 6);
+        assert_eq!(chief.party(), "Goblins");
+        assert_eq!(chief.iter_equipment().count(), 2);
+        let sword = library.item("Rusty Sword").unwrap();
+        assert_eq!(sword.get_weapon_stats().unwrap().base_damage, 5);
+        assert!(library.reload_if_changed().is_none());
+
+        // A broken pack is refused with a report, and the previous content stays
+        write_pack(&root, "a_expansion", &[
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 7\nreactions = Spikes"),
+        ]);
+        let report = library.reload_if_changed().unwrap().unwrap_err();
+        assert!(report.contains("Item Rusty Sword: There is no script reaction Spikes."), "{}", report);
+        assert_eq!(library.item("Rusty Sword").unwrap().get_weapon_stats().unwrap().base_damage, 5);
+
+        // Fixing the pack reloads it, and refreshes items already equipped
+        let mut combat = Combat::from_participants(vec![chief]);
+        let chief = combat.get_character_mut(&"Goblin Chief".to_string()).unwrap();
+        let index = chief.iter_equipment().position(|e| e.get_name() == "Rusty Sword").unwrap();
+        chief.wear_equipment(index, 4);
+        write_pack(&root, "a_expansion", &[
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 7\ndurability = 10"),
+        ]);
+        assert!(library.reload_if_changed().unwrap().is_ok());
+        assert!(library.refresh(&mut combat).is_empty());
+        let sword = combat.get_character("Goblin Chief").unwrap().iter_equipment().next().unwrap();
+        assert_eq!(sword.get_weapon_stats().unwrap().base_damage, 7);
+        assert_eq!(sword.get_durability(), 6);
+
+        // Only items built from packs are rebuilt, and only if they can still be equipped
+        let mut goblin = Character::new("Goblin".to_string(), None, parse_stats("str 3").unwrap());
+        goblin.equip(Equipment::new("Rusty Sword".to_string(), EquipmentType::Weapon, parse_stats("").unwrap())).unwrap();
+        combat.add_participant(goblin, None).unwrap();
+        write_pack(&root, "a_expansion", &[
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 7\nrequires = dex 9, str 9, grt 9, wil 9, cha 9, int 9"),
+            ("archetypes.txt", "[Goblin]\nstats = dex 4, str 3, grt 3"),
+        ]);
+        assert!(library.reload_if_changed().unwrap().is_ok());
+        assert_eq!(library.refresh(&mut combat), vec!["Goblin Chief can't equip Rusty Sword anymore (Not meeting \
+            the stat requirement.), so it was moved to their inventory.".to_string()]);
+        let chief = combat.get_character("Goblin Chief").unwrap();
+        assert_eq!(chief.iter_equipment().map(|e| e.get_name().as_str()).collect::<Vec<_>>(), vec!["Thorny Ring"]);
+        assert_eq!(chief.inventory().get(0).unwrap().name(), "Rusty Sword");
+        let goblin = combat.get_character("Goblin").unwrap().iter_equipment().next().unwrap();
+        assert!(goblin.get_weapon_stats().is_none());
+
+        // Dependencies must be met
+        write_pack(&root, "a_expansion", &[("pack.txt", "name = Expansion\nversion = 0.1\ndepends = Base >= 2")]);
+        let report = library.reload_if_changed().unwrap().unwrap_err();
+        assert_eq!(report, "Expansion depends on Base 2.0.0 or later, but found 1.0.0.");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_world_content() {
+        use crate::world::World;
+
+        let root = env::temp_dir().join(format!("world_content_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write_pack(&root, "base", &[
+            ("pack.txt", "name = Base\nversion = 1.0.0"),
+            ("rules.txt", "[max_hp]\nbase = 50"),
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 2"),
+        ]);
+
+        // The world plays by the content's rules, without activating them for the whole game
+        let mut world = World::new();
+        world.set_content(ContentLibrary::new(root.to_str().unwrap())).unwrap();
+        assert_eq!(world.get_ruleset().unwrap().max_hp.base, 50);
+        assert_eq!(crate::ruleset::ruleset().max_hp.base, Ruleset::default().max_hp.base);
+
+        let mut goblin = Character::new("Goblin".to_string(), None, parse_stats("str 3").unwrap());
+        goblin.equip(world.get_content().unwrap().item("Rusty Sword").unwrap()).unwrap();
+        world.add_context(Box::new(Combat::from_participants(vec![goblin])));
+        assert!(world.reload_content().is_none());
+        world.process_turn().unwrap();
+
+        // Changed packs are picked up between turns, refreshing equipped items
+        write_pack(&root, "base", &[
+            ("rules.txt", "[max_hp]\nbase = 150"),
+            ("items.txt", "[Rusty Sword]\ntype = weapon\ndamage = PHY Slash 12"),
+        ]);
+        assert!(world.reload_content().unwrap().is_ok());
+        assert_eq!(world.get_ruleset().unwrap().max_hp.base, 150);
+        let goblin = world.iter_contexts().next().unwrap().get_character("Goblin").unwrap();
+        assert_eq!(goblin.iter_equipment().next().unwrap().get_weapon_stats().unwrap().base_damage, 12);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+//! Contains the demo encounter shared by the binaries: `combat_demo` shows it turn by turn and
+//! `simulate` runs it many times over, so both are about the very same fight.
+
+use crate::characters::{CharStat, Character, Stats};
+use crate::combat::{Combat, DamageType};
+use crate::effects::StatAdditive;
+use crate::equipment::{Equipment, EquipmentType};
+use crate::mov::Counter;
+
+/// Builds a character named `name` with all stats at `3 * f`
+pub fn demo_character(name: String, f: f64) -> Character {
+    let stat = (3f64 * f) as i64;
+    Character::new(name, None, Stats {
+        str: stat,
+        dex: stat,
+        grt: stat,
+        wil: stat,
+        int: stat,
+        cha: stat,
+    })
+}
+
+/// Builds a ring giving its wearer a special counter ability (and some GRT to take the hits)
+pub fn counter_ring() -> Equipment {
+    let mut eq = Equipment::new("Counter Ring".to_string(), EquipmentType::Ring, Stats {
+        dex: 5,
+        str: 0,
+        grt: 0,
+        wil: 0,
+        cha: 0,
+        int: 0,
+    });
+    eq.add_reaction(Box::new(
+        Counter::new(DamageType::PHY(""), 0f64, 1f64)));
+    eq.add_passive_effect(Box::new(StatAdditive(CharStat::GRT(10))));
+    eq
+}
+
+/// Builds the demo encounter: Lindtbert with his counter ring against two baddies
+pub fn build_demo_combat() -> Combat {
+    let mut lindtbert = demo_character("Lindtbert".to_string(), 3f64);
+    lindtbert.set_party("Best Friends".to_string());
+    lindtbert.equip(counter_ring()).unwrap();
+
+    let mut party = vec![lindtbert];
+    let mut baddies = vec![demo_character("Baddie".to_string(), 1f64), demo_character("Baddie 2".to_string(), 1f64)];
+    for char in baddies.iter_mut() {
+        char.set_party("Baddies!".to_string());
+    }
+    // conjoin both groups into one encounter list
+    party.extend(baddies);
+    Combat::from_participants(party)
+}
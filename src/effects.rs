@@ -1,10 +1,11 @@
 use std::fmt::{Display, Formatter};
-use crate::characters::{CharStat, Character, Stats};
-use crate::combat::DamageType;
+use crate::characters::{CharStat, Character, CharacterId, CharacterRef, GameStats, Stats};
+use crate::combat::{Damage, DamageType};
 
 
-/// Effect Trait flexibly describes functionality of (passive) effects affecting a character
-pub trait Effect {
+/// Effect Trait flexibly describes functionality of (passive) effects affecting a character.
+/// Effects are `Send` and `Sync`, so characters (and whole combats) can move between threads.
+pub trait Effect: EffectClone + Send + Sync {
 
     /// Describe this effect briefly
     fn describe(&self) -> String;
@@ -14,6 +15,19 @@ pub trait Effect {
         // Default Implementation is to do nothing
     }
 
+    /// Called to develop the 'effective' current game stats of this character, after they have
+    /// been derived from the current stats. Effects are applied in `effect_order`.
+    fn apply_to_game_stats(&self, _game_stats: &mut GameStats) {
+        // Default Implementation is to do nothing
+    }
+
+    /// Called when the affected character receives `damage`, before defenses are applied.
+    /// Returns the adjusted damage `amount` (which may already be adjusted by other effects).
+    fn on_damage_receive(&self, _damage: &Damage, amount: i64) -> i64 {
+        // Default Implementation is to not adjust damage
+        amount
+    }
+
     /// This function is called for every effect once for every turn
     fn process_turn(&self, target: &mut Character) {
         // Default Implementation is to do nothing
@@ -27,6 +41,35 @@ pub trait Effect {
         false
     }
 
+    /// If this returns the ID of a character, the affected character is **taunted** by them,
+    /// i.e. forced to target them with their maneuvers (as long as possible).
+    fn taunted_by(&self) -> Option<CharacterId> {
+        None
+    }
+
+    /// Identifies the kind of this effect. Together with the effect's source, the ID decides
+    /// which effects stack with each other. Default is the effect's description.
+    fn id(&self) -> String {
+        self.describe()
+    }
+
+    /// Decides what happens when this effect is applied to a character already affected by an
+    /// effect with the same ID and source. Default is to refresh the existing effect's duration.
+    fn stacking(&self) -> StackingPolicy {
+        StackingPolicy::Refresh
+    }
+
+    /// Compares effects with the same ID under the `Strongest` stacking policy. Default is 0.
+    fn strength(&self) -> i64 {
+        0
+    }
+
+    /// Negative effects (ailments) hamper the affected character and are removed by purges.
+    /// Default is a positive effect.
+    fn is_negative(&self) -> bool {
+        false
+    }
+
     /// Allows effects to specify a priority number. The higher the priority number specified in
     /// `effect_order`, the **later in resolution order** will these effects applied.
     fn effect_order(&self) -> i64 {
@@ -36,9 +79,82 @@ pub trait Effect {
 
 }
 
-/// A timed effect, described with a borrowed effect and a numer of turns this effect will remain
-/// active
-pub struct TimedEffect<'a>(&'a dyn Effect, i64);
+/// Allows cloning boxed effects, e.g. when the same effect is given to multiple characters.
+/// Implemented automatically for every `Effect` that is `Clone`.
+pub trait EffectClone {
+    fn clone_box(&self) -> Box<dyn Effect>;
+}
+
+impl<T: 'static + Effect + Clone> EffectClone for T {
+    fn clone_box(&self) -> Box<dyn Effect> {
+        Box::new(self.clone())
+    }
+}
+
+/// Describes what happens when an effect is applied to a character that is already affected by
+/// an effect of the same identity
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StackingPolicy {
+    /// The existing effect stays, and its duration is refreshed
+    Refresh,
+    /// The effect stacks up to the given number of times, refreshing the duration of all stacks
+    Intensity(usize),
+    /// Only the strongest effect is kept (see `Effect::strength`)
+    Strongest,
+    /// Every application is an independent effect with its own duration
+    Independent,
+}
+
+/// Identifies an effect by its ID and the character it stems from (if any)
+#[derive(Clone, PartialEq, Debug)]
+pub struct EffectIdentity {
+    pub id: String,
+    pub source: Option<CharacterId>,
+}
+
+/// Selects which effects of a holder are targeted, e.g. with `EntityPointer::Effect`
+#[derive(Clone, PartialEq, Debug)]
+pub enum EffectSelector {
+    /// All effects of the holder
+    All,
+    /// Only the effects of the given identity
+    Only(EffectIdentity),
+}
+
+impl EffectSelector {
+    /// Checks whether `timed` is one of the selected effects
+    pub fn matches(&self, timed: &TimedEffect) -> bool {
+        match self {
+            EffectSelector::All => true,
+            EffectSelector::Only(identity) => timed.identity() == *identity,
+        }
+    }
+}
+
+/// A timed effect on a character, with the number of turns it remains active and the character
+/// it stems from (if any)
+pub struct TimedEffect {
+    pub effect: Box<dyn Effect>,
+    pub remaining: i64,
+    pub source: Option<CharacterId>,
+}
+
+impl TimedEffect {
+    pub fn new(effect: Box<dyn Effect>, remaining: i64, source: Option<CharacterId>) -> TimedEffect {
+        TimedEffect {
+            effect,
+            remaining,
+            source,
+        }
+    }
+
+    pub fn identity(&self) -> EffectIdentity {
+        EffectIdentity {
+            id: self.effect.id(),
+            source: self.source,
+        }
+    }
+}
 
 
 
@@ -46,6 +162,7 @@ pub struct TimedEffect<'a>(&'a dyn Effect, i64);
 
 
 /// Applies the value wrapped with `CharStat` additively
+#[derive(Clone)]
 pub struct StatAdditive(pub CharStat);
 
 impl Effect for StatAdditive {
@@ -55,6 +172,11 @@ impl Effect for StatAdditive {
             self.0.get_stat_name())
     }
 
+    /// Stat changes lowering a stat are negative
+    fn is_negative(&self) -> bool {
+        self.0.get_value() < 0
+    }
+
     /// Overwrite stat change function to add the delta value of this stat change to the input
     /// `stats` to build.
     fn apply_to_stats(&self, stats: &mut Stats) {
@@ -81,13 +203,63 @@ impl Effect for StatAdditive {
             _ => {}
         }
     }
+
+    /// Game stat changes are applied directly to the derived game stats
+    fn apply_to_game_stats(&self, game_stats: &mut GameStats) {
+        game_stats.add(&self.0);
+    }
+}
+
+/// Changes a game stat by the value wrapped with `CharStat` in percent, e.g. `MHP(15)` for
+/// +15% MHP
+#[derive(Clone)]
+pub struct StatPercentage(pub CharStat);
+
+impl Effect for StatPercentage {
+    /// Quick Format: e.g. +15% MHP
+    fn describe(&self) -> String {
+        format!("{}{}% {}", if self.0.get_value() > 0 { "+" } else { "-" }, self.0.get_value().abs(),
+            self.0.get_stat_name())
+    }
+
+    fn is_negative(&self) -> bool {
+        self.0.get_value() < 0
+    }
+
+    fn apply_to_game_stats(&self, game_stats: &mut GameStats) {
+        game_stats.add_percentage(&self.0);
+    }
+
+    /// Percentages apply after all additive changes
+    fn effect_order(&self) -> i64 {
+        10
+    }
+}
+
+/// Taunts the affected character, forcing them to target the referenced character
+#[derive(Clone)]
+pub struct Taunted(pub CharacterRef);
+
+impl Effect for Taunted {
+    fn describe(&self) -> String {
+        format!("Taunted by {}", self.0.name())
+    }
+
+    fn taunted_by(&self) -> Option<CharacterId> {
+        Some(self.0.id())
+    }
+
+    fn is_negative(&self) -> bool {
+        true
+    }
 }
 
 /// The fundamental defense values are set by physical and magical defense. This struct represents
 /// a resistance to a **damage subtype** defined by its String name and the resistance involved
 /// A **negative resistance number** can be used as an additional **vulnerability** to that damage
 /// type
-struct DamageResistance(DamageType, f64);
+#[derive(Clone)]
+pub struct DamageResistance(pub DamageType, pub f64);
 
 impl Effect for DamageResistance {
     fn describe(&self) -> String {
@@ -97,6 +269,32 @@ impl Effect for DamageResistance {
                 if self.1 > 0f64 {"RES"} else {"VUL"}, self.0)
     }
 
+    /// Resistances (and vulnerabilities) to the same damage type don't add up
+    fn id(&self) -> String {
+        format!("{} to {}", if self.1 > 0f64 {"RES"} else {"VUL"}, self.0)
+    }
+
+    fn stacking(&self) -> StackingPolicy {
+        StackingPolicy::Strongest
+    }
+
+    fn strength(&self) -> i64 {
+        (self.1.abs() * 100f64).floor() as i64
+    }
+
+    /// Vulnerabilities are negative
+    fn is_negative(&self) -> bool {
+        self.1 < 0f64
+    }
+
+    /// Reduces (or increases, for vulnerabilities) incoming damage of covered damage types
+    fn on_damage_receive(&self, damage: &Damage, amount: i64) -> i64 {
+        if self.0.covers(damage.dmg_type()) {
+            (amount as f64 * (1f64 - self.1)).floor() as i64
+        } else {
+            amount
+        }
+    }
 
 
     /// Ensure this (multiplicative) effect is processed only after the more basic (additive)
@@ -143,4 +341,70 @@ mod tests {
 
         assert_eq!(character.calculate_current_stats().cha, cha_pre);
     }
+
+    #[test]
+    fn test_game_stat_modifiers() {
+        use crate::equipment::{Equipment, EquipmentType};
+
+        let mut character = test_character();
+        let pre = character.calculate_game_stats();
+
+        let mut amulet = Equipment::new("Amulet".to_string(), EquipmentType::Accessory, Stats {
+            str: 0, dex: 0, grt: 0, wil: 0, int: 0, cha: 0,
+        });
+        amulet.add_passive_effect(Box::new(StatPercentage(CharStat::MHP(15))));
+        amulet.add_passive_effect(Box::new(StatAdditive(CharStat::PDF(20))));
+        amulet.add_passive_effect(Box::new(StatAdditive(CharStat::MHP(100))));
+        character.equip(amulet).unwrap();
+        let post = character.calculate_game_stats();
+
+        assert_eq!(post.pdf(), pre.pdf() + 20);
+        // Percentages apply after additive modifiers
+        assert_eq!(post.mhp(), ((pre.mhp() + 100) as f64 * 1.15).floor() as i64);
+        assert_eq!(post.mob(), pre.mob());
+    }
+
+    /// Stacks in intensity, up to three times
+    #[derive(Clone)]
+    struct Poison;
+
+    impl Effect for Poison {
+        fn describe(&self) -> String {
+            "Poison".to_string()
+        }
+
+        fn stacking(&self) -> StackingPolicy {
+            StackingPolicy::Intensity(3)
+        }
+    }
+
+    #[test]
+    fn test_stacking_policies() {
+        let mut character = test_character();
+        let mage = Some(CharacterId::generate());
+
+        // Refreshing the same buff from the same source keeps a single copy
+        character.apply_timed_effect(Box::new(StatAdditive(CharStat::DEX(2))), 2, mage);
+        character.apply_timed_effect(Box::new(StatAdditive(CharStat::DEX(2))), 4, mage);
+        character.apply_timed_effect(Box::new(StatAdditive(CharStat::DEX(2))), 1, None);
+        assert_eq!(character.summarize_timed_effects(), vec![
+            ("+2 DEX".to_string(), 1, 4), ("+2 DEX".to_string(), 1, 1)]);
+
+        // Poison stacks up to three times
+        for turns in 1..=5 {
+            character.apply_timed_effect(Box::new(Poison), turns, mage);
+        }
+        assert_eq!(character.summarize_timed_effects()[2], ("Poison".to_string(), 3, 5));
+
+        // Only the strongest resistance is kept
+        let resistance = |r| Box::new(DamageResistance(DamageType::MAG("Fire"), r));
+        character.apply_timed_effect(resistance(0.2), 3, mage);
+        character.apply_timed_effect(resistance(0.5), 3, mage);
+        character.apply_timed_effect(resistance(0.1), 3, mage);
+        let resistances: Vec<i64> = character.iter_timed_effects()
+            .filter(|t| t.effect.id().starts_with("RES to"))
+            .map(|t| t.effect.strength())
+            .collect();
+        assert_eq!(resistances, vec![50]);
+    }
 }
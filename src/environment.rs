@@ -0,0 +1,193 @@
+//! Contains the environment of a combat, acting as an entity of its own.
+//!
+//! An environment is made of any number of **conditions**, which either
+//!
+//! * act every turn, injecting their own actions (sourced from `EntityPointer::Environment`)
+//! after all characters acted, e.g. burning ground or a storm, or
+//! * apply a global `Effect` to every participant, e.g. darkness or sacred ground.
+
+use crate::characters::{CharStat, Character, GameStats};
+use crate::combat::{Action, ActionEffect, Damage, DamageType, EntityPointer};
+use crate::effects::{Effect, StatAdditive};
+use crate::random::SeededRng;
+use crate::text::{MakesWords, TextFormatting};
+use crate::world::WorldContext;
+
+/// Describes a single condition of an environment
+#[derive(Clone, Copy)]
+pub enum Condition {
+    /// Burns every standing character each turn for the given (magical fire) damage
+    BurningGround(i64),
+    /// Strikes a random standing character with the given `ZAP` damage every `interval` turns
+    Storm { damage: i64, interval: u64 },
+    /// Lowers every character's MOB by the given amount
+    Darkness(i64),
+    /// Boosts every character's WIL by the given amount
+    SacredGround(i64),
+}
+
+impl Condition {
+    /// Describe this condition briefly
+    pub fn describe(&self) -> String {
+        match self {
+            Condition::BurningGround(damage) => format!("Burning Ground ({} Fire per turn)", damage),
+            Condition::Storm { damage, interval } => format!("Storm ({} Lightning every {} turns)", damage, interval),
+            Condition::Darkness(amount) => format!("Darkness (-{} MOB)", amount),
+            Condition::SacredGround(amount) => format!("Sacred Ground (+{} WIL)", amount),
+        }
+    }
+
+    /// Returns the effect this condition has on every participant, if any
+    fn global_effect(&self) -> Option<Box<dyn Effect>> {
+        match self {
+            Condition::Darkness(amount) => Some(Box::new(Darkness(*amount))),
+            Condition::SacredGround(amount) => Some(Box::new(StatAdditive(CharStat::WIL(*amount)))),
+            _ => None,
+        }
+    }
+
+    /// Builds the actions this condition takes in the given `turn` on `context`
+    fn act(&self, turn: u64, context: &dyn WorldContext, rng: &mut SeededRng) -> Vec<Action> {
+        let standing = context.find_characters(&|c: &Character| !c.is_defeated());
+
+        match self {
+            // Everyone burns on their own, so the damage isn't split as in group attacks
+            Condition::BurningGround(damage) => standing.iter()
+                .map(|c| Action::from_source(EntityPointer::Environment,
+                                             ActionEffect::Attack(Damage(DamageType::MAG("Fire"), *damage)),
+                                             c.as_target()))
+                .collect(),
+            Condition::Storm { damage, interval } if *interval > 0 && turn % interval == 0 && !standing.is_empty() => {
+                let struck = standing[rng.range(0, standing.len() as i64 - 1) as usize];
+                vec![Action::from_source(EntityPointer::Environment,
+                                         ActionEffect::Attack(Damage(DamageType::ZAP("Lightning"), *damage)),
+                                         struck.as_target())]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// The environment a combat takes place in
+pub struct Environment {
+    /// Name of this environment, e.g. "Volcano"
+    name: String,
+    /// All conditions of this environment, acting in order
+    conditions: Vec<Condition>,
+}
+
+impl Environment {
+    pub fn new(name: String) -> Environment {
+        Environment {
+            name,
+            conditions: vec![],
+        }
+    }
+
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn iter_conditions(&self) -> core::slice::Iter<Condition> {
+        self.conditions.iter()
+    }
+
+    /// Checks whether this environment has no conditions at all, i.e. never does anything
+    pub fn is_calm(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// Returns the effects this environment has on every participant
+    pub fn global_effects(&self) -> Vec<Box<dyn Effect>> {
+        self.conditions.iter().filter_map(|c| c.global_effect()).collect()
+    }
+
+    /// Builds the actions of all conditions of this environment for the given `turn`
+    pub fn act(&self, turn: u64, context: &dyn WorldContext, rng: &mut SeededRng) -> Vec<Action> {
+        self.conditions.iter().flat_map(|c| c.act(turn, context, rng)).collect()
+    }
+}
+
+/// Environments are listed with all of their conditions
+impl MakesWords for Environment {
+    fn format_words(&self, formatting: TextFormatting) -> Vec<(String, usize)> {
+        let mut output = formatting.to_words(format!("{}:", self.name), "environment-target", None);
+        for condition in self.conditions.iter() {
+            output.extend(formatting.to_words(condition.describe(), "effect", None));
+        }
+        output
+    }
+}
+
+/// Lowers the affected character's MOB
+#[derive(Clone)]
+pub struct Darkness(pub i64);
+
+impl Effect for Darkness {
+    fn describe(&self) -> String {
+        format!("Darkness (-{} MOB)", self.0)
+    }
+
+    fn apply_to_game_stats(&self, game_stats: &mut GameStats) {
+        game_stats.add(&CharStat::MOB(-self.0));
+    }
+
+    fn is_negative(&self) -> bool {
+        true
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::Stats;
+    use crate::combat::Combat;
+    use super::*;
+
+    fn test_character(name: &str, party: &str) -> Character {
+        let mut character = Character::new(name.to_string(), None, Stats {
+            str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+        character.set_party(party.to_string());
+        character
+    }
+
+    fn build_combat(condition: Condition) -> Combat {
+        let mut combat = Combat::from_participants(vec![
+            test_character("Lindtbert", "Best Friends"),
+            test_character("Baddie", "Baddies!"),
+        ]);
+        let mut environment = Environment::new("Test Site".to_string());
+        environment.add_condition(condition);
+        combat.set_environment(environment);
+        combat
+    }
+
+    #[test]
+    fn test_global_effects() {
+        let mut combat = build_combat(Condition::SacredGround(5));
+        combat.add_participant(test_character("Imp", "Baddies!"), None).unwrap();
+        combat.process_turn(None).unwrap();
+
+        // Everyone on sacred ground is affected, including newcomers
+        assert_eq!(combat.get_character("Lindtbert").unwrap().calculate_current_stats().wil, 7);
+        assert_eq!(combat.get_character("Imp").unwrap().calculate_current_stats().wil, 7);
+    }
+
+    #[test]
+    fn test_storm() {
+        let mut combat = build_combat(Condition::Storm { damage: 30, interval: 2 });
+        let total_mp = |combat: &Combat| combat.iter_characters().map(|c| c.mp()).sum::<i64>();
+        let max_mp = total_mp(&combat);
+
+        // The storm only strikes every other turn, zapping someone's MP
+        combat.process_turn(None).unwrap();
+        assert_eq!(total_mp(&combat), max_mp);
+        combat.process_turn(None).unwrap();
+        assert!(total_mp(&combat) < max_mp);
+    }
+}
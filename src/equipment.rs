@@ -4,14 +4,17 @@
 //! `Reaction`s.
 
 use std::fmt::Display;
+use std::sync::Arc;
+use crate::battlefield::Range;
 use crate::characters::{Stats, Character};
+use crate::combat::DamageType;
 use crate::effects::Effect;
-use crate::mov::{Maneuver, Reaction};
+use crate::mov::{Maneuver, Reaction, WeaponAttack};
 use crate::text::{InfoLine, TextFormatting};
 
 /// Describes different types of equipment. Each character is limited by equipment types, e.g.
 /// one person cannot wear more than one Helmet.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum EquipmentType {
     Weapon,
     Head,
@@ -54,6 +57,12 @@ impl EquipmentType {
         }
     }
 
+    /// Armour protects its wearer, and wears down while absorbing damage
+    pub fn is_armour(&self) -> bool {
+        matches!(self, EquipmentType::Head | EquipmentType::Chest | EquipmentType::Arms
+            | EquipmentType::Hands | EquipmentType::Feet)
+    }
+
     /// Convenience function performs a equipment type check on a given `character`:
     ///
     /// * If the character could equip another item of this type, returns `true`
@@ -72,6 +81,196 @@ impl Display for EquipmentType {
     }
 }
 
+/// Describes the rarity tier of equipment. Rarer equipment rolls more random affixes when
+/// generated as loot (see `loot::LootGenerator`).
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    /// All rarity tiers, from most common to rarest
+    pub const ALL: [Rarity; 5] = [Rarity::Common, Rarity::Uncommon, Rarity::Rare, Rarity::Epic,
+        Rarity::Legendary];
+
+    /// Returns the number of random affixes rolled for equipment of this rarity
+    pub fn num_affixes(&self) -> usize {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Uncommon => 1,
+            Rarity::Rare => 2,
+            Rarity::Epic => 3,
+            Rarity::Legendary => 4,
+        }
+    }
+
+    /// Returns this rarity's shortcode as a 1-char string
+    pub fn shortcode(&self) -> &'static str {
+        match self {
+            Rarity::Common => "C",
+            Rarity::Uncommon => "U",
+            Rarity::Rare => "R",
+            Rarity::Epic => "E",
+            Rarity::Legendary => "L",
+        }
+    }
+}
+
+impl Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Describes how strongly each base stat of the wielder feeds into a value (e.g. weapon damage).
+/// Every coefficient is multiplied with the respective stat, and all products are summed up.
+#[derive(Clone, Copy, Default)]
+pub struct StatScaling {
+    pub dex: f64,
+    pub str: f64,
+    pub grt: f64,
+    pub wil: f64,
+    pub cha: f64,
+    pub int: f64,
+}
+
+impl StatScaling {
+    /// Calculates the scaled bonus value for the given `stats`
+    pub fn scale(&self, stats: &Stats) -> i64 {
+        let scaled = stats.dex as f64 * self.dex
+            + stats.str as f64 * self.str
+            + stats.grt as f64 * self.grt
+            + stats.wil as f64 * self.wil
+            + stats.cha as f64 * self.cha
+            + stats.int as f64 * self.int;
+
+        scaled.floor() as i64
+    }
+}
+
+/// Describes the offensive profile of a `Weapon`. Any equipment with weapon stats provides its
+/// wielder a `WeaponAttack` maneuver built from these values.
+#[derive(Clone, Copy)]
+pub struct WeaponStats {
+    /// Damage dealt before any stat scaling is applied
+    pub base_damage: i64,
+    /// Type of damage dealt, incl. its subtype (e.g. `DamageType::PHY("Slash")`)
+    pub damage_type: DamageType,
+    /// Scales the weapon's damage with the wielder's current stats
+    pub scaling: StatScaling,
+    /// Number of targets a single attack of this weapon can reach
+    pub target_count: usize,
+    /// How far the weapon reaches
+    pub range: Range,
+}
+
+impl WeaponStats {
+    /// Calculates the total damage this weapon deals when wielded by a character with `stats`.
+    /// Ensures minimum damage.
+    pub fn damage_for(&self, stats: &Stats) -> i64 {
+        (self.base_damage + self.scaling.scale(stats)).max(1)
+    }
+}
+
+/// Describes a bonus granted by an `ItemSet` once a character wears enough pieces of the set.
+pub struct SetBonus {
+    /// Number of equipped set pieces needed to activate this bonus
+    pieces_required: usize,
+    /// Passive effects granted while this bonus is active
+    passive_effects: Vec<Box<dyn Effect>>,
+    /// Moves granted while this bonus is active
+    moves: Vec<Box<dyn Maneuver>>,
+    /// Reactions granted while this bonus is active
+    reactions: Vec<Box<dyn Reaction>>,
+}
+
+impl SetBonus {
+    pub fn new(pieces_required: usize) -> SetBonus {
+        SetBonus {
+            pieces_required,
+            passive_effects: vec![],
+            moves: vec![],
+            reactions: vec![],
+        }
+    }
+
+    pub fn add_passive_effect(&mut self, effect: Box<dyn Effect>) {
+        self.passive_effects.push(effect);
+    }
+
+    pub fn add_move(&mut self, mov: Box<dyn Maneuver>) {
+        self.moves.push(mov);
+    }
+
+    pub fn add_reaction(&mut self, reaction: Box<dyn Reaction>) {
+        self.reactions.push(reaction);
+    }
+
+    pub fn pieces_required(&self) -> usize {
+        self.pieces_required
+    }
+}
+
+/// Describes a set of equipment. Pieces of the same set share one `ItemSet` (via `Arc`), and
+/// wearing several pieces at once unlocks the set's `SetBonus`es.
+///
+/// Set names are considered the **unique ID** of a set.
+pub struct ItemSet {
+    /// Name of the set, e.g. "Stormguard"
+    name: String,
+    /// Total number of pieces that belong to this set
+    num_pieces: usize,
+    /// All bonuses of this set, each unlocked at their own number of pieces
+    bonuses: Vec<SetBonus>,
+}
+
+impl ItemSet {
+    pub fn new(name: String, num_pieces: usize) -> ItemSet {
+        ItemSet {
+            name,
+            num_pieces,
+            bonuses: vec![],
+        }
+    }
+
+    pub fn add_bonus(&mut self, bonus: SetBonus) {
+        self.bonuses.push(bonus);
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn num_pieces(&self) -> usize {
+        self.num_pieces
+    }
+
+    /// Returns all bonuses that are active with `equipped` pieces of this set
+    pub fn active_bonuses(&self, equipped: usize) -> impl Iterator<Item=&SetBonus> {
+        self.bonuses.iter().filter(move |b| b.pieces_required <= equipped)
+    }
+
+    // ~~~ Listy Getters ~~~
+
+    pub fn add_passive_effects<'a>(&'a self, equipped: usize, effects: &mut Vec<&'a Box<dyn Effect>>) {
+        self.active_bonuses(equipped).for_each(|b| effects.extend(b.passive_effects.iter()));
+    }
+
+    pub fn add_moves<'a>(&'a self, equipped: usize, moves: &mut Vec<Box<dyn Maneuver + 'a>>) {
+        self.active_bonuses(equipped)
+            .for_each(|b| b.moves.iter().for_each(|m| moves.push(Box::new(m.as_ref()))));
+    }
+
+    pub fn add_reactions<'a>(&'a self, equipped: usize, reactions: &mut Vec<&'a dyn Reaction>) {
+        self.active_bonuses(equipped)
+            .for_each(|b| b.reactions.iter().for_each(|r| reactions.push(r.as_ref())));
+    }
+}
+
 /// Describes all equipments in game.
 pub struct Equipment {
     /// Name of the equipment
@@ -95,6 +294,21 @@ pub struct Equipment {
     moves: Vec<Box<dyn Maneuver>>,
     /// Equipment can make additional reactions available
     reactions: Vec<Box<dyn Reaction>>,
+    /// If set, this equipment can be used to attack (see `WeaponAttack`)
+    weapon_stats: Option<WeaponStats>,
+    /// If set, this equipment is a piece of the referenced set
+    item_set: Option<Arc<ItemSet>>,
+    /// Rarity tier of this equipment
+    rarity: Rarity,
+    /// Current durability of this equipment. Equipment at 0 durability is **broken** and stops
+    /// providing effects, moves and reactions until it's repaired.
+    durability: i64,
+    /// Maximum durability of this equipment. Equipment with a maximum durability of `0` never
+    /// wears down.
+    max_durability: i64,
+    /// If this equipment was built from a content pack, the names of the pack and of the item
+    /// definition it was built from
+    origin: Option<(String, String)>,
 }
 
 impl Equipment {
@@ -111,9 +325,24 @@ impl Equipment {
             passive_effects: vec![],
             moves: vec![],
             reactions: vec![],
+            weapon_stats: None,
+            item_set: None,
+            // Unless specified otherwise, equipment is common
+            rarity: Rarity::Common,
+            // By default, equipment is indestructible
+            durability: 0,
+            max_durability: 0,
+            origin: None,
         }
     }
 
+    /// Builds a `Weapon` type equipment that attacks as described by `weapon_stats`
+    pub fn weapon(name: String, stat_requirements: Stats, weapon_stats: WeaponStats) -> Equipment {
+        let mut weapon = Equipment::new(name, EquipmentType::Weapon, stat_requirements);
+        weapon.set_weapon_stats(weapon_stats);
+        weapon
+    }
+
     pub fn add_passive_effect(&mut self, effect: Box<dyn Effect>) {
         self.passive_effects.push(effect);
     }
@@ -126,8 +355,79 @@ impl Equipment {
         self.reactions.push(reaction);
     }
 
+    pub fn set_weapon_stats(&mut self, weapon_stats: WeaponStats) {
+        self.weapon_stats = Some(weapon_stats);
+    }
+
+    pub fn set_rarity(&mut self, rarity: Rarity) {
+        self.rarity = rarity;
+    }
+
+    /// Marks this equipment as built from the item definition named `definition` of the content
+    /// pack named `pack`
+    pub fn set_origin(&mut self, pack: String, definition: String) {
+        self.origin = Some((pack, definition));
+    }
+
+    /// Sets the maximum durability of this equipment and fully restores it
+    pub fn set_max_durability(&mut self, max_durability: i64) {
+        self.max_durability = max_durability;
+        self.durability = max_durability;
+    }
+
+    /// Makes this equipment a piece of `item_set`
+    pub fn set_item_set(&mut self, item_set: Arc<ItemSet>) {
+        self.item_set = Some(item_set);
+    }
+
+    // ~~~~~~~~~~~~~~ Durability ~~~~~~~~~~~~~~
+
+    /// Reduces this equipment's durability by `amount` (unless it's indestructible)
+    pub fn wear(&mut self, amount: i64) {
+        if self.max_durability > 0 {
+            self.durability = (self.durability - amount).max(0);
+        }
+    }
+
+    /// Restores up to `amount` durability, but no more than the maximum durability
+    pub fn repair(&mut self, amount: i64) {
+        self.durability = (self.durability + amount).min(self.max_durability);
+    }
+
+    /// Broken equipment provides no effects, moves or reactions
+    pub fn is_broken(&self) -> bool {
+        self.max_durability > 0 && self.durability <= 0
+    }
+
+    /// Checks whether this equipment has lost any durability
+    pub fn needs_repair(&self) -> bool {
+        self.durability < self.max_durability
+    }
+
+    pub fn get_durability(&self) -> i64 {
+        self.durability
+    }
+
+    pub fn get_max_durability(&self) -> i64 {
+        self.max_durability
+    }
+
     // ~~~~~~~~~~~~~~ Getters ~~~~~~~~~~~~~~
 
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_rarity(&self) -> Rarity {
+        self.rarity
+    }
+
+    /// Returns the names of the content pack and item definition this equipment was built from,
+    /// if it was built from a content pack at all
+    pub fn get_origin(&self) -> Option<(&String, &String)> {
+        self.origin.as_ref().map(|(pack, definition)| (pack, definition))
+    }
+
     pub fn get_eq_type(&self) -> &EquipmentType {
         &self.eq_type
     }
@@ -140,11 +440,28 @@ impl Equipment {
         &self.passive_effects
     }
 
+    pub fn get_weapon_stats(&self) -> Option<&WeaponStats> {
+        self.weapon_stats.as_ref()
+    }
+
+    pub fn get_item_set(&self) -> Option<&ItemSet> {
+        self.item_set.as_deref()
+    }
+
     // ~~~ Listy Getters ~~~
 
     pub fn add_reactions<'a>(&'a self, reactions: &mut Vec<&'a dyn Reaction>) {
         self.reactions.iter().for_each(|r| reactions.push(r.as_ref()));
     }
+
+    /// Adds all maneuvers this equipment provides to `moves`. Weapons add their `WeaponAttack`
+    /// ahead of any other moves they carry.
+    pub fn add_moves<'a>(&'a self, moves: &mut Vec<Box<dyn Maneuver + 'a>>) {
+        if self.weapon_stats.is_some() {
+            moves.push(Box::new(WeaponAttack(self)));
+        }
+        self.moves.iter().for_each(|m| moves.push(Box::new(m.as_ref())));
+    }
 }
 
 impl InfoLine for Equipment {
@@ -153,9 +470,12 @@ impl InfoLine for Equipment {
         let total_type = 4 + 2 + 1;
         let total_name = len - total_type;
         let mut name = String::from(&self.name);
-        if self.name.len() < total_name {
+        if self.is_broken() {
+            name.push_str(" (broken)");
+        }
+        if name.len() < total_name {
             // Pad as needed
-            name.push_str(&" ".repeat(total_name - self.name.len()));
+            name.push_str(&" ".repeat(total_name - name.len()));
         } else {
             name.truncate(total_name-2);
             name.push_str("..");
@@ -0,0 +1,173 @@
+//! Contains the ways out of combat other than defeat: fleeing, surrender and retreat.
+//!
+//! * **Fleeing** takes a whole party out of combat, if it wins a MOB contest against the fastest
+//! opponent.
+//! * **Surrendering** takes a whole party out of combat immediately, but comes with
+//! `SurrenderConsequence`s.
+//! * **Retreating** takes a single character out of combat immediately.
+//!
+//! Characters leaving combat this way are moved into the combat's holding area. Escapes are
+//! triggered by `PlayerInput` or by a party's `EscapePolicy`.
+
+use std::fmt::{Display, Formatter};
+use crate::characters::Character;
+use crate::random::SeededRng;
+
+/// Describes how a combat ended
+#[derive(Clone, PartialEq, Debug)]
+pub enum CombatOutcome {
+    /// All other parties have been defeated by the given party
+    Victory(String),
+    /// The given party fled, leaving the last party standing
+    Fled(String),
+    /// The given party surrendered to the last party standing
+    Surrendered(String),
+    /// The last standing member of the given party retreated
+    Retreated(String),
+}
+
+impl Display for CombatOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CombatOutcome::Victory(p) => write!(f, "{} won.", p),
+            CombatOutcome::Fled(p) => write!(f, "{} fled.", p),
+            CombatOutcome::Surrendered(p) => write!(f, "{} surrendered.", p),
+            CombatOutcome::Retreated(p) => write!(f, "{} retreated.", p),
+        }
+    }
+}
+
+/// Describes what a surrendering party loses to the party it surrenders to
+#[derive(Clone, Copy)]
+pub enum SurrenderConsequence {
+    /// Every surrendering character hands over the given share of their currency
+    ForfeitCurrency(f64),
+    /// Every surrendering character hands over all items in their inventory
+    ForfeitItems,
+    /// Every surrendering character loses their remaining AP and MP
+    Exhaustion,
+}
+
+/// Describes when a party's characters try to get out of combat on their own. All thresholds are
+/// HP to Max HP ratios.
+#[derive(Clone, Copy, Default)]
+pub struct EscapePolicy {
+    /// The party surrenders once its average HP ratio drops below this value
+    pub surrender_below: Option<f64>,
+    /// The party tries to flee once its average HP ratio drops below this value
+    pub flee_below: Option<f64>,
+    /// Single characters retreat once their HP ratio drops below this value
+    pub retreat_below: Option<f64>,
+}
+
+/// Runs a flee contest between the slowest member of the `fleeing` party and the fastest of its
+/// `chasers`. Both sides roll their MOB plus a d20, ties go to the fleeing party.
+pub fn flee_contest(fleeing: &[&Character], chasers: &[&Character], rng: &mut SeededRng) -> bool {
+    let mob = |c: &&Character| c.calculate_game_stats().mob();
+
+    let flee_mob = fleeing.iter().map(mob).min().unwrap_or(0);
+    let chase_mob = match chasers.iter().map(mob).max() {
+        Some(m) => m,
+        // Nobody to chase after the fleeing party
+        None => return true,
+    };
+
+    flee_mob + rng.range(1, 20) >= chase_mob + rng.range(1, 20)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::{CharUnit, Stats};
+    use crate::combat::{Actor, Combat};
+    use crate::player::PlayerInput;
+    use crate::world::WorldContext;
+    use super::*;
+
+    fn test_character(name: &str, party: &str, dex: i64) -> Character {
+        let mut character = Character::new(name.to_string(), None, Stats {
+            str: 3, dex, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+        character.set_party(party.to_string());
+        character
+    }
+
+    fn build_combat(dex: i64) -> Combat {
+        Combat::from_participants(vec![
+            test_character("Lindtbert", "Best Friends", dex),
+            test_character("Baddie", "Baddies!", 1),
+        ])
+    }
+
+    #[test]
+    fn test_flee() {
+        // Nobody catches up with Lindtbert
+        let mut combat = build_combat(100);
+        let input = PlayerInput::Flee { party: "Best Friends".to_string() };
+
+        assert_eq!(combat.process_player_input(&input).unwrap(), "Best Friends fled.");
+        assert_eq!(combat.get_outcome(), Some(&CombatOutcome::Fled("Best Friends".to_string())));
+        assert_eq!(combat.iter_holding().next().unwrap().name(), "Lindtbert");
+        assert!(combat.get_character("Lindtbert").is_none());
+        assert!(combat.process_player_input(&input).is_err());
+    }
+
+    #[test]
+    fn test_surrender() {
+        let mut combat = build_combat(8);
+        combat.get_character_mut(&"Baddie".to_string()).unwrap().inventory_mut().add_currency(100).unwrap();
+        combat.set_surrender_consequences(vec![
+            SurrenderConsequence::ForfeitCurrency(0.5),
+            SurrenderConsequence::Exhaustion,
+        ]);
+
+        combat.surrender(&"Baddies!".to_string()).unwrap();
+
+        assert_eq!(combat.get_outcome(), Some(&CombatOutcome::Surrendered("Baddies!".to_string())));
+        assert_eq!(combat.get_stash("Best Friends").unwrap().currency(), 50);
+        let baddie = combat.take_holding().pop().unwrap();
+        assert_eq!(baddie.inventory().currency(), 50);
+        assert_eq!(baddie.ap(), 0);
+
+        // With two opponent parties left, nobody wins the surrender
+        let mut combat = build_combat(8);
+        combat.add_participant(test_character("Bystander", "Onlookers", 8), None).unwrap();
+        combat.get_character_mut(&"Baddie".to_string()).unwrap().inventory_mut().add_currency(100).unwrap();
+
+        combat.surrender(&"Baddies!".to_string()).unwrap();
+
+        assert_eq!(combat.get_outcome(), None);
+        assert!(combat.get_stash("Best Friends").is_none());
+        assert!(combat.get_stash("Onlookers").is_none());
+        assert_eq!(combat.take_holding().pop().unwrap().inventory().currency(), 100);
+    }
+
+    #[test]
+    fn test_retreat() {
+        let mut combat = build_combat(8);
+        let twin = combat.add_participant(test_character("Lindtbert", "Best Friends", 8), None).unwrap();
+
+        // Only the retreating twin leaves, even though the names are alike
+        let input = PlayerInput::Retreat { character: twin };
+        combat.process_player_input(&input).unwrap();
+        assert_eq!(combat.iter_holding().next().unwrap().id(), twin);
+        assert!(combat.get_character_by_id(twin).is_none());
+        assert!(combat.get_character("Lindtbert").is_some());
+        assert!(combat.process_player_input(&input).is_err());
+    }
+
+    #[test]
+    fn test_retreat_policy() {
+        let mut combat = build_combat(8);
+        combat.set_escape_policy("Baddies!".to_string(), EscapePolicy {
+            retreat_below: Some(0.5),
+            ..EscapePolicy::default()
+        });
+        combat.get_character_mut(&"Baddie".to_string()).unwrap().apply_directly(&CharUnit::HP(-300));
+
+        combat.process_turn(None).unwrap();
+
+        assert_eq!(combat.get_outcome(), Some(&CombatOutcome::Retreated("Baddies!".to_string())));
+        assert_eq!(combat.remaining_parties(), vec!["Best Friends"]);
+    }
+}
@@ -0,0 +1,239 @@
+//! Contains items and the inventories holding them.
+//!
+//! Every `Character` carries a slot-limited `Inventory` for items they don't have equipped, and
+//! every party shares a **stash**, which is an `Inventory` without slot limit.
+
+use crate::characters::CharacterId;
+use crate::consumables::Consumable;
+use crate::equipment::Equipment;
+use crate::text::{InfoGrid, InfoLine, TextFormatting};
+
+/// Describes anything that can be stored in an `Inventory`
+pub enum Item {
+    /// A piece of (unequipped) equipment
+    Equipment(Equipment),
+    /// A single-use item
+    Consumable(Consumable),
+}
+
+impl Item {
+    pub fn name(&self) -> &String {
+        match self {
+            Item::Equipment(e) => e.get_name(),
+            Item::Consumable(c) => c.get_name(),
+        }
+    }
+}
+
+/// Items are listed in one line each, e.g. `[HEAD] Leather Cap` or `[CNSM] Potion`
+impl InfoLine for Item {
+    fn format_line(&self, len: usize, formatting: TextFormatting) -> String {
+        match self {
+            Item::Equipment(e) => e.format_line(len, formatting),
+            // Same row format as equipment
+            Item::Consumable(c) => format!("[CNSM] {}", c.get_name().format_line(len.saturating_sub(7), formatting)),
+        }
+    }
+}
+
+/// Describes where items are located, allowing players to move items around.
+pub enum ItemLocation {
+    /// The inventory of the character with the given ID
+    Inventory(CharacterId),
+    /// The party's shared stash
+    Stash,
+}
+
+/// A list of items plus an amount of currency. Inventories can be limited to a number of slots,
+/// where every item takes up one slot. Currency does not take up slots.
+pub struct Inventory {
+    /// Maximum number of items this inventory can hold. `None` means no limit.
+    capacity: Option<usize>,
+    /// All items in this inventory
+    items: Vec<Item>,
+    /// Amount of currency in this inventory
+    currency: i64,
+}
+
+impl Inventory {
+
+    /// Builds an empty inventory with `capacity` slots
+    pub fn with_capacity(capacity: usize) -> Self {
+        Inventory {
+            capacity: Some(capacity),
+            items: vec![],
+            currency: 0,
+        }
+    }
+
+    /// Builds an empty inventory without slot limit (e.g. for a party stash)
+    pub fn unlimited() -> Self {
+        Inventory {
+            capacity: None,
+            items: vec![],
+            currency: 0,
+        }
+    }
+
+    // ~~~~~~~~~~~~~~ Items ~~~~~~~~~~~~~~
+
+    /// Checks whether this inventory has room for at least one more item
+    pub fn has_space(&self) -> bool {
+        match self.capacity {
+            None => true,
+            Some(c) => self.items.len() < c,
+        }
+    }
+
+    /// Adds an `item` to this inventory. If there's no space left, the item is handed back as
+    /// the error.
+    pub fn add(&mut self, item: Item) -> Result<(), Item> {
+        if !self.has_space() {
+            return Err(item);
+        }
+        self.items.push(item);
+        Ok(())
+    }
+
+    /// Removes and returns the item at `index` (if there is one)
+    pub fn remove(&mut self, index: usize) -> Option<Item> {
+        if index < self.items.len() {
+            Some(self.items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Item> {
+        self.items.get(index)
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<Item> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+    }
+
+    // ~~~~~~~~~~~~~~ Currency ~~~~~~~~~~~~~~
+
+    pub fn currency(&self) -> i64 {
+        self.currency
+    }
+
+    /// Adds `amount` currency to this inventory. Negative amounts are refused.
+    pub fn add_currency(&mut self, amount: i64) -> Result<(), String> {
+        if amount < 0 {
+            return Err(format!("Can't add a negative amount of currency ({}).", amount));
+        }
+        self.currency = self.currency.saturating_add(amount);
+        Ok(())
+    }
+
+    /// Takes `amount` currency out of this inventory, unless there's not enough. Negative amounts
+    /// are refused.
+    pub fn take_currency(&mut self, amount: i64) -> Result<(), String> {
+        if amount < 0 {
+            return Err(format!("Can't take a negative amount of currency ({}).", amount));
+        }
+        if amount > self.currency {
+            return Err(format!("Only {} currency available.", self.currency));
+        }
+        self.currency -= amount;
+        Ok(())
+    }
+
+    /// Moves all items and currency of `other` into this inventory. Items that don't fit
+    /// remain in `other`.
+    pub fn absorb(&mut self, other: &mut Inventory) {
+        self.currency += other.currency;
+        other.currency = 0;
+        while self.has_space() && !other.items.is_empty() {
+            self.items.push(other.items.remove(0));
+        }
+    }
+}
+
+/// Inventories are displayed as a header line (slots used, currency) followed by one line per
+/// item
+impl InfoGrid for Inventory {
+    fn display(&self, w: usize, h: usize, formatting: TextFormatting) -> Vec<String> {
+        let mut output = Vec::new();
+
+        let slots = match self.capacity {
+            None => format!("{} items", self.items.len()),
+            Some(c) => format!("{}/{} items", self.items.len(), c),
+        };
+        output.push(format!("{}, {} G", slots, self.currency).format_line(w, formatting));
+
+        for item in self.items.iter().take(h.saturating_sub(1)) {
+            output.push(item.format_line(w, formatting));
+        }
+
+        // Backfill output to ensure it has sufficient lines
+        while output.len() < h {
+            output.push(" ".to_string().repeat(w));
+        }
+
+        // Truncate if Output length is larger than requested
+        output.truncate(h);
+
+        output
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::Stats;
+    use crate::equipment::EquipmentType;
+    use super::*;
+
+    fn cap(name: &str) -> Item {
+        Item::Equipment(Equipment::new(name.to_string(), EquipmentType::Head, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        }))
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut inventory = Inventory::with_capacity(2);
+        assert!(inventory.add(cap("Cap A")).is_ok());
+        assert!(inventory.add(cap("Cap B")).is_ok());
+
+        // Full inventories hand back the item
+        match inventory.add(cap("Cap C")) {
+            Err(item) => assert_eq!(item.name(), "Cap C"),
+            Ok(_) => panic!("Inventory should be full"),
+        }
+
+        let mut stash = Inventory::unlimited();
+        stash.add_currency(30).unwrap();
+        assert!(stash.add_currency(-10).is_err());
+        stash.add(cap("Cap C")).ok();
+        inventory.remove(0);
+        inventory.absorb(&mut stash);
+        assert_eq!(inventory.len(), 2);
+        assert_eq!(inventory.currency(), 30);
+        assert!(inventory.take_currency(31).is_err());
+        assert!(inventory.take_currency(-1000).is_err());
+        assert_eq!(inventory.currency(), 30);
+
+        let lines = inventory.display(20, 4, TextFormatting::Plain);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].trim_end(), "2/2 items, 30 G");
+    }
+}
@@ -0,0 +1,240 @@
+//! Contains procedural generation of equipment (loot).
+//!
+//! A `LootGenerator` holds a **catalog of item bases** (e.g. "Iron Sword") and **weighted affix
+//! pools**. Generating loot picks a base from the catalog, rolls a `Rarity` tier, and rolls one
+//! random affix per rarity tier on top of the base item.
+//!
+//! All rolls use a `SeededRng`, so the same seed always generates the same loot.
+
+use crate::characters::{CharStat, Stats};
+use crate::combat::DamageType;
+use crate::effects::{DamageResistance, StatAdditive};
+use crate::equipment::{Equipment, EquipmentType, Rarity, WeaponStats};
+use crate::mov::Counter;
+use crate::random::SeededRng;
+
+/// Describes the blueprint of an item in the loot catalog. Generated loot starts out as a copy of
+/// its base before affixes are rolled onto it.
+pub struct ItemBase {
+    /// Name of the generated equipment
+    name: String,
+    /// Type of the generated equipment
+    eq_type: EquipmentType,
+    /// Stat requirements of the generated equipment
+    stat_requirements: Stats,
+    /// If set, generated equipment is a weapon
+    weapon_stats: Option<WeaponStats>,
+}
+
+impl ItemBase {
+    pub fn new(name: String, eq_type: EquipmentType, stat_requirements: Stats) -> ItemBase {
+        ItemBase {
+            name,
+            eq_type,
+            stat_requirements,
+            weapon_stats: None,
+        }
+    }
+
+    /// Builds an item base for a `Weapon` with the given `weapon_stats`
+    pub fn weapon(name: String, stat_requirements: Stats, weapon_stats: WeaponStats) -> ItemBase {
+        ItemBase {
+            name,
+            eq_type: EquipmentType::Weapon,
+            stat_requirements,
+            weapon_stats: Some(weapon_stats),
+        }
+    }
+
+    pub fn get_eq_type(&self) -> &EquipmentType {
+        &self.eq_type
+    }
+
+    /// Builds a new (affix-less) piece of equipment from this base
+    pub fn build(&self) -> Equipment {
+        let mut equipment = Equipment::new(self.name.clone(), self.eq_type, self.stat_requirements);
+        if let Some(weapon_stats) = self.weapon_stats {
+            equipment.set_weapon_stats(weapon_stats);
+        }
+        equipment
+    }
+}
+
+/// Describes a random property that can be rolled onto generated equipment. Each affix defines
+/// the range its value is rolled in.
+pub enum Affix {
+    /// Adds a passive `StatAdditive` to the equipment, rolled between min and max, e.g.
+    /// `Affix::Stat(CharStat::STR, 1, 3)`
+    Stat(fn(i64) -> CharStat, i64, i64),
+    /// Adds a passive `DamageResistance` to the damage type, rolled between min and max
+    Resistance(DamageType, f64, f64),
+    /// Adds a cheap `Counter` reaction against the damage type, returning a share of the incoming
+    /// damage rolled between min and max
+    Counter(DamageType, f64, f64),
+}
+
+impl Affix {
+    /// Rolls this affix' value and adds the result to `equipment`
+    fn roll_onto(&self, equipment: &mut Equipment, rng: &mut SeededRng) {
+        match self {
+            Affix::Stat(stat, min, max) => {
+                equipment.add_passive_effect(Box::new(StatAdditive(stat(rng.range(*min, *max)))));
+            }
+            Affix::Resistance(dt, min, max) => {
+                equipment.add_passive_effect(Box::new(DamageResistance(*dt, rng.range_f64(*min, *max))));
+            }
+            Affix::Counter(dt, min, max) => {
+                // Counters from affixes never reduce incoming damage
+                equipment.add_reaction(Box::new(Counter::new(*dt, 1f64, rng.range_f64(*min, *max))));
+            }
+        }
+    }
+}
+
+/// A weighted list of affixes that can be rolled onto equipment of certain types.
+pub struct AffixPool {
+    /// Equipment types this pool applies to
+    eq_types: Vec<EquipmentType>,
+    /// All affixes in this pool, each with their weight
+    affixes: Vec<(u32, Affix)>,
+}
+
+impl AffixPool {
+    pub fn new(eq_types: Vec<EquipmentType>) -> AffixPool {
+        AffixPool {
+            eq_types,
+            affixes: vec![],
+        }
+    }
+
+    /// Adds an `affix` with the given `weight` to this pool
+    pub fn add_affix(&mut self, weight: u32, affix: Affix) {
+        self.affixes.push((weight, affix));
+    }
+
+    pub fn applies_to(&self, eq_type: &EquipmentType) -> bool {
+        self.eq_types.contains(eq_type)
+    }
+}
+
+/// Generates random equipment from a catalog of `ItemBase`s and weighted `AffixPool`s.
+pub struct LootGenerator {
+    /// Source of all randomness of this generator
+    rng: SeededRng,
+    /// All item bases that can be generated, each with their weight
+    catalog: Vec<(u32, ItemBase)>,
+    /// Weights of each rarity tier, ordered as in `Rarity::ALL`
+    rarity_weights: [u32; 5],
+    /// Affix pools to roll affixes from
+    affix_pools: Vec<AffixPool>,
+}
+
+impl LootGenerator {
+
+    /// Builds an empty generator rolling with the given `seed`
+    pub fn new(seed: u64) -> LootGenerator {
+        LootGenerator {
+            rng: SeededRng::from_seed(seed),
+            catalog: vec![],
+            // By default, every tier is a few times less likely than the one before it
+            rarity_weights: [60, 25, 10, 4, 1],
+            affix_pools: vec![],
+        }
+    }
+
+    pub fn add_item_base(&mut self, weight: u32, base: ItemBase) {
+        self.catalog.push((weight, base));
+    }
+
+    pub fn add_affix_pool(&mut self, pool: AffixPool) {
+        self.affix_pools.push(pool);
+    }
+
+    pub fn set_rarity_weights(&mut self, rarity_weights: [u32; 5]) {
+        self.rarity_weights = rarity_weights;
+    }
+
+    /// Rolls a rarity tier
+    pub fn roll_rarity(&mut self) -> Rarity {
+        match self.rng.weighted_index(&self.rarity_weights) {
+            Some(i) => Rarity::ALL[i],
+            None => Rarity::Common,
+        }
+    }
+
+    /// Generates a random piece of equipment. Returns `None` if the catalog is empty.
+    pub fn generate(&mut self) -> Option<Equipment> {
+        let weights: Vec<u32> = self.catalog.iter().map(|(w, _)| *w).collect();
+        let base_index = self.rng.weighted_index(&weights)?;
+
+        // Roll rarity, then roll as many affixes as the rarity grants
+        let rarity = self.roll_rarity();
+        let base = &self.catalog[base_index].1;
+        let mut equipment = base.build();
+        equipment.set_rarity(rarity);
+
+        // Affixes are rolled from all pools that apply to this equipment's type
+        let affixes: Vec<&(u32, Affix)> = self.affix_pools.iter()
+            .filter(|p| p.applies_to(base.get_eq_type()))
+            .flat_map(|p| p.affixes.iter())
+            .collect();
+        let affix_weights: Vec<u32> = affixes.iter().map(|(w, _)| *w).collect();
+
+        for _ in 0..rarity.num_affixes() {
+            if let Some(i) = self.rng.weighted_index(&affix_weights) {
+                affixes[i].1.roll_onto(&mut equipment, &mut self.rng);
+            }
+        }
+
+        Some(equipment)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_requirements() -> Stats {
+        Stats { dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0 }
+    }
+
+    fn test_generator(seed: u64) -> LootGenerator {
+        let mut generator = LootGenerator::new(seed);
+        generator.add_item_base(1, ItemBase::new("Leather Cap".to_string(), EquipmentType::Head, no_requirements()));
+
+        let mut pool = AffixPool::new(vec![EquipmentType::Head]);
+        pool.add_affix(3, Affix::Stat(CharStat::STR, 1, 3));
+        pool.add_affix(1, Affix::Resistance(DamageType::PHY("Slash"), 0.1, 0.2));
+        generator.add_affix_pool(pool);
+
+        generator
+    }
+
+    #[test]
+    fn test_seeded_loot() {
+        let mut a = test_generator(1234);
+        let mut b = test_generator(1234);
+
+        for _ in 0..20 {
+            let loot_a = a.generate().unwrap();
+            let loot_b = b.generate().unwrap();
+
+            assert_eq!(loot_a.get_rarity(), loot_b.get_rarity());
+            assert_eq!(loot_a.get_passive_effects().len(), loot_a.get_rarity().num_affixes());
+            let describe = |e: &Equipment| e.get_passive_effects().iter()
+                .map(|fx| fx.describe()).collect::<Vec<String>>();
+            assert_eq!(describe(&loot_a), describe(&loot_b));
+        }
+    }
+
+    #[test]
+    fn test_guaranteed_rarity() {
+        let mut generator = test_generator(5);
+        generator.set_rarity_weights([0, 0, 0, 0, 1]);
+
+        let loot = generator.generate().unwrap();
+        assert_eq!(loot.get_rarity(), Rarity::Legendary);
+        assert_eq!(loot.get_passive_effects().len(), 4);
+    }
+}
@@ -1,8 +1,10 @@
-use crate::characters::Character;
+use crate::battlefield::{can_reach, Range, Row, ROW_MOVE_COST};
+use crate::characters::{Character, CharacterRef};
 use crate::combat::{DamageType, Actor, Damage, Action, EntityPointer, ActionEffect};
-use crate::effects::Effect;
-use crate::equipment::Equipment;
+use crate::effects::Taunted;
+use crate::equipment::{Equipment, StatScaling};
 use crate::world::WorldContext;
+use crate::ruleset::ruleset;
 
 
 /// Describes character abilities that affect the `ActionStack`, which come in two types:
@@ -10,8 +12,9 @@ use crate::world::WorldContext;
 /// * `Maneuver`s, which are 'standalone attacks'
 /// * `Reaction`s, which are conditional moves that cost AP and are reactions to other moves.
 ///
-/// This trait describes their shared behavior.
-pub trait Move {
+/// This trait describes their shared behavior. Moves are `Send` and `Sync`, so characters (and
+/// whole combats) can move between threads.
+pub trait Move: Send + Sync {
     /// Every move has a name
     fn name(&self) -> String;
 
@@ -37,6 +40,155 @@ pub trait Maneuver: Move {
     /// Each move returns exactly one "initial action" that starts the stack to resolve this move.
     fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action>;
 
+    /// Called during move selection. Allows moves to opt out of being selected by `character`
+    /// when they are not available or not useful right now (e.g. a healing potion at full HP).
+    /// Default is to always be available.
+    fn is_available(&self, _character: &Character, _context: &dyn WorldContext) -> bool {
+        true
+    }
+
+    /// Describes how far this move reaches. Default is `Ranged`, i.e. this move reaches anyone
+    /// from anywhere.
+    fn range(&self) -> Range {
+        Range::Ranged
+    }
+
+    /// Number of turns this move is unavailable after being used. Default is 0, i.e. the move
+    /// can be used every turn.
+    fn cooldown(&self) -> i64 {
+        0
+    }
+
+    /// Number of turns a character has to wind up before this move resolves. While winding up,
+    /// opponents can interrupt the move. Default is 0, i.e. the move resolves right away.
+    fn charge_turns(&self) -> i64 {
+        0
+    }
+
+}
+
+/// Borrowed moves behave exactly like the moves they point to. This allows owned moves (e.g. from
+/// `Equipment`) and temporary moves (e.g. `WeaponAttack`) to be listed side by side.
+impl<M: Move + ?Sized> Move for &M {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn mp_cost(&self) -> i64 {
+        (**self).mp_cost()
+    }
+}
+
+impl<M: Maneuver + ?Sized> Maneuver for &M {
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        (**self).execute(character, context)
+    }
+
+    fn is_available(&self, character: &Character, context: &dyn WorldContext) -> bool {
+        (**self).is_available(character, context)
+    }
+
+    fn range(&self) -> Range {
+        (**self).range()
+    }
+
+    fn cooldown(&self) -> i64 {
+        (**self).cooldown()
+    }
+
+    fn charge_turns(&self) -> i64 {
+        (**self).charge_turns()
+    }
+}
+
+/// Tracks the timing of a character's moves across turns: Which moves are on cooldown, and which
+/// charge-up move is currently being wound up.
+pub struct MoveState {
+    /// Names of all moves on cooldown, each with the number of turns they remain unavailable
+    cooldowns: Vec<(String, i64)>,
+    /// Name of the charge-up move currently being wound up, with the number of turns it has been
+    /// wound up for
+    charging: Option<(String, i64)>,
+    /// Whether the charge-up move has been wound up further during the current turn
+    wound_up: bool,
+}
+
+impl MoveState {
+    pub fn new() -> MoveState {
+        MoveState {
+            cooldowns: vec![],
+            charging: None,
+            wound_up: false,
+        }
+    }
+
+    /// Checks whether the move named `name` is currently on cooldown
+    pub fn is_on_cooldown(&self, name: &str) -> bool {
+        self.cooldowns.iter().any(|(n, _)| n == name)
+    }
+
+    /// Makes the move named `name` unavailable for the next `turns` turns
+    pub fn start_cooldown(&mut self, name: String, turns: i64) {
+        self.cooldowns.retain(|(n, _)| *n != name);
+        // The current turn doesn't count towards the cooldown
+        self.cooldowns.push((name, turns + 1));
+    }
+
+    /// Returns the name of the charge-up move being wound up, and for how many turns it's been
+    /// wound up so far
+    pub fn get_charging(&self) -> Option<(&String, i64)> {
+        self.charging.as_ref().map(|(n, t)| (n, *t))
+    }
+
+    /// Winds up the charge-up move named `name` by one turn. Winding up a different move
+    /// starts over.
+    pub fn wind_up(&mut self, name: &str) {
+        match &mut self.charging {
+            Some((n, turns)) if n == name => *turns += 1,
+            _ => self.charging = Some((name.to_string(), 1)),
+        }
+        self.wound_up = true;
+    }
+
+    /// Progresses all timings by one turn. Called at the end of each turn.
+    ///
+    /// A charge-up move that hasn't been wound up this turn is dropped: Either it has been
+    /// released, or it has been interrupted.
+    pub fn tick(&mut self) {
+        for (_, remaining) in &mut self.cooldowns {
+            *remaining -= 1;
+        }
+        self.cooldowns.retain(|(_, remaining)| *remaining > 0);
+
+        if !self.wound_up {
+            self.charging = None;
+        }
+        self.wound_up = false;
+    }
+}
+
+/// Winds up a charge-up move for a turn instead of executing it. Wraps the actual move, which is
+/// only released once it's been wound up for enough turns.
+pub struct WindUp<'a>(pub Box<dyn Maneuver + 'a>);
+
+impl<'a> Move for WindUp<'a> {
+    fn name(&self) -> String {
+        format!("{} (winding up)", self.0.name())
+    }
+
+    fn describe(&self) -> String {
+        format!("Winds up {}, which can be interrupted.", self.0.name())
+    }
+}
+
+impl<'a> Maneuver for WindUp<'a> {
+    fn execute(&self, character: &Character, _: &dyn WorldContext) -> Vec<Action> {
+        vec![Action::from_source(character.as_target(), ActionEffect::WindUp(self.0.name()), character.as_target())]
+    }
 }
 
 /// Describes a reaction. Reactions can be made **towards any character move/action and to other
@@ -59,6 +211,27 @@ pub trait Reaction: Move {
     fn react(&self, character: &Character, action: &Action, context: &dyn WorldContext) -> Option<Vec<Action>>;
 }
 
+/// Selects up to `count` opponents of `character` still standing and within `range` as targets,
+/// in order of preference:
+///
+/// 1. The character taunting `character` (if any) is always targeted first
+/// 2. Opponents that generated more threat with `character` come next
+/// 3. Ties are broken by going for the most weakened opponents (lowest HP to Max HP ratio)
+pub fn select_opponents<'a>(character: &Character, context: &'a dyn WorldContext, count: usize, range: Range) -> Vec<&'a Character> {
+    let mut opponents = context.find_characters(
+        &|c: &Character| !c.party_check(character.party()) && !c.is_defeated() && can_reach(range, c, context));
+
+    let taunted_by = character.taunted_by();
+    opponents.sort_by_key(|c| (
+        Some(c.id()) != taunted_by,
+        -context.get_threat(c.id(), character.id()),
+        (c.hp_to_max_hp_ratio() * 1000f64) as i64,
+    ));
+    opponents.truncate(count);
+
+    opponents
+}
+
 /// A very basic move that is available to all characters
 pub struct BarehandedBlow;
 
@@ -77,31 +250,374 @@ impl Maneuver for BarehandedBlow {
 
 
     ///
+    fn range(&self) -> Range {
+        Range::Melee
+    }
+
     fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
         // Calculate Damage
         let stats = character.calculate_current_stats();
-        let blow_damage = (stats.dex + stats.str)*3 // Main DMG stats
-            + stats.grt + stats.int;
-        // Adjust stat-based factors a multiplier, ensure minimum damage
-        let blow_damage = ((blow_damage as f64 * 0.45) as i64).max(1);
+        // Ensure minimum damage
+        let blow_damage = ruleset().barehanded_blow.apply(&stats).max(1);
         let blow_damage = ActionEffect::Attack(Damage(DamageType::PHY("Strike"), blow_damage));
 
         // This move can at maximum attack one target
-        // -> Start of with all valid targets, i.e. non-party members
-        let targets = context.find_characters(
-            // Look for anyone who's not part of our party
-            &|char: &Character| !char.party_check(character.party()));
+        match select_opponents(character, context, 1, Range::Melee).first() {
+            Some(target) => vec![Action::from_source(character.as_target(), blow_damage, target.as_target())],
+            // Nobody left to attack
+            None => vec![],
+        }
+    }
+}
+
+/// Attacks with a piece of equipment that has `WeaponStats`. Damage scales with the wielder's
+/// current stats, and the weapon can reach multiple targets at once.
+pub struct WeaponAttack<'a>(pub &'a Equipment);
+
+impl<'a> Move for WeaponAttack<'a> {
+    fn name(&self) -> String {
+        self.0.get_name().clone()
+    }
+
+    fn describe(&self) -> String {
+        match self.0.get_weapon_stats() {
+            Some(w) => format!("Attacks up to {} target(s) for {} {} damage (before scaling).",
+                               w.target_count, w.base_damage, w.damage_type),
+            None => "Not a weapon.".to_string(),
+        }
+    }
+}
+
+impl<'a> Maneuver for WeaponAttack<'a> {
+    fn range(&self) -> Range {
+        self.0.get_weapon_stats().map_or(Range::Melee, |w| w.range)
+    }
+
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        let weapon = self.0.get_weapon_stats().expect("Weapon Attack without weapon stats");
+
+        // Calculate Damage based on the wielder's current stats
+        let damage = weapon.damage_for(&character.calculate_current_stats());
+        let damage = ActionEffect::Attack(Damage(weapon.damage_type, damage));
+
+        // Attack as many opponents as the weapon reaches
+        let targets: Vec<CharacterRef> = select_opponents(character, context, weapon.target_count.max(1), weapon.range)
+            .iter()
+            .map(|c| c.to_ref())
+            .collect();
+
+        if targets.is_empty() {
+            // Nobody left to attack
+            return vec![];
+        }
+
+        let mut attack = Action::from_source(character.as_target(), damage, EntityPointer::Character(targets));
+        // The weapon is one of the wielder's equipment, so it wears down along with the attack
+        if let Some(index) = character.iter_equipment().position(|e| std::ptr::eq(e, self.0)) {
+            attack.set_equipment(index);
+        }
+
+        vec![attack]
+    }
+}
+
+
+/// Moves the character to the given row of their party's formation, which costs MVE.
+pub struct Reposition(pub Row);
+
+impl Move for Reposition {
+    fn name(&self) -> String {
+        match self.0 {
+            Row::Front => "Advance".to_string(),
+            Row::Back => "Fall Back".to_string(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("Moves to the {} ({} MVE).", self.0, ROW_MOVE_COST)
+    }
+}
+
+impl Maneuver for Reposition {
+    fn execute(&self, character: &Character, _: &dyn WorldContext) -> Vec<Action> {
+        vec![Action::from_source(character.as_target(), ActionEffect::Reposition(self.0), character.as_target())]
+    }
+
+    /// Characters can only move to another row, and only if they've got enough movement left
+    fn is_available(&self, character: &Character, _: &dyn WorldContext) -> bool {
+        character.get_row() != self.0 && character.mve() >= ROW_MOVE_COST
+    }
+}
 
-        // Attack the target with the lowest HP to Max HP ratio
-        let target = targets.iter().max_by_key(
-            |c| (c.hp_to_max_hp_ratio() * 1000f64) as i64).expect("Couldn't find a target");
+/// Does nothing for a turn. Characters wait when they can't perform any other move, e.g. when
+/// they're stuck in the back row without a ranged move.
+pub struct Wait;
 
-        vec![Action::from_source(character.as_target(), blow_damage, target.as_target())]
+impl Move for Wait {
+    fn name(&self) -> String {
+        "Wait".to_string()
+    }
+
+    fn describe(&self) -> String {
+        "Waits for a better opportunity.".to_string()
+    }
+}
+
+impl Maneuver for Wait {
+    fn execute(&self, _: &Character, _: &dyn WorldContext) -> Vec<Action> {
+        vec![]
+    }
+}
+
+/// Describes which characters an area attack hits, starting out from its primary target (as
+/// selected by `select_opponents`).
+#[derive(Clone, Copy)]
+pub enum AreaOfEffect {
+    /// Hits the primary target and the characters right next to it in its row
+    Cleave,
+    /// Hits the primary target's whole party (as far as the attack reaches)
+    Party,
+    /// Hits the primary target, then chains on to up to the given number of further opponents.
+    /// Every hop deals the damage of the previous hop times the given falloff factor.
+    Chain(usize, f64),
+}
+
+impl AreaOfEffect {
+    /// Builds the actions of `character` attacking with `damage` in this area, reaching as far as
+    /// `range`. Cleaves and party attacks are a single group attack, splitting their damage
+    /// between all targets. Chains are one attack per hop.
+    pub fn build_attack(&self, character: &Character, context: &dyn WorldContext, damage: Damage, range: Range) -> Vec<Action> {
+        let opponents = select_opponents(character, context, match self {
+            AreaOfEffect::Chain(hops, _) => hops + 1,
+            _ => 1,
+        }, range);
+        let primary = match opponents.first() {
+            Some(p) => *p,
+            // Nobody left to attack
+            None => return vec![],
+        };
+
+        // All characters standing in the primary target's party within reach, in order
+        let party: Vec<CharacterRef> = context.find_characters(
+            &|c: &Character| c.party_check(primary.party()) && !c.is_defeated() && can_reach(range, c, context))
+            .iter().map(|c| c.to_ref()).collect();
+        // ... of which only those in the same row are adjacent to the primary target
+        let row: Vec<CharacterRef> = context.find_characters(
+            &|c: &Character| c.party_check(primary.party()) && !c.is_defeated() && c.get_row() == primary.get_row())
+            .iter().map(|c| c.to_ref()).collect();
+
+        let group_attack = |targets: Vec<CharacterRef>|
+            vec![Action::from_source(character.as_target(), ActionEffect::Attack(damage), EntityPointer::Character(targets))];
+
+        match self {
+            AreaOfEffect::Cleave => {
+                let i = row.iter().position(|c| c.id() == primary.id()).unwrap_or(0);
+                let neighbours = row[i.saturating_sub(1)..=(i + 1).min(row.len() - 1)].to_vec();
+                group_attack(neighbours)
+            }
+            AreaOfEffect::Party => group_attack(party),
+            AreaOfEffect::Chain(_, falloff) => {
+                let mut amount = damage.amount() as f64;
+                let mut actions = Vec::new();
+                for target in opponents {
+                    actions.push(Action::from_source(character.as_target(),
+                                                     ActionEffect::Attack(Damage(damage.0, amount.floor() as i64)),
+                                                     target.as_target()));
+                    amount *= falloff;
+                }
+                actions
+            }
+        }
+    }
+}
+
+/// Attacks several opponents at once, as described by its `AreaOfEffect`. Damage scales with
+/// the attacker's current stats.
+pub struct AreaAttack {
+    /// Name of the attack
+    name: String,
+    /// Damage dealt before any stat scaling (and splitting between targets) is applied
+    damage: Damage,
+    /// Scales the damage with the attacker's current stats
+    scaling: StatScaling,
+    /// Describes who's hit by the attack
+    area: AreaOfEffect,
+    /// Describes how far the attack reaches, i.e. whom it can hit at all
+    range: Range,
+}
+
+impl AreaAttack {
+    pub fn new(name: String, damage: Damage, scaling: StatScaling, area: AreaOfEffect, range: Range) -> AreaAttack {
+        AreaAttack {
+            name,
+            damage,
+            scaling,
+            area,
+            range,
+        }
     }
 }
 
-struct WeaponAttack<'a>(&'a Equipment);
+impl Move for AreaAttack {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
 
+    fn describe(&self) -> String {
+        let area = match self.area {
+            AreaOfEffect::Cleave => "a target and its neighbours".to_string(),
+            AreaOfEffect::Party => "a whole party".to_string(),
+            AreaOfEffect::Chain(hops, _) => format!("a target, chaining to {} more", hops),
+        };
+        format!("Deals {} to {} (before scaling).", self.damage, area)
+    }
+}
+
+impl Maneuver for AreaAttack {
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        let amount = (self.damage.amount() + self.scaling.scale(&character.calculate_current_stats())).max(1);
+        self.area.build_attack(character, context, Damage(self.damage.0, amount), self.range)
+    }
+
+    fn range(&self) -> Range {
+        self.range
+    }
+}
+
+/// Taunts all opponents for a number of turns, forcing them to target the taunting character.
+pub struct Taunt {
+    /// Number of turns the taunt lasts
+    turns: i64,
+}
+
+impl Taunt {
+    pub fn new(turns: i64) -> Taunt {
+        Taunt {
+            turns,
+        }
+    }
+}
+
+impl Move for Taunt {
+    fn name(&self) -> String {
+        "Taunt".to_string()
+    }
+
+    fn describe(&self) -> String {
+        format!("Provokes all opponents into attacking you for {} turns.", self.turns)
+    }
+}
+
+impl Maneuver for Taunt {
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        let targets: Vec<CharacterRef> = context.find_characters(
+            &|c: &Character| !c.party_check(character.party()) && !c.is_defeated())
+            .iter().map(|c| c.to_ref()).collect();
+        if targets.is_empty() {
+            return vec![];
+        }
+
+        vec![Action::from_source(character.as_target(),
+                                 ActionEffect::GiveTimedEffect(Box::new(Taunted(character.to_ref())), self.turns),
+                                 EntityPointer::Character(targets))]
+    }
+
+    /// Taunting only makes sense if some opponent isn't taunted by this character already
+    fn is_available(&self, character: &Character, context: &dyn WorldContext) -> bool {
+        !context.find_characters(&|c: &Character| !c.party_check(character.party()) && !c.is_defeated()
+            && c.taunted_by() != Some(character.id())).is_empty()
+    }
+}
+
+/// Guards weakened allies, redirecting attacks targeting them onto the guarding character.
+pub struct Guard {
+    /// Allies are guarded once their HP to Max HP ratio is below this value
+    hp_ratio: f64,
+}
+
+impl Guard {
+    pub fn new(hp_ratio: f64) -> Guard {
+        Guard {
+            hp_ratio,
+        }
+    }
+}
+
+impl Move for Guard {
+    fn name(&self) -> String {
+        "Guard".to_string()
+    }
+
+    fn describe(&self) -> String {
+        format!("Takes attacks meant for allies below {}% HP.", (self.hp_ratio * 100f64) as i64)
+    }
+}
+
+impl Reaction for Guard {
+    fn ap_cost(&self) -> i64 {
+        3
+    }
+
+    fn react(&self, character: &Character, action: &Action, context: &dyn WorldContext) -> Option<Vec<Action>> {
+        // Requirement 1: Only guarding against attacks
+        if !matches!(action.get_effect(), ActionEffect::Attack(_)) {
+            return None;
+        }
+
+        // Requirement 2: Only guarding a single weakened ally (not myself)
+        let guarded = match action.get_target() {
+            EntityPointer::Character(chars) if chars.len() == 1 && chars[0].id() != character.id() =>
+                context.get_character_by_id(chars[0].id())?,
+            _ => return None,
+        };
+        if !guarded.party_check(character.party()) || guarded.hp_to_max_hp_ratio() >= self.hp_ratio {
+            return None;
+        }
+
+        Some(vec![Action::from_source(character.as_target(),
+                                      ActionEffect::ChangeTarget(character.as_target()),
+                                      action.build_self_target())])
+    }
+}
+
+/// Interrupts opponents winding up a charge-up move, canceling their wind-up for this turn.
+pub struct Interrupt;
+
+impl Move for Interrupt {
+    fn name(&self) -> String {
+        "Interrupt".to_string()
+    }
+
+    fn describe(&self) -> String {
+        "Disrupts an opponent winding up a big move.".to_string()
+    }
+}
+
+impl Reaction for Interrupt {
+    fn ap_cost(&self) -> i64 {
+        2
+    }
+
+    fn react(&self, character: &Character, action: &Action, context: &dyn WorldContext) -> Option<Vec<Action>> {
+        // Requirement 1: Only reacting to moves being wound up
+        if !matches!(action.get_effect(), ActionEffect::WindUp(_)) {
+            return None;
+        }
+
+        // Requirement 2: Only interrupting opponents
+        let by_opponent = match action.get_source() {
+            EntityPointer::Character(chars) => chars.iter()
+                .filter_map(|c| context.get_character_by_id(c.id()))
+                .any(|c| !c.party_check(character.party())),
+            _ => false,
+        };
+        if !by_opponent {
+            return None;
+        }
+
+        Some(vec![Action::from_source(character.as_target(), ActionEffect::Cancel, action.build_self_target())])
+    }
+}
 
 /// Describes a general Counter Ability. A counter attack is a **reaction to a Damage Effect**,
 /// that can **reduce incoming damage** and/or **counter-damage the attacker**.
@@ -130,54 +646,7 @@ impl Counter {
 
     /// Checks whether this instance would react to the `incoming` damage type.
     fn relevant_for(&self, incoming: &DamageType) -> bool {
-        // ULT Damage is unblockable
-        if let DamageType::ULT = incoming {
-            return false;
-        }
-
-        match self.damage_type {
-            DamageType::PHY(s) => {
-                if s == "" {
-                    // Empty string means any PHY subtype is defended against
-                    matches!(incoming, DamageType::PHY(_))
-                } else {
-                    if let DamageType::PHY(s2) = incoming {
-                        s == *s2
-                    } else {
-                        false
-                    }
-                }
-
-            },
-            DamageType::MAG(s) => {
-                if s == "" {
-                    // Empty string means any MAG subtype is defended against
-                    matches!(incoming, DamageType::MAG(_))
-                } else {
-                    if let DamageType::MAG(s2) = incoming {
-                        s == *s2
-                    } else {
-                        false
-                    }
-                }
-
-            },
-            DamageType::ZAP(s) => {
-                if s == "" {
-                    // Empty string means any ZAP subtype is defended against
-                    matches!(incoming, DamageType::ZAP(_))
-                } else {
-                    if let DamageType::ZAP(s2) = incoming {
-                        s == *s2
-                    } else {
-                        false
-                    }
-                }
-
-            },
-            // ULT -> Generic Counter against any damage
-            DamageType::ULT => true,
-        }
+        self.damage_type.covers(incoming)
     }
 }
 
@@ -193,20 +662,19 @@ impl Move for Counter {
     /// Update MP Cost to reflect calculated cost based on value
     fn mp_cost(&self) -> i64 {
         let mut total_cost = 0;
+        let rules = ruleset().counter;
 
         // Account for Counter Damage Reduction
-        // Damage Reduction Up To / Until 30% is free
-        let red_cutoff = 0.7f64;
-        if self.incoming_factor < red_cutoff {
-            let red_cost = ((red_cutoff - self.incoming_factor) * 10f64) as i64;
+        // Damage Reduction Up To / Until the cutoff (30% by default) is free
+        if self.incoming_factor < rules.reduction_cutoff {
+            let red_cost = ((rules.reduction_cutoff - self.incoming_factor) * rules.reduction_cost) as i64;
             total_cost += red_cost;
         }
 
         // Account for Counter Return Damage
-        // Return Damage Up To / Until 30% is free
-        let dmg_cutoff = 0.3f64;
-        if self.outgoing_factor > dmg_cutoff {
-            let dmg_cost = ((self.outgoing_factor - dmg_cutoff) * 15f64) as i64;
+        // Return Damage Up To / Until the cutoff (30% by default) is free
+        if self.outgoing_factor > rules.damage_cutoff {
+            let dmg_cost = ((self.outgoing_factor - rules.damage_cutoff) * rules.damage_cost) as i64;
             total_cost += dmg_cost;
         }
 
@@ -222,24 +690,31 @@ impl Reaction for Counter {
 
     fn react(&self, character: &Character, action: &Action, context: &dyn WorldContext) -> Option<Vec<Action>> {
         // Requirement 1: Only affecting actions that target me as a character directly
-        if !action.targets_character(character.name()) {
+        if !action.targets_character(character.id()) {
             return None
         }
         // Requirement 2: Only reacting to incoming `Attack`s
-        if let ActionEffect::Attack(Damage(dt, damage)) = action.get_effect() {
+        // (Group attacks are countered based on my share of the damage only)
+        if let Some(Damage(dt, damage)) = action.damage_for(character.id()) {
             // Requirement 3: Only reacting if the dt matches.
-            if self.relevant_for(dt) {
+            if self.relevant_for(&dt) {
                 // All Checks passed! Build the counter-action
                 let mut res = Vec::new();
 
-                // Possibly reduce incoming damage
+                // Possibly reduce incoming damage. When I'm one of several targets, only my
+                // damage is reduced
                 if self.incoming_factor != 1f64 {
-                    res.push(Action::from_source(character.as_target(), ActionEffect::AdjustDamageMul(self.incoming_factor), action.build_self_target()))
+                    let adjustment = if action.get_target().num_entities() > 1 {
+                        ActionEffect::AdjustDamageMulFor(character.id(), self.incoming_factor)
+                    } else {
+                        ActionEffect::AdjustDamageMul(self.incoming_factor)
+                    };
+                    res.push(Action::from_source(character.as_target(), adjustment, action.build_self_target()))
                 }
 
                 // Possibly return a counter attack
                 if self.outgoing_factor != 0f64 {
-                    res.push(Action::from_source(character.as_target(), ActionEffect::Attack(Damage(dt.clone(), (self.outgoing_factor * *damage as f64) as i64)), action.get_source().clone()))
+                    res.push(Action::from_source(character.as_target(), ActionEffect::Attack(Damage(dt, (self.outgoing_factor * damage as f64) as i64)), action.get_source().clone()))
                 }
 
                 Some(res)
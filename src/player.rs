@@ -1,7 +1,23 @@
+use crate::characters::CharacterId;
+use crate::inventory::ItemLocation;
 use crate::world;
 
-pub struct PlayerInput {
-
+/// Describes all commands players can give to a world context in between turns.
+pub enum PlayerInput {
+    /// Moves the item at `index` of the `from` location to the `to` location
+    MoveItem { from: ItemLocation, index: usize, to: ItemLocation },
+    /// Moves `amount` currency from the `from` location to the `to` location
+    MoveCurrency { from: ItemLocation, amount: i64, to: ItemLocation },
+    /// Equips the equipment at `index` of the `character`'s inventory
+    Equip { character: CharacterId, index: usize },
+    /// Unequips the `character`'s equipment at `index` into their inventory
+    Unequip { character: CharacterId, index: usize },
+    /// The `party` tries to flee from combat
+    Flee { party: String },
+    /// The `party` surrenders
+    Surrender { party: String },
+    /// The `character` retreats from combat on their own
+    Retreat { character: CharacterId },
 }
 
 pub struct Player {
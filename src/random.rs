@@ -0,0 +1,124 @@
+//! Contains the game's source of randomness. All random rolls in the game (e.g. loot) go through a
+//! `SeededRng`, so any random outcome can be **reproduced exactly from its seed**.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small, fast and seeded pseudo random number generator (SplitMix64).
+///
+/// Not suited for cryptography, but more than good enough for dice rolls.
+#[derive(Clone)]
+pub struct SeededRng {
+    /// Internal state, advanced with every roll
+    state: u64,
+}
+
+impl SeededRng {
+
+    /// Builds a new generator. Two generators built from the same `seed` roll the same numbers.
+    pub fn from_seed(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    /// Builds a new generator from a seed the system picks, so every such generator rolls
+    /// different numbers
+    pub fn from_entropy() -> Self {
+        // Hash keys are random for every process, and advance with every new hasher
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos()));
+        SeededRng::from_seed(hasher.finish())
+    }
+
+    /// Rolls the next raw 64 bit number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Rolls a number in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        // Use the upper 53 bits for a uniformly distributed mantissa
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Rolls a number between `min` and `max` (both inclusive)
+    pub fn range(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let span = (max - min) as u64 + 1;
+        min + (self.next_u64() % span) as i64
+    }
+
+    /// Rolls a number between `min` (inclusive) and `max` (exclusive)
+    pub fn range_f64(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    /// Returns `true` with the given `probability` (`0.0` to `1.0`)
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    /// Picks an index from a list of `weights`. Each index is picked with a probability
+    /// proportional to its weight.
+    ///
+    /// Returns `None` if all weights are `0` (or no weights are given).
+    pub fn weighted_index(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: u64 = weights.iter().map(|w| *w as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = self.next_u64() % total;
+        for (i, w) in weights.iter().enumerate() {
+            if roll < *w as u64 {
+                return Some(i);
+            }
+            roll -= *w as u64;
+        }
+
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let mut a = SeededRng::from_seed(42);
+        let mut b = SeededRng::from_seed(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        // ... while generators seeded by the system don't
+        assert_ne!(SeededRng::from_entropy().next_u64(), SeededRng::from_entropy().next_u64());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = SeededRng::from_seed(7);
+
+        for _ in 0..1000 {
+            let roll = rng.range(-3, 5);
+            assert!(roll >= -3 && roll <= 5);
+            let roll = rng.next_f64();
+            assert!(roll >= 0f64 && roll < 1f64);
+        }
+
+        // Zero weights are never picked
+        for _ in 0..1000 {
+            assert_ne!(rng.weighted_index(&[3, 0, 1]), Some(1));
+        }
+        assert_eq!(rng.weighted_index(&[0, 0]), None);
+    }
+}
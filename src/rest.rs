@@ -0,0 +1,245 @@
+//! Contains the `Rest` world context, in which a party recovers between adventures.
+
+use std::collections::HashMap;
+use crate::characters::{Character, CharacterId};
+use crate::combat::{Action, Actor, CostLedger};
+use crate::inventory::{Inventory, Item, ItemLocation};
+use crate::player::PlayerInput;
+use crate::world::{TurnLogger, WorldContext};
+
+/// Amount of durability restored on every piece of equipment per turn of rest
+const REPAIR_PER_TURN: i64 = 5;
+
+/// Simulates a party resting. Every turn of rest, characters regenerate and **repair their
+/// equipment**, including broken equipment.
+///
+/// While resting, players can manage items between the characters' inventories, their
+/// equipment and the party's shared stash.
+pub struct Rest {
+    /// All resting characters are owned by this context during its lifetime.
+    participants: Vec<Character>,
+    /// Position of every resting character in `participants`, by ID
+    index: HashMap<CharacterId, usize>,
+    /// The resting party's shared stash
+    stash: Inventory,
+}
+
+impl Rest {
+
+    pub fn from_participants(participants: Vec<Character>) -> Self {
+        Rest {
+            index: participants.iter().enumerate().map(|(i, c)| (c.id(), i)).collect(),
+            participants,
+            stash: Inventory::unlimited(),
+        }
+    }
+
+    /// Ends the rest, handing back ownership of all resting characters
+    pub fn into_participants(self) -> Vec<Character> {
+        self.participants
+    }
+
+    pub fn stash(&self) -> &Inventory {
+        &self.stash
+    }
+
+    pub fn stash_mut(&mut self) -> &mut Inventory {
+        &mut self.stash
+    }
+
+    /// Resolves an item `location` to the inventory it describes
+    fn inventory_mut(&mut self, location: &ItemLocation) -> Result<&mut Inventory, String> {
+        match location {
+            ItemLocation::Stash => Ok(&mut self.stash),
+            ItemLocation::Inventory(id) => self.get_character_by_id_mut(*id)
+                .map(|c| c.inventory_mut())
+                .ok_or(format!("No character {} is resting here.", id)),
+        }
+    }
+
+    /// Moves the item at `index` of the `from` location to the `to` location
+    fn move_item(&mut self, from: &ItemLocation, index: usize, to: &ItemLocation) -> Result<String, String> {
+        if !self.inventory_mut(to)?.has_space() {
+            return Err("No space left.".to_string());
+        }
+        let item = self.inventory_mut(from)?.remove(index)
+            .ok_or(format!("No item at position {}.", index))?;
+        let message = format!("Moved {}.", item.name());
+
+        // Space was checked before, so this is guaranteed to succeed
+        self.inventory_mut(to)?.add(item).ok();
+
+        Ok(message)
+    }
+
+    /// Equips the equipment at `index` of the character's inventory
+    fn equip_from_inventory(&mut self, id: CharacterId, index: usize) -> Result<String, String> {
+        let character = self.get_character_by_id_mut(id).ok_or(format!("No character {} is resting here.", id))?;
+
+        match character.inventory().get(index) {
+            Some(Item::Equipment(e)) => character.can_equip(e)?,
+            Some(_) => return Err("Not an equipment.".to_string()),
+            None => return Err(format!("No item at position {}.", index)),
+        }
+
+        match character.inventory_mut().remove(index) {
+            Some(Item::Equipment(e)) => {
+                let message = format!("{} equipped {}.", character.name(), e.get_name());
+                character.equip(e)?;
+                Ok(message)
+            }
+            _ => Err("Not an equipment.".to_string()),
+        }
+    }
+
+    /// Unequips the character's equipment at `index` into their inventory
+    fn unequip_to_inventory(&mut self, id: CharacterId, index: usize) -> Result<String, String> {
+        let character = self.get_character_by_id_mut(id).ok_or(format!("No character {} is resting here.", id))?;
+
+        if !character.inventory().has_space() {
+            return Err("No space left.".to_string());
+        }
+        let equipment = character.unequip(index).ok_or(format!("No equipment at position {}.", index))?;
+        let message = format!("{} unequipped {}.", character.name(), equipment.get_name());
+        character.inventory_mut().add(Item::Equipment(equipment)).ok();
+
+        Ok(message)
+    }
+}
+
+/// Rest is a world context, meaning it **independently processes turns**.
+impl WorldContext for Rest {
+
+    fn process_turn(&mut self, _: Option<&mut dyn TurnLogger>) -> Result<(), String> {
+        for character in self.participants.iter_mut() {
+            // Regenerate like in any other turn
+            character.pre_turn();
+
+            // Take care of equipment
+            character.repair_equipment(REPAIR_PER_TURN);
+
+            character.post_turn();
+        }
+
+        Ok(())
+    }
+
+    fn process_player_input(&mut self, input: &PlayerInput) -> Result<String, String> {
+        match input {
+            PlayerInput::MoveItem { from, index, to } => self.move_item(from, *index, to),
+            PlayerInput::MoveCurrency { from, amount, to } => {
+                // Make sure the target exists before taking anything out
+                self.inventory_mut(to)?;
+                self.inventory_mut(from)?.take_currency(*amount)?;
+                self.inventory_mut(to)?.add_currency(*amount)?;
+                Ok(format!("Moved {} G.", amount))
+            }
+            PlayerInput::Equip { character, index } => self.equip_from_inventory(*character, *index),
+            PlayerInput::Unequip { character, index } => self.unequip_to_inventory(*character, *index),
+            PlayerInput::Flee { .. } | PlayerInput::Surrender { .. } | PlayerInput::Retreat { .. } =>
+                Err("Nothing to escape from while resting.".to_string()),
+        }
+    }
+
+    fn iter_characters(&self) -> core::slice::Iter<Character> {
+        self.participants.iter()
+    }
+
+    fn iter_characters_mut(&mut self) -> core::slice::IterMut<Character> {
+        self.participants.iter_mut()
+    }
+
+    fn get_character_by_id(&self, id: CharacterId) -> Option<&Character> {
+        self.index.get(&id).map(|i| &self.participants[*i])
+    }
+
+    fn get_character_by_id_mut(&mut self, id: CharacterId) -> Option<&mut Character> {
+        self.index.get(&id).map(|i| &mut self.participants[*i])
+    }
+
+    /// Nothing happens during rest that could be reacted to
+    fn request_reactions(&mut self, _: &Action, _: &CostLedger) -> Vec<Action> {
+        vec![]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::battlefield::Range;
+    use crate::characters::Stats;
+    use crate::combat::DamageType;
+    use crate::equipment::{Equipment, EquipmentType, StatScaling, WeaponStats};
+    use super::*;
+
+    #[test]
+    fn test_item_commands() {
+        let character = Character::new("Lindtbert".to_string(), None, Stats {
+            str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+        let mut rest = Rest::from_participants(vec![character]);
+
+        rest.stash_mut().add_currency(50).unwrap();
+        rest.stash_mut().add(Item::Equipment(Equipment::new("Cap".to_string(), EquipmentType::Head,
+            Stats { dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0 }))).ok();
+
+        let lindtbert = rest.get_character("Lindtbert").unwrap().id();
+        rest.process_player_input(&PlayerInput::MoveItem {
+            from: ItemLocation::Stash, index: 0, to: ItemLocation::Inventory(lindtbert),
+        }).unwrap();
+        rest.process_player_input(&PlayerInput::MoveCurrency {
+            from: ItemLocation::Stash, amount: 20, to: ItemLocation::Inventory(lindtbert),
+        }).unwrap();
+        assert!(rest.process_player_input(&PlayerInput::MoveCurrency {
+            from: ItemLocation::Stash, amount: 40, to: ItemLocation::Inventory(lindtbert),
+        }).is_err());
+        // Moving a negative amount must not create currency
+        assert!(rest.process_player_input(&PlayerInput::MoveCurrency {
+            from: ItemLocation::Stash, amount: -1000, to: ItemLocation::Inventory(lindtbert),
+        }).is_err());
+
+        rest.process_player_input(&PlayerInput::Equip { character: lindtbert, index: 0 }).unwrap();
+        {
+            let character = rest.get_character_by_id(lindtbert).unwrap();
+            assert!(character.inventory().is_empty());
+            assert_eq!(character.inventory().currency(), 20);
+            assert_eq!(character.iter_equipment().count(), 1);
+        }
+
+        rest.process_player_input(&PlayerInput::Unequip { character: lindtbert, index: 0 }).unwrap();
+        assert_eq!(rest.get_character_by_id(lindtbert).unwrap().inventory().len(), 1);
+        assert_eq!(rest.stash().currency(), 30);
+    }
+
+    #[test]
+    fn test_repair() {
+        let no_stats = Stats { dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0 };
+        let mut character = Character::new("Lindtbert".to_string(), None, Stats {
+            str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+
+        let mut sword = Equipment::weapon("Sword".to_string(), no_stats, WeaponStats {
+            base_damage: 10,
+            damage_type: DamageType::PHY("Slash"),
+            scaling: StatScaling::default(),
+            target_count: 1,
+            range: Range::Melee,
+        });
+        sword.set_max_durability(8);
+        character.equip(sword).unwrap();
+
+        // Break the sword: It no longer provides its attack
+        character.wear_equipment(0, 8);
+
+        let mut rest = Rest::from_participants(vec![character]);
+        {
+            let character = rest.get_character("Lindtbert").unwrap();
+            assert_ne!(character.next_move(&rest).name(), "Sword");
+        }
+        rest.process_turn(None).unwrap();
+
+        let character = rest.get_character("Lindtbert").unwrap();
+        assert_eq!(character.iter_equipment().next().unwrap().get_durability(), REPAIR_PER_TURN);
+        assert_eq!(character.next_move(&rest).name(), "Sword");
+    }
+}
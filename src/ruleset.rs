@@ -0,0 +1,445 @@
+//! Contains the `Ruleset`, which defines the coefficients of the game's rule formulas, e.g. how
+//! many HP a point of GRT is worth or how much MP a Counter costs.
+//!
+//! Rulesets are loaded from plain text files, so balance can be tuned without recompiling:
+//!
+//! ```text
+//! # Comments start with '#'
+//! [max_hp]
+//! base = 100
+//! factor = 12
+//! grt = 2
+//!
+//! [counter]
+//! reduction_cutoff = 0.7
+//! ```
+//!
+//! Every section configures one formula or rule. Anything not set keeps its default value, which
+//! is the game's built-in rules.
+//!
+//! The active ruleset is consulted whenever a rule is applied. It's set for the whole game with
+//! `set_ruleset`, or for the current thread only with `with_ruleset` (e.g. to simulate different
+//! rulesets side by side).
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::characters::Stats;
+
+/// A linear formula deriving a value from stats: `base + floor(factor * weighted stats)`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Formula {
+    /// Value before any stats are taken into account
+    pub base: i64,
+    /// Multiplier applied to the weighted stats
+    pub factor: f64,
+    /// Weight of each stat
+    pub weights: Stats,
+}
+
+impl Formula {
+    pub fn new(base: i64, factor: f64, weights: Stats) -> Formula {
+        Formula {
+            base,
+            factor,
+            weights,
+        }
+    }
+
+    /// Applies this formula to `stats`
+    pub fn apply(&self, stats: &Stats) -> i64 {
+        let w = &self.weights;
+        let weighted = w.dex * stats.dex + w.str * stats.str + w.grt * stats.grt
+            + w.wil * stats.wil + w.cha * stats.cha + w.int * stats.int;
+
+        self.base + (self.factor * weighted as f64).floor() as i64
+    }
+
+    /// Sets the coefficient `key` (`base`, `factor` or a stat) to `value`
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "base" => self.base = parse_value(value)?,
+            "factor" => self.factor = parse_value(value)?,
+            "dex" => self.weights.dex = parse_value(value)?,
+            "str" => self.weights.str = parse_value(value)?,
+            "grt" => self.weights.grt = parse_value(value)?,
+            "wil" => self.weights.wil = parse_value(value)?,
+            "cha" => self.weights.cha = parse_value(value)?,
+            "int" => self.weights.int = parse_value(value)?,
+            _ => return Err(format!("Unknown formula coefficient {}.", key)),
+        }
+        Ok(())
+    }
+
+    fn write(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "base = {}", self.base)?;
+        writeln!(f, "factor = {}", self.factor)?;
+        let w = &self.weights;
+        for (stat, weight) in [("dex", w.dex), ("str", w.str), ("grt", w.grt), ("wil", w.wil), ("cha", w.cha), ("int", w.int)] {
+            if weight != 0 {
+                writeln!(f, "{} = {}", stat, weight)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Defines the MP cost of a `Counter`. Reducing incoming damage down to the reduction cutoff and
+/// returning damage up to the damage cutoff is free, anything beyond costs MP.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CounterRules {
+    /// Incoming damage factor below which reducing damage costs MP
+    pub reduction_cutoff: f64,
+    /// MP per 1.0 of incoming damage factor below the cutoff
+    pub reduction_cost: f64,
+    /// Outgoing damage factor above which returning damage costs MP
+    pub damage_cutoff: f64,
+    /// MP per 1.0 of outgoing damage factor above the cutoff
+    pub damage_cost: f64,
+}
+
+/// Defines how defenses reduce incoming damage. Each defense is multiplied by its factor before
+/// it's subtracted from the damage.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DamageRules {
+    /// Share of PDF applied against PHY damage
+    pub phys_defense_factor: f64,
+    /// Share of MDF applied against MAG damage
+    pub mag_defense_factor: f64,
+    /// Share of MDF applied against ZAP damage
+    pub zap_defense_factor: f64,
+}
+
+/// Defines the coefficients of all rule formulas of the game
+#[derive(Clone, PartialEq, Debug)]
+pub struct Ruleset {
+    pub max_hp: Formula,
+    pub max_mp: Formula,
+    pub max_ap: Formula,
+    pub action_points: Formula,
+    pub move_speed: Formula,
+    pub phys_defense: Formula,
+    pub mag_defense: Formula,
+    pub mobility: Formula,
+    pub health_regen: Formula,
+    pub magic_regen: Formula,
+    pub max_vit: Formula,
+    pub carry_slots: Formula,
+    /// Damage of the `BarehandedBlow`
+    pub barehanded_blow: Formula,
+    pub counter: CounterRules,
+    pub damage: DamageRules,
+}
+
+/// Shorthand to build stat weights
+fn weights(dex: i64, str: i64, grt: i64, wil: i64, cha: i64, int: i64) -> Stats {
+    Stats { dex, str, grt, wil, cha, int }
+}
+
+impl Default for Ruleset {
+    /// The game's built-in rules
+    fn default() -> Self {
+        Ruleset {
+            max_hp: Formula::new(100, 12f64, weights(0, 1, 2, 2, 1, 0)),
+            max_mp: Formula::new(0, 2f64, weights(1, 0, 0, 1, 2, 2)),
+            max_ap: Formula::new(0, 0.2, weights(2, 0, 1, 2, 0, 2)),
+            // Every 20 weighted points, one additional AP is earned
+            action_points: Formula::new(1, 0.05, weights(2, 0, 1, 1, 0, 2)),
+            move_speed: Formula::new(0, 0.15, weights(2, 1, 0, 0, 0, 0)),
+            phys_defense: Formula::new(0, 1.2, weights(0, 1, 1, 0, 0, 0)),
+            mag_defense: Formula::new(0, 1.2, weights(0, 0, 0, 1, 1, 0)),
+            mobility: Formula::new(0, 1.2, weights(1, 0, 0, 0, 0, 1)),
+            health_regen: Formula::new(1, 0.6, weights(0, 1, 2, 1, 0, 0)),
+            magic_regen: Formula::new(1, 0.33, weights(0, 0, 0, 2, 1, 1)),
+            max_vit: Formula::new(0, 15f64, weights(0, 1, 4, 3, 1, 0)),
+            carry_slots: Formula::new(5, 0.5, weights(0, 1, 1, 0, 0, 0)),
+            barehanded_blow: Formula::new(0, 0.45, weights(3, 3, 1, 0, 0, 1)),
+            counter: CounterRules {
+                reduction_cutoff: 0.7,
+                reduction_cost: 10f64,
+                damage_cutoff: 0.3,
+                damage_cost: 15f64,
+            },
+            damage: DamageRules {
+                phys_defense_factor: 1f64,
+                mag_defense_factor: 1f64,
+                zap_defense_factor: 0.5,
+            },
+        }
+    }
+}
+
+impl Ruleset {
+    /// Reads a ruleset from the file at `path`
+    pub fn load(path: &str) -> Result<Ruleset, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read ruleset {}: {}", path, e))?;
+        Ruleset::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Parses a ruleset from `text`, starting out from the built-in rules
+    pub fn parse(text: &str) -> Result<Ruleset, String> {
+        let mut ruleset = Ruleset::default();
+        ruleset.apply(text)?;
+        Ok(ruleset)
+    }
+
+    /// Applies the values set in `text` to this ruleset, e.g. to layer rule changes on top of
+    /// each other. Stops at the first error.
+    pub fn apply(&mut self, text: &str) -> Result<(), String> {
+        let mut section: Option<String> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim().to_string();
+                // Check the section exists right away, so typos don't go unnoticed
+                if !self.has_section(&name) {
+                    return Err(format!("Line {}: Unknown section {}.", i + 1, name));
+                }
+                section = Some(name);
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .ok_or(format!("Line {}: Expected 'key = value'.", i + 1))?;
+            let section = section.as_ref()
+                .ok_or(format!("Line {}: Values must be in a section.", i + 1))?;
+            self.set(section, key.trim(), value.trim())
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        }
+
+        Ok(())
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        section == "counter" || section == "damage" || self.formulas().iter().any(|(name, _)| *name == section)
+    }
+
+    /// Lists all formulas by section name
+    fn formulas(&self) -> [(&str, &Formula); 13] {
+        [
+            ("max_hp", &self.max_hp), ("max_mp", &self.max_mp), ("max_ap", &self.max_ap),
+            ("action_points", &self.action_points), ("move_speed", &self.move_speed),
+            ("phys_defense", &self.phys_defense), ("mag_defense", &self.mag_defense),
+            ("mobility", &self.mobility), ("health_regen", &self.health_regen),
+            ("magic_regen", &self.magic_regen), ("max_vit", &self.max_vit),
+            ("carry_slots", &self.carry_slots), ("barehanded_blow", &self.barehanded_blow),
+        ]
+    }
+
+    fn formula_mut(&mut self, section: &str) -> Option<&mut Formula> {
+        match section {
+            "max_hp" => Some(&mut self.max_hp),
+            "max_mp" => Some(&mut self.max_mp),
+            "max_ap" => Some(&mut self.max_ap),
+            "action_points" => Some(&mut self.action_points),
+            "move_speed" => Some(&mut self.move_speed),
+            "phys_defense" => Some(&mut self.phys_defense),
+            "mag_defense" => Some(&mut self.mag_defense),
+            "mobility" => Some(&mut self.mobility),
+            "health_regen" => Some(&mut self.health_regen),
+            "magic_regen" => Some(&mut self.magic_regen),
+            "max_vit" => Some(&mut self.max_vit),
+            "carry_slots" => Some(&mut self.carry_slots),
+            "barehanded_blow" => Some(&mut self.barehanded_blow),
+            _ => None,
+        }
+    }
+
+    /// Sets the value `key` of `section` to `value`
+    fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), String> {
+        match (section, key) {
+            ("counter", "reduction_cutoff") => self.counter.reduction_cutoff = parse_value(value)?,
+            ("counter", "reduction_cost") => self.counter.reduction_cost = parse_value(value)?,
+            ("counter", "damage_cutoff") => self.counter.damage_cutoff = parse_value(value)?,
+            ("counter", "damage_cost") => self.counter.damage_cost = parse_value(value)?,
+            ("damage", "phys_defense_factor") => self.damage.phys_defense_factor = parse_value(value)?,
+            ("damage", "mag_defense_factor") => self.damage.mag_defense_factor = parse_value(value)?,
+            ("damage", "zap_defense_factor") => self.damage.zap_defense_factor = parse_value(value)?,
+            ("counter", _) | ("damage", _) => return Err(format!("Unknown {} rule {}.", section, key)),
+            _ => match self.formula_mut(section) {
+                Some(formula) => formula.set(key, value)?,
+                None => return Err(format!("Unknown section {}.", section)),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Writes the ruleset in the file format read by `Ruleset::parse`
+impl Display for Ruleset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, formula) in self.formulas() {
+            writeln!(f, "[{}]", name)?;
+            formula.write(f)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "[counter]")?;
+        writeln!(f, "reduction_cutoff = {}", self.counter.reduction_cutoff)?;
+        writeln!(f, "reduction_cost = {}", self.counter.reduction_cost)?;
+        writeln!(f, "damage_cutoff = {}", self.counter.damage_cutoff)?;
+        writeln!(f, "damage_cost = {}", self.counter.damage_cost)?;
+        writeln!(f)?;
+        writeln!(f, "[damage]")?;
+        writeln!(f, "phys_defense_factor = {}", self.damage.phys_defense_factor)?;
+        writeln!(f, "mag_defense_factor = {}", self.damage.mag_defense_factor)?;
+        writeln!(f, "zap_defense_factor = {}", self.damage.zap_defense_factor)
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value {}.", value))
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Active Ruleset ~~~~~~~~~~~~~~~~~~~
+
+/// The ruleset active for the whole game (if not the built-in one), with its generation
+static ACTIVE: RwLock<Option<(u64, Arc<Ruleset>)>> = RwLock::new(None);
+
+/// Every activation of a ruleset gets a fresh generation, so caches of values calculated from
+/// rules (e.g. `GameStats`) can tell when the rules changed. Generation 0 is the built-in ruleset.
+static GENERATIONS: AtomicU64 = AtomicU64::new(1);
+
+/// The generation of the ruleset active for the whole game, readable without locking `ACTIVE`
+static ACTIVE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The ruleset overriding the game's ruleset on the current thread, with its generation
+    static THREAD_ACTIVE: RefCell<Option<(u64, Arc<Ruleset>)>> = RefCell::new(None);
+}
+
+fn built_in() -> &'static Arc<Ruleset> {
+    static BUILT_IN: OnceLock<Arc<Ruleset>> = OnceLock::new();
+    BUILT_IN.get_or_init(|| Arc::new(Ruleset::default()))
+}
+
+/// Hands out a fresh generation for a ruleset about to be activated. Rulesets activated again
+/// and again (e.g. by `with_generation` every turn) keep their generation, so cached values
+/// survive in between.
+pub fn new_generation() -> u64 {
+    GENERATIONS.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Gives the active rulesets (the game's and the current thread's) fresh generations, so all
+/// values cached from them are calculated anew. Called when something else all characters' game
+/// stats depend on changes (e.g. reloaded scripts).
+pub fn bump_generation() {
+    let mut active = ACTIVE.write().unwrap();
+    let ruleset = active.take().map_or_else(|| built_in().clone(), |(_, ruleset)| ruleset);
+    let generation = new_generation();
+    *active = Some((generation, ruleset));
+    ACTIVE_GENERATION.store(generation, Ordering::Release);
+
+    THREAD_ACTIVE.with(|active| if let Some((generation, _)) = active.borrow_mut().as_mut() {
+        *generation = new_generation();
+    });
+}
+
+/// Activates `ruleset` for the whole game
+pub fn set_ruleset(ruleset: Ruleset) {
+    let generation = new_generation();
+    let mut active = ACTIVE.write().unwrap();
+    *active = Some((generation, Arc::new(ruleset)));
+    ACTIVE_GENERATION.store(generation, Ordering::Release);
+}
+
+/// Restores the ruleset the current thread had before `with_ruleset` once dropped, even if the
+/// overriding closure panicked
+struct ThreadRulesetGuard(Option<(u64, Arc<Ruleset>)>);
+
+impl Drop for ThreadRulesetGuard {
+    fn drop(&mut self) {
+        THREAD_ACTIVE.with(|active| *active.borrow_mut() = self.0.take());
+    }
+}
+
+/// Runs `f` with `ruleset` active on the current thread only, overriding the game's ruleset
+pub fn with_ruleset<R>(ruleset: Arc<Ruleset>, f: impl FnOnce() -> R) -> R {
+    with_generation(new_generation(), ruleset, f)
+}
+
+/// Like `with_ruleset`, but with `ruleset` keeping the given `generation` (see `new_generation`),
+/// so values cached while it was active before are still up to date
+pub fn with_generation<R>(generation: u64, ruleset: Arc<Ruleset>, f: impl FnOnce() -> R) -> R {
+    let _guard = ThreadRulesetGuard(THREAD_ACTIVE.with(|active| active.replace(Some((generation, ruleset)))));
+    f()
+}
+
+/// Returns the active ruleset, with its generation
+pub fn active_ruleset() -> (u64, Arc<Ruleset>) {
+    if let Some(active) = THREAD_ACTIVE.with(|active| active.borrow().clone()) {
+        return active;
+    }
+    match ACTIVE.read().unwrap().as_ref() {
+        Some(active) => active.clone(),
+        None => (0, built_in().clone()),
+    }
+}
+
+/// Returns the generation of the active ruleset without fetching the ruleset itself, e.g. to check
+/// whether cached values are still up to date
+pub fn active_generation() -> u64 {
+    THREAD_ACTIVE.with(|active| active.borrow().as_ref().map(|(generation, _)| *generation))
+        .unwrap_or_else(|| ACTIVE_GENERATION.load(Ordering::Acquire))
+}
+
+/// Returns the active ruleset
+pub fn ruleset() -> Arc<Ruleset> {
+    active_ruleset().1
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ruleset_files() {
+        let ruleset = Ruleset::parse("# Tankier characters\n[max_hp]\nbase = 150 # up from 100\ngrt = 3\n\n\
+                                      [counter]\ndamage_cutoff = 0.5\n").unwrap();
+        assert_eq!(ruleset.max_hp.base, 150);
+        assert_eq!(ruleset.max_hp.weights.grt, 3);
+        assert_eq!(ruleset.max_hp.weights.wil, 2);
+        assert_eq!(ruleset.counter.damage_cutoff, 0.5);
+        assert_eq!(ruleset.mobility, Ruleset::default().mobility);
+
+        // Written rulesets read back the same
+        assert_eq!(Ruleset::parse(&ruleset.to_string()).unwrap(), ruleset);
+
+        assert_eq!(Ruleset::parse("[max_hp]\nluck = 2").unwrap_err(), "Line 2: Unknown formula coefficient luck.");
+        assert_eq!(Ruleset::parse("[max_luck]").unwrap_err(), "Line 1: Unknown section max_luck.");
+        assert!(Ruleset::parse("base = 100").is_err());
+    }
+
+    #[test]
+    fn test_thread_ruleset() {
+        let stats = Stats { dex: 1, str: 1, grt: 1, wil: 1, cha: 1, int: 1 };
+        let mut tanky = Ruleset::default();
+        tanky.max_hp.base = 1000;
+
+        // The built-in rules are the game's hard-coded formulas
+        assert_eq!(stats.max_hp(), 172);
+        let tanky_hp = with_ruleset(Arc::new(tanky), || stats.max_hp());
+        assert_eq!(tanky_hp, 1072);
+        assert_eq!(stats.max_hp(), 172);
+
+        // The thread's rules are restored even if the closure panics
+        let mut tanky = Ruleset::default();
+        tanky.max_hp.base = 1000;
+        assert!(std::panic::catch_unwind(|| with_ruleset(Arc::new(tanky), || panic!("Simulation failed"))).is_err());
+        assert_eq!(stats.max_hp(), 172);
+
+        // Rulesets activated with a generation keep it, so cached values stay up to date
+        let generation = new_generation();
+        let tanky = Arc::new(Ruleset::default());
+        assert_eq!(with_generation(generation, tanky.clone(), active_generation), generation);
+        assert_eq!(with_generation(generation, tanky.clone(), active_generation), generation);
+        assert_ne!(with_ruleset(tanky, active_generation), generation);
+    }
+}
@@ -0,0 +1,1843 @@
+//! Contains the embedded scripting language, which lets content authors define `Maneuver`s,
+//! `Reaction`s and `Effect`s without recompiling the game.
+//!
+//! Scripts define abilities by kind and name, each with properties and hooks:
+//!
+//! ```text
+//! maneuver "Fireball" {
+//!     describe = "Hurls fire at up to two opponents."
+//!     mp_cost = 4
+//!     execute {
+//!         for target in targets(2) {
+//!             attack(target, "MAG", "Fire", stats.int * 3)
+//!         }
+//!     }
+//! }
+//!
+//! reaction "Thorns" {
+//!     ap_cost = 1
+//!     react {
+//!         if action.is_attack and action.targets_me {
+//!             attack(action.source, "PHY", "Thorns", action.damage / 4)
+//!         }
+//!     }
+//! }
+//!
+//! effect "Weakened" {
+//!     negative = true
+//!     stats { stats.str = stats.str - 2 }
+//! }
+//! ```
+//!
+//! Hooks are small programs with `let`, assignments, `if`/`else`, `for .. in` and `return`.
+//! Scripts run **sandboxed**: They can only read the game through the values they are given (e.g.
+//! `me`, `stats`, `action`) and the query functions, and only change it by emitting actions.
+//! Every run is limited in steps, so no script can stall the game.
+//!
+//! Script abilities are registered by name with a `ScriptRegistry`. They only keep their name, so
+//! they are referenced (e.g. by items) by name, and always run their currently loaded definition.
+//!
+//! The language is written here instead of embedding Rhai or Lua, since the game has no
+//! dependencies, and a language this small can be sandboxed completely. There's no save format
+//! yet, so saves can't refer to script abilities; once there is, they should store ability names
+//! the same way item files do.
+//!
+//! # Hooks
+//!
+//! * `maneuver`: `execute` emits the maneuver's actions, `available` returns whether it can be used
+//!   (optional). Properties: `describe`, `mp_cost`, `cooldown`, `charge_turns`, `range`
+//!   (`"melee"` or `"ranged"`).
+//! * `reaction`: `react` emits the reaction's actions, if any. Properties: `describe`, `ap_cost`,
+//!   `mp_cost`.
+//! * `effect`: `stats` changes the `stats` of the affected character, `game_stats` changes
+//!   `mhp`, `mmp`, `tap`, `mve`, `pdf`, `mdf`, `mob`, `hrg` and `mrg`, and `on_damage` returns the
+//!   adjusted damage `amount` (of `damage_type` and `damage_subtype`). Properties: `describe`,
+//!   `negative`, `stacking` (`"refresh"`, `"intensity"`, `"strongest"`, `"independent"`),
+//!   `max_stacks`, `strength`, `order`.
+//!
+//! # API
+//!
+//! Characters have the fields `name`, `party`, `hp`, `mp`, `ap`, `max_hp`, `max_mp`, `max_ap`,
+//! `hp_ratio`, `defeated`, `row`, `pdf`, `mdf`, `mob`, `mve` and `stats` (with `dex`, `str`,
+//! `grt`, `wil`, `cha` and `int`). The `action` a reaction responds to has the fields `kind`,
+//! `is_attack`, `is_reaction`, `damage`, `damage_type`, `damage_subtype`, `source`, `targets`
+//! and `targets_me`.
+//!
+//! * Queries: `characters()`, `allies()` and `opponents()` (standing only), `targets(count)`
+//!   (opponents in reach, by preference)
+//! * Actions: `attack(target, type, "subtype", amount)` (with the subtype written out as text),
+//!   `heal(target, unit, amount)`, `give_effect(target, effect, turns)` (a script effect),
+//!   `reposition(target, row)`, and for reactions `cancel()`, `adjust_damage(factor)`, `adjust_damage_by(amount)`, `redirect(target)`
+//! * Utilities: `len`, `min`, `max`, `abs`, `int`, `float`
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::{Arc, Mutex, RwLock};
+use crate::battlefield::{Range, Row};
+use crate::characters::{CharUnit, Character, CharStat, CharacterRef, GameStats, Stats};
+use crate::combat::{Action, ActionCost, ActionEffect, Damage, DamageType, EntityPointer};
+use crate::effects::{Effect, StackingPolicy};
+use crate::mov::{select_opponents, Maneuver, Move, Reaction};
+use crate::world::WorldContext;
+
+/// Maximum number of steps (statements and expressions) a single hook may run
+const MAX_STEPS: usize = 10_000;
+
+/// Maximum nesting of blocks and expressions in a script, so parsing can't exhaust the stack
+const MAX_DEPTH: usize = 64;
+
+// ~~~~~~~~~~~~~~~~~~~ Tokens ~~~~~~~~~~~~~~~~~~~
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Sym(&'static str),
+    /// Ends a statement (line break or `;`)
+    Newline,
+    Eof,
+}
+
+const SYMBOLS: [&str; 21] = ["==", "!=", "<=", ">=", "(", ")", "{", "}", "[", "]", ",", ".", "=", "<", ">",
+    "+", "-", "*", "/", "%", ";"];
+
+const KEYWORDS: [&str; 14] = ["let", "if", "else", "for", "in", "return", "and", "or", "not", "true", "false",
+    "maneuver", "reaction", "effect"];
+
+/// Splits `source` into tokens, each with its line number
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' || c == ';' {
+            tokens.push((Token::Newline, line));
+            if c == '\n' {
+                line += 1;
+            }
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None | Some('\n') => return Err(format!("Line {}: Unterminated string.", line)),
+                    Some('"') => break,
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some('n') => text.push('\n'),
+                            Some(c) => text.push(*c),
+                            None => return Err(format!("Line {}: Unterminated string.", line)),
+                        }
+                        i += 1;
+                    }
+                    Some(c) => text.push(*c),
+                }
+                i += 1;
+            }
+            tokens.push((Token::Str(text), line));
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let is_float = chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit());
+            if is_float {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let number: String = chars[start..i].iter().collect();
+            let token = if is_float {
+                Token::Float(number.parse().map_err(|_| format!("Line {}: Invalid number {}.", line, number))?)
+            } else {
+                Token::Int(number.parse().map_err(|_| format!("Line {}: Invalid number {}.", line, number))?)
+            };
+            tokens.push((token, line));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else {
+            let symbol = SYMBOLS.iter().find(|s| s.chars().enumerate().all(|(k, sc)| chars.get(i + k) == Some(&sc)))
+                .ok_or(format!("Line {}: Unexpected character '{}'.", line, c))?;
+            tokens.push((Token::Sym(symbol), line));
+            i += symbol.len();
+        }
+    }
+
+    tokens.push((Token::Eof, line));
+    Ok(tokens)
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Syntax Tree ~~~~~~~~~~~~~~~~~~~
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum BinOp {
+    Or, And, Eq, Ne, Lt, Le, Gt, Ge, Add, Sub, Mul, Div, Rem,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    List(Vec<Expr>),
+    Var(String),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Debug)]
+enum StmtKind {
+    Let(String, Expr),
+    /// Assigns to a variable, or to a field of a variable
+    Assign(String, Option<String>, Expr),
+    /// Branches with their conditions, and the `else` branch
+    If(Vec<(Expr, Block)>, Option<Block>),
+    For(String, Expr, Block),
+    Return(Expr),
+    Expr(Expr),
+}
+
+#[derive(Clone, Debug)]
+struct Stmt {
+    line: usize,
+    kind: StmtKind,
+}
+
+type Block = Vec<Stmt>;
+
+// ~~~~~~~~~~~~~~~~~~~ Parser ~~~~~~~~~~~~~~~~~~~
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+/// A parsed ability definition: its kind, name, properties and hooks
+struct Definition {
+    kind: String,
+    name: String,
+    line: usize,
+    properties: Vec<(String, Expr, usize)>,
+    hooks: HashMap<String, Block>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>) -> Parser {
+        Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("Line {}: {}", self.line(), message))
+    }
+
+    fn is_sym(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Sym(s) if *s == symbol)
+    }
+
+    fn is_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Token::Ident(i) if i == ident)
+    }
+
+    fn eat_sym(&mut self, symbol: &str) -> bool {
+        if self.is_sym(symbol) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_sym(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_sym(symbol) {
+            Ok(())
+        } else {
+            self.error(&format!("Expected '{}'.", symbol))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, String> {
+        match self.peek().clone() {
+            Token::Ident(ident) if !KEYWORDS.contains(&ident.as_str()) => {
+                self.next();
+                Ok(ident)
+            }
+            _ => self.error("Expected a name."),
+        }
+    }
+
+    /// Runs `parse` one nesting level deeper, failing once `MAX_DEPTH` is exceeded
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Parser) -> Result<T, String>) -> Result<T, String> {
+        if self.depth >= MAX_DEPTH {
+            return self.error(&format!("Nested more than {} levels deep.", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn skip_newlines(&mut self) {
+        while *self.peek() == Token::Newline {
+            self.next();
+        }
+    }
+
+    /// Parses a whole script file into its definitions
+    fn parse_file(&mut self) -> Result<Vec<Definition>, String> {
+        let mut definitions = vec![];
+        loop {
+            self.skip_newlines();
+            if *self.peek() == Token::Eof {
+                return Ok(definitions);
+            }
+            definitions.push(self.parse_definition()?);
+        }
+    }
+
+    fn parse_definition(&mut self) -> Result<Definition, String> {
+        let line = self.line();
+        let kind = match self.next() {
+            Token::Ident(kind) if ["maneuver", "reaction", "effect"].contains(&kind.as_str()) => kind,
+            _ => return Err(format!("Line {}: Expected 'maneuver', 'reaction' or 'effect'.", line)),
+        };
+        let name = match self.next() {
+            Token::Str(name) => name,
+            _ => return Err(format!("Line {}: Expected the {}'s name in quotes.", line, kind)),
+        };
+
+        let mut definition = Definition {
+            kind,
+            name,
+            line,
+            properties: vec![],
+            hooks: HashMap::new(),
+        };
+
+        self.expect_sym("{")?;
+        loop {
+            self.skip_newlines();
+            if self.eat_sym("}") {
+                return Ok(definition);
+            }
+            let line = self.line();
+            let key = self.expect_ident()?;
+            if self.eat_sym("=") {
+                definition.properties.push((key, self.parse_expr()?, line));
+            } else if self.is_sym("{") {
+                let block = self.parse_block()?;
+                if definition.hooks.insert(key.clone(), block).is_some() {
+                    return Err(format!("Line {}: Hook {} is defined twice.", line, key));
+                }
+            } else {
+                return self.error(&format!("Expected '=' or '{{' after {}.", key));
+            }
+            self.expect_end_of_statement()?;
+        }
+    }
+
+    fn expect_end_of_statement(&mut self) -> Result<(), String> {
+        match self.peek() {
+            Token::Newline | Token::Eof => Ok(()),
+            Token::Sym("}") => Ok(()),
+            _ => self.error("Expected the end of the line."),
+        }
+    }
+
+    fn parse_block(&mut self) -> Result<Block, String> {
+        self.nested(Parser::parse_block_contents)
+    }
+
+    fn parse_block_contents(&mut self) -> Result<Block, String> {
+        self.expect_sym("{")?;
+        let mut block = vec![];
+        loop {
+            self.skip_newlines();
+            if self.eat_sym("}") {
+                return Ok(block);
+            }
+            if *self.peek() == Token::Eof {
+                return self.error("Expected '}'.");
+            }
+            block.push(self.parse_stmt()?);
+            self.expect_end_of_statement()?;
+        }
+    }
+
+    fn parse_stmt(&mut self) -> Result<Stmt, String> {
+        let line = self.line();
+        let kind = if self.is_ident("let") {
+            self.next();
+            let name = self.expect_ident()?;
+            self.expect_sym("=")?;
+            StmtKind::Let(name, self.parse_expr()?)
+        } else if self.is_ident("if") {
+            let mut branches = vec![];
+            let mut otherwise = None;
+            self.next();
+            branches.push((self.parse_expr()?, self.parse_block()?));
+            loop {
+                // `else` may follow on the next line
+                let before = self.pos;
+                self.skip_newlines();
+                if !self.is_ident("else") {
+                    self.pos = before;
+                    break;
+                }
+                self.next();
+                if self.is_ident("if") {
+                    self.next();
+                    branches.push((self.parse_expr()?, self.parse_block()?));
+                } else {
+                    otherwise = Some(self.parse_block()?);
+                    break;
+                }
+            }
+            StmtKind::If(branches, otherwise)
+        } else if self.is_ident("for") {
+            self.next();
+            let name = self.expect_ident()?;
+            if !self.is_ident("in") {
+                return self.error("Expected 'in'.");
+            }
+            self.next();
+            StmtKind::For(name, self.parse_expr()?, self.parse_block()?)
+        } else if self.is_ident("return") {
+            self.next();
+            StmtKind::Return(self.parse_expr()?)
+        } else {
+            let expr = self.parse_expr()?;
+            if self.eat_sym("=") {
+                match expr {
+                    Expr::Var(name) => StmtKind::Assign(name, None, self.parse_expr()?),
+                    Expr::Field(target, field) => match *target {
+                        Expr::Var(name) => StmtKind::Assign(name, Some(field), self.parse_expr()?),
+                        _ => return Err(format!("Line {}: Only variables and their fields can be assigned.", line)),
+                    },
+                    _ => return Err(format!("Line {}: Only variables and their fields can be assigned.", line)),
+                }
+            } else {
+                StmtKind::Expr(expr)
+            }
+        };
+        Ok(Stmt { line, kind })
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        self.nested(Parser::parse_or)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.is_ident("or") {
+            self.next();
+            expr = Expr::Binary(BinOp::Or, Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_not()?;
+        while self.is_ident("and") {
+            self.next();
+            expr = Expr::Binary(BinOp::And, Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.is_ident("not") {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.nested(Parser::parse_not)?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let expr = self.parse_sum()?;
+        let op = match self.peek() {
+            Token::Sym("==") => BinOp::Eq,
+            Token::Sym("!=") => BinOp::Ne,
+            Token::Sym("<") => BinOp::Lt,
+            Token::Sym("<=") => BinOp::Le,
+            Token::Sym(">") => BinOp::Gt,
+            Token::Sym(">=") => BinOp::Ge,
+            _ => return Ok(expr),
+        };
+        self.next();
+        Ok(Expr::Binary(op, Box::new(expr), Box::new(self.parse_sum()?)))
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Token::Sym("+") => BinOp::Add,
+                Token::Sym("-") => BinOp::Sub,
+                _ => return Ok(expr),
+            };
+            self.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Sym("*") => BinOp::Mul,
+                Token::Sym("/") => BinOp::Div,
+                Token::Sym("%") => BinOp::Rem,
+                _ => return Ok(expr),
+            };
+            self.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.eat_sym("-") {
+            return Ok(Expr::Neg(Box::new(self.nested(Parser::parse_unary)?)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.eat_sym(".") {
+                expr = Expr::Field(Box::new(expr), self.expect_ident()?);
+            } else if self.eat_sym("[") {
+                let index = self.parse_expr()?;
+                self.expect_sym("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Parses comma separated expressions up to the `closing` symbol
+    fn parse_list(&mut self, closing: &str) -> Result<Vec<Expr>, String> {
+        let mut items = vec![];
+        loop {
+            self.skip_newlines();
+            if self.eat_sym(closing) {
+                return Ok(items);
+            }
+            items.push(self.parse_expr()?);
+            self.skip_newlines();
+            if !self.eat_sym(",") {
+                self.skip_newlines();
+                self.expect_sym(closing)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.peek().clone() {
+            Token::Int(i) => {
+                self.next();
+                Ok(Expr::Int(i))
+            }
+            Token::Float(f) => {
+                self.next();
+                Ok(Expr::Float(f))
+            }
+            Token::Str(s) => {
+                self.next();
+                Ok(Expr::Str(s))
+            }
+            Token::Ident(i) if i == "true" || i == "false" => {
+                self.next();
+                Ok(Expr::Bool(i == "true"))
+            }
+            Token::Sym("(") => {
+                self.next();
+                let expr = self.parse_expr()?;
+                self.expect_sym(")")?;
+                Ok(expr)
+            }
+            Token::Sym("[") => {
+                self.next();
+                Ok(Expr::List(self.parse_list("]")?))
+            }
+            Token::Ident(_) => {
+                let name = self.expect_ident()?;
+                if self.eat_sym("(") {
+                    let args = self.parse_list(")")?;
+                    // Damage subtypes are fixed when loading, so attacks must spell them out
+                    if name == "attack" && args.len() == 4 {
+                        match &args[2] {
+                            Expr::Str(subtype) => { intern(subtype).or_else(|e| self.error(&e))?; }
+                            _ => return self.error("Damage subtypes must be written as text."),
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                } else {
+                    Ok(Expr::Var(name))
+                }
+            }
+            _ => self.error("Expected a value."),
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Interpreter ~~~~~~~~~~~~~~~~~~~
+
+/// A value scripts work with. Characters and actions are borrowed from the game for the duration
+/// of the hook, so scripts can read but never change them.
+#[derive(Clone)]
+enum Value<'a> {
+    Unit,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    List(Vec<Value<'a>>),
+    Character(&'a Character),
+    Action(&'a Action),
+    Stats(Stats),
+}
+
+impl<'a> Value<'a> {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unit => "nothing",
+            Value::Int(_) => "an integer",
+            Value::Float(_) => "a number",
+            Value::Bool(_) => "a boolean",
+            Value::Str(_) => "a string",
+            Value::List(_) => "a list",
+            Value::Character(_) => "a character",
+            Value::Action(_) => "an action",
+            Value::Stats(_) => "stats",
+        }
+    }
+
+    fn as_int(&self) -> Result<i64, String> {
+        match self {
+            Value::Int(i) => Ok(*i),
+            Value::Float(f) => Ok(f.floor() as i64),
+            _ => Err(format!("Expected a number, got {}.", self.type_name())),
+        }
+    }
+
+    fn as_float(&self) -> Result<f64, String> {
+        match self {
+            Value::Int(i) => Ok(*i as f64),
+            Value::Float(f) => Ok(*f),
+            _ => Err(format!("Expected a number, got {}.", self.type_name())),
+        }
+    }
+
+    fn as_bool(&self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(format!("Expected a boolean, got {}.", self.type_name())),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, String> {
+        match self {
+            Value::Str(s) => Ok(s),
+            _ => Err(format!("Expected a string, got {}.", self.type_name())),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Value::Unit => String::new(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Str(s) => s.clone(),
+            Value::List(l) => format!("[{}]", l.iter().map(|v| v.to_text()).collect::<Vec<_>>().join(", ")),
+            Value::Character(c) => c.name().clone(),
+            Value::Action(a) => a.get_effect().short_name().to_string(),
+            Value::Stats(s) => s.format_as_req_string(),
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Unit, Value::Unit) => true,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) =>
+                self.as_float().ok() == other.as_float().ok(),
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y)),
+            (Value::Character(a), Value::Character(b)) => a.id() == b.id(),
+            (Value::Stats(a), Value::Stats(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Signals how a block was left
+enum Flow<'a> {
+    /// Ran to its end, with the value of its last expression
+    Normal(Value<'a>),
+    /// Left by `return`
+    Return(Value<'a>),
+}
+
+/// Runs a hook. Scripts only touch the game through the interpreter, which keeps the actions
+/// they emit.
+struct Interpreter<'a> {
+    context: Option<&'a dyn WorldContext>,
+    /// The character using the ability
+    me: Option<&'a Character>,
+    /// The action a reaction responds to
+    action: Option<&'a Action>,
+    /// Reach of the maneuver, used to find its targets
+    range: Range,
+    /// Registry to look up script effects in
+    registry: Option<&'a ScriptRegistry>,
+    scopes: Vec<HashMap<String, Value<'a>>>,
+    emitted: Vec<Action>,
+    steps: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    /// An interpreter without access to the game, e.g. for effect hooks and properties
+    fn detached() -> Interpreter<'a> {
+        Interpreter {
+            context: None,
+            me: None,
+            action: None,
+            range: Range::Ranged,
+            registry: None,
+            scopes: vec![HashMap::new()],
+            emitted: vec![],
+            steps: 0,
+        }
+    }
+
+    /// An interpreter for abilities `me` uses in `context`
+    fn attached(me: &'a Character, context: &'a dyn WorldContext, registry: &'a ScriptRegistry) -> Interpreter<'a> {
+        let mut interpreter = Interpreter::detached();
+        interpreter.context = Some(context);
+        interpreter.me = Some(me);
+        interpreter.registry = Some(registry);
+        interpreter.define("me", Value::Character(me));
+        interpreter.define("stats", Value::Stats(me.calculate_current_stats()));
+        interpreter
+    }
+
+    fn define(&mut self, name: &str, value: Value<'a>) {
+        self.scopes.last_mut().unwrap().insert(name.to_string(), value);
+    }
+
+    fn lookup(&self, name: &str) -> Result<Value<'a>, String> {
+        self.scopes.iter().rev().find_map(|s| s.get(name)).cloned()
+            .ok_or(format!("Unknown variable {}.", name))
+    }
+
+    fn tick(&mut self) -> Result<(), String> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(format!("Exceeded the limit of {} steps.", MAX_STEPS));
+        }
+        Ok(())
+    }
+
+    /// Runs `block` in a new scope
+    fn run(&mut self, block: &Block) -> Result<Flow<'a>, String> {
+        self.scopes.push(HashMap::new());
+        let flow = self.run_stmts(block);
+        self.scopes.pop();
+        flow
+    }
+
+    /// Runs a hook, returning its value (the value returned, or of its last expression)
+    fn run_hook(&mut self, block: &Block) -> Result<Value<'a>, String> {
+        match self.run(block)? {
+            Flow::Normal(value) | Flow::Return(value) => Ok(value),
+        }
+    }
+
+    fn run_stmts(&mut self, block: &Block) -> Result<Flow<'a>, String> {
+        let mut last = Value::Unit;
+        for stmt in block {
+            let flow = self.run_stmt(stmt).map_err(|e|
+                // Keep the line of the innermost statement that failed
+                if e.starts_with("Line ") { e } else { format!("Line {}: {}", stmt.line, e) })?;
+            match flow {
+                Flow::Normal(value) => last = value,
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+            }
+        }
+        Ok(Flow::Normal(last))
+    }
+
+    fn run_stmt(&mut self, stmt: &Stmt) -> Result<Flow<'a>, String> {
+        self.tick()?;
+        match &stmt.kind {
+            StmtKind::Let(name, expr) => {
+                let value = self.eval(expr)?;
+                self.define(name, value);
+            }
+            StmtKind::Assign(name, field, expr) => {
+                let value = self.eval(expr)?;
+                let slot = self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name))
+                    .ok_or(format!("Unknown variable {}.", name))?;
+                match (field, slot) {
+                    (None, slot) => *slot = value,
+                    (Some(field), Value::Stats(stats)) => {
+                        let value = value.as_int()?;
+                        match field.as_str() {
+                            "dex" => stats.dex = value,
+                            "str" => stats.str = value,
+                            "grt" => stats.grt = value,
+                            "wil" => stats.wil = value,
+                            "cha" => stats.cha = value,
+                            "int" => stats.int = value,
+                            _ => return Err(format!("Stats have no field {}.", field)),
+                        }
+                    }
+                    (Some(field), slot) => return Err(format!("Can't change {} of {}.", field, slot.type_name())),
+                }
+            }
+            StmtKind::If(branches, otherwise) => {
+                for (condition, block) in branches {
+                    if self.eval(condition)?.as_bool()? {
+                        return self.run(block);
+                    }
+                }
+                if let Some(block) = otherwise {
+                    return self.run(block);
+                }
+            }
+            StmtKind::For(name, expr, block) => {
+                let items = match self.eval(expr)? {
+                    Value::List(items) => items,
+                    other => return Err(format!("Can only loop over lists, not {}.", other.type_name())),
+                };
+                for item in items {
+                    self.scopes.push(HashMap::from([(name.clone(), item)]));
+                    let flow = self.run_stmts(block);
+                    self.scopes.pop();
+                    if let Flow::Return(value) = flow? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            }
+            StmtKind::Return(expr) => return Ok(Flow::Return(self.eval(expr)?)),
+            StmtKind::Expr(expr) => return Ok(Flow::Normal(self.eval(expr)?)),
+        }
+        Ok(Flow::Normal(Value::Unit))
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value<'a>, String> {
+        self.tick()?;
+        Ok(match expr {
+            Expr::Int(i) => Value::Int(*i),
+            Expr::Float(f) => Value::Float(*f),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::List(items) => Value::List(items.iter().map(|i| self.eval(i)).collect::<Result<_, _>>()?),
+            Expr::Var(name) => self.lookup(name)?,
+            Expr::Field(target, field) => {
+                let target = self.eval(target)?;
+                self.field(&target, field)?
+            }
+            Expr::Index(target, index) => {
+                let index = self.eval(index)?.as_int()?;
+                match self.eval(target)? {
+                    Value::List(items) => usize::try_from(index).ok().and_then(|i| items.get(i).cloned())
+                        .ok_or(format!("Index {} is out of bounds.", index))?,
+                    other => return Err(format!("Can't index {}.", other.type_name())),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args.iter().map(|a| self.eval(a)).collect::<Result<Vec<_>, _>>()?;
+                self.call(name, args)?
+            }
+            Expr::Neg(expr) => match self.eval(expr)? {
+                Value::Int(i) => Value::Int(i.checked_neg().ok_or(format!("Can't negate {}.", i))?),
+                other => Value::Float(-other.as_float()?),
+            },
+            Expr::Not(expr) => Value::Bool(!self.eval(expr)?.as_bool()?),
+            Expr::Binary(BinOp::And, a, b) => Value::Bool(self.eval(a)?.as_bool()? && self.eval(b)?.as_bool()?),
+            Expr::Binary(BinOp::Or, a, b) => Value::Bool(self.eval(a)?.as_bool()? || self.eval(b)?.as_bool()?),
+            Expr::Binary(op, a, b) => {
+                let a = self.eval(a)?;
+                let b = self.eval(b)?;
+                Self::binary(*op, a, b)?
+            }
+        })
+    }
+
+    fn binary(op: BinOp, a: Value<'a>, b: Value<'a>) -> Result<Value<'a>, String> {
+        Ok(match op {
+            BinOp::Eq => Value::Bool(a.equals(&b)),
+            BinOp::Ne => Value::Bool(!a.equals(&b)),
+            BinOp::Lt => Value::Bool(a.as_float()? < b.as_float()?),
+            BinOp::Le => Value::Bool(a.as_float()? <= b.as_float()?),
+            BinOp::Gt => Value::Bool(a.as_float()? > b.as_float()?),
+            BinOp::Ge => Value::Bool(a.as_float()? >= b.as_float()?),
+            BinOp::Add => match (a, b) {
+                (Value::Str(a), b) => Value::Str(a + &b.to_text()),
+                (a, Value::Str(b)) => Value::Str(a.to_text() + &b),
+                (Value::List(mut a), Value::List(b)) => {
+                    a.extend(b);
+                    Value::List(a)
+                }
+                (Value::Int(a), Value::Int(b)) => Value::Int(a.saturating_add(b)),
+                (a, b) => Value::Float(a.as_float()? + b.as_float()?),
+            },
+            BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem => match (a, b) {
+                (Value::Int(a), Value::Int(b)) => Value::Int(match op {
+                    BinOp::Sub => a.saturating_sub(b),
+                    BinOp::Mul => a.saturating_mul(b),
+                    _ if b == 0 => return Err("Division by zero.".to_string()),
+                    BinOp::Div => a.checked_div_euclid(b).ok_or(format!("Can't divide {} by {}.", a, b))?,
+                    _ => a.checked_rem_euclid(b).ok_or(format!("Can't divide {} by {}.", a, b))?,
+                }),
+                (a, b) => {
+                    let (a, b) = (a.as_float()?, b.as_float()?);
+                    Value::Float(match op {
+                        BinOp::Sub => a - b,
+                        BinOp::Mul => a * b,
+                        _ if b == 0f64 => return Err("Division by zero.".to_string()),
+                        BinOp::Div => a / b,
+                        _ => a.rem_euclid(b),
+                    })
+                }
+            },
+            BinOp::And | BinOp::Or => unreachable!("Handled lazily"),
+        })
+    }
+
+    fn field(&self, target: &Value<'a>, field: &str) -> Result<Value<'a>, String> {
+        Ok(match (target, field) {
+            (Value::Stats(s), _) => Value::Int(match field {
+                "dex" => s.dex,
+                "str" => s.str,
+                "grt" => s.grt,
+                "wil" => s.wil,
+                "cha" => s.cha,
+                "int" => s.int,
+                _ => return Err(format!("Stats have no field {}.", field)),
+            }),
+            (Value::Character(c), _) => match field {
+                "name" => Value::Str(c.name().clone()),
+                "party" => Value::Str(c.party().clone()),
+                "hp" => Value::Int(c.hp()),
+                "mp" => Value::Int(c.mp()),
+                "ap" => Value::Int(c.ap()),
+                "max_hp" => Value::Int(c.calculate_game_stats().mhp()),
+                "max_mp" => Value::Int(c.calculate_game_stats().mmp()),
+                "max_ap" => Value::Int(c.calculate_game_stats().map()),
+                "pdf" => Value::Int(c.calculate_game_stats().pdf()),
+                "mdf" => Value::Int(c.calculate_game_stats().mdf()),
+                "mob" => Value::Int(c.calculate_game_stats().mob()),
+                "mve" => Value::Int(c.calculate_game_stats().mve()),
+                "hp_ratio" => Value::Float(c.hp_to_max_hp_ratio()),
+                "defeated" => Value::Bool(c.is_defeated()),
+                "row" => Value::Str(match c.get_row() { Row::Front => "front", Row::Back => "back" }.to_string()),
+                "stats" => Value::Stats(c.calculate_current_stats()),
+                _ => return Err(format!("Characters have no field {}.", field)),
+            },
+            (Value::Action(a), _) => {
+                let me = self.me.map(|c| c.id());
+                let damage = match a.get_effect() {
+                    ActionEffect::Attack(d) => Some(me.and_then(|id| a.damage_for(id)).unwrap_or(*d)),
+                    _ => None,
+                };
+                match field {
+                    "kind" => Value::Str(a.get_effect().short_name().to_string()),
+                    "is_attack" => Value::Bool(damage.is_some()),
+                    "is_reaction" => Value::Bool(a.get_reaction().is_some()),
+                    "damage" => Value::Int(damage.map_or(0, |d| d.amount())),
+                    "damage_type" => Value::Str(damage.map_or("", |d| d.dmg_type().get_damage_type_name()).to_string()),
+                    "damage_subtype" => Value::Str(damage.map_or("", |d| d.dmg_type().get_subtype_name()).to_string()),
+                    "targets_me" => Value::Bool(me.is_some_and(|id| a.targets_character(id))),
+                    "source" => self.characters_of(a.get_source()).into_iter().next().unwrap_or(Value::Unit),
+                    "targets" => Value::List(self.characters_of(a.get_target())),
+                    _ => return Err(format!("Actions have no field {}.", field)),
+                }
+            }
+            (Value::List(l), "len") => Value::Int(l.len() as i64),
+            _ => return Err(format!("{} has no field {}.", target.type_name(), field)),
+        })
+    }
+
+    /// Looks up the characters `pointer` points to
+    fn characters_of(&self, pointer: &EntityPointer) -> Vec<Value<'a>> {
+        match (pointer, self.context) {
+            (EntityPointer::Character(refs), Some(context)) => refs.iter()
+                .filter_map(|r| context.get_character_by_id(r.id()))
+                .map(Value::Character)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn context(&self) -> Result<(&'a dyn WorldContext, &'a Character), String> {
+        match (self.context, self.me) {
+            (Some(context), Some(me)) => Ok((context, me)),
+            _ => Err("The game can't be accessed here.".to_string()),
+        }
+    }
+
+    fn reacted_to(&self) -> Result<&'a Action, String> {
+        self.action.ok_or("Only reactions can change the action they react to.".to_string())
+    }
+
+    /// Builds an entity pointer to the character(s) of `target`
+    fn target_of(target: &Value<'a>) -> Result<EntityPointer, String> {
+        let refs: Vec<CharacterRef> = match target {
+            Value::Character(c) => vec![c.to_ref()],
+            Value::List(items) => items.iter().map(|i| match i {
+                Value::Character(c) => Ok(c.to_ref()),
+                other => Err(format!("Expected characters, got {}.", other.type_name())),
+            }).collect::<Result<_, _>>()?,
+            other => return Err(format!("Expected a character, got {}.", other.type_name())),
+        };
+        Ok(EntityPointer::Character(refs))
+    }
+
+    fn emit(&mut self, effect: ActionEffect, target: EntityPointer) -> Result<Value<'a>, String> {
+        let (_, me) = self.context()?;
+        self.emitted.push(Action::from_source(me.as_target(), effect, target));
+        Ok(Value::Unit)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value<'a>>) -> Result<Value<'a>, String> {
+        let arity = |n: usize| if args.len() == n { Ok(()) } else {
+            Err(format!("{} takes {} argument(s), got {}.", name, n, args.len()))
+        };
+        match name {
+            // Utilities
+            "len" => {
+                arity(1)?;
+                match &args[0] {
+                    Value::List(l) => Ok(Value::Int(l.len() as i64)),
+                    Value::Str(s) => Ok(Value::Int(s.chars().count() as i64)),
+                    other => Err(format!("{} has no length.", other.type_name())),
+                }
+            }
+            "min" | "max" => {
+                arity(2)?;
+                let pick_first = (args[0].as_float()? <= args[1].as_float()?) == (name == "min");
+                Ok(args[if pick_first { 0 } else { 1 }].clone())
+            }
+            "abs" => {
+                arity(1)?;
+                match &args[0] {
+                    Value::Int(i) => Ok(Value::Int(i.saturating_abs())),
+                    other => Ok(Value::Float(other.as_float()?.abs())),
+                }
+            }
+            "int" => {
+                arity(1)?;
+                Ok(Value::Int(args[0].as_int()?))
+            }
+            "float" => {
+                arity(1)?;
+                Ok(Value::Float(args[0].as_float()?))
+            }
+
+            // Queries
+            "characters" | "allies" | "opponents" => {
+                arity(0)?;
+                let (context, me) = self.context()?;
+                let found = match name {
+                    "characters" => context.find_characters(&|_: &Character| true),
+                    "allies" => context.find_characters(&|c: &Character| c.party_check(me.party()) && !c.is_defeated()),
+                    _ => context.find_characters(&|c: &Character| !c.party_check(me.party()) && !c.is_defeated()),
+                };
+                Ok(Value::List(found.into_iter().map(Value::Character).collect()))
+            }
+            "targets" => {
+                arity(1)?;
+                let (context, me) = self.context()?;
+                let count = args[0].as_int()?.max(0) as usize;
+                Ok(Value::List(select_opponents(me, context, count, self.range).into_iter().map(Value::Character).collect()))
+            }
+
+            // Actions
+            "attack" => {
+                arity(4)?;
+                let subtype = interned(args[2].as_str()?)?;
+                let damage_type = match args[1].as_str()? {
+                    "PHY" => DamageType::PHY(subtype),
+                    "MAG" => DamageType::MAG(subtype),
+                    "ZAP" => DamageType::ZAP(subtype),
+                    "ULT" => DamageType::ULT,
+                    other => return Err(format!("Unknown damage type {}.", other)),
+                };
+                let amount = args[3].as_int()?.max(0);
+                self.emit(ActionEffect::Attack(Damage(damage_type, amount)), Self::target_of(&args[0])?)
+            }
+            "heal" => {
+                arity(3)?;
+                let amount = args[2].as_int()?;
+                if amount < 0 {
+                    return Err(format!("Can't heal a negative amount ({}).", amount));
+                }
+                let unit = match args[1].as_str()? {
+                    "HP" => CharUnit::HP(amount),
+                    "MP" => CharUnit::MP(amount),
+                    "AP" => CharUnit::AP(amount),
+                    "VIT" => CharUnit::VIT(amount),
+                    other => return Err(format!("Unknown unit {}.", other)),
+                };
+                self.emit(ActionEffect::Heal(unit), Self::target_of(&args[0])?)
+            }
+            "give_effect" => {
+                arity(3)?;
+                let effect_name = args[1].as_str()?;
+                let registry = self.registry.ok_or("Effects can't be given here.".to_string())?;
+                let effect = registry.effect(effect_name)?;
+                self.emit(ActionEffect::GiveTimedEffect(effect, args[2].as_int()?), Self::target_of(&args[0])?)
+            }
+            "reposition" => {
+                arity(2)?;
+                let row = match args[1].as_str()? {
+                    "front" => Row::Front,
+                    "back" => Row::Back,
+                    other => return Err(format!("Unknown row {}.", other)),
+                };
+                self.emit(ActionEffect::Reposition(row), Self::target_of(&args[0])?)
+            }
+            "cancel" => {
+                arity(0)?;
+                let action = self.reacted_to()?;
+                self.emit(ActionEffect::Cancel, action.build_self_target())
+            }
+            "adjust_damage" => {
+                arity(1)?;
+                let action = self.reacted_to()?;
+                let factor = args[0].as_float()?;
+                // Like counters, only adjust my own share of group attacks
+                let effect = match self.me {
+                    Some(me) if action.get_target().num_entities() > 1 && action.targets_character(me.id()) =>
+                        ActionEffect::AdjustDamageMulFor(me.id(), factor),
+                    _ => ActionEffect::AdjustDamageMul(factor),
+                };
+                self.emit(effect, action.build_self_target())
+            }
+            "adjust_damage_by" => {
+                arity(1)?;
+                let action = self.reacted_to()?;
+                self.emit(ActionEffect::AdjustDamageAbs(args[0].as_int()?), action.build_self_target())
+            }
+            "redirect" => {
+                arity(1)?;
+                let action = self.reacted_to()?;
+                self.emit(ActionEffect::ChangeTarget(Self::target_of(&args[0])?), action.build_self_target())
+            }
+            _ => Err(format!("Unknown function {}.", name)),
+        }
+    }
+}
+
+/// Maximum number of distinct damage subtypes scripts and content files may name, counted over
+/// the whole process including all reloads
+const MAX_SUBTYPES: usize = 256;
+
+static SUBTYPES: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+/// Damage subtypes are static strings, so the subtypes written in scripts and content files are
+/// leaked once per distinct name while loading them. Loading a name again, e.g. when reloading
+/// scripts, returns the name leaked the first time. Running scripts can't add new subtypes, and
+/// at most `MAX_SUBTYPES` names are ever kept: names dropped by a reload still count, so reloads
+/// that keep making up new subtypes eventually fail to load.
+pub(crate) fn intern(name: &str) -> Result<&'static str, String> {
+    let mut subtypes = SUBTYPES.lock().unwrap();
+    let subtypes = subtypes.get_or_insert_with(HashSet::new);
+    match subtypes.get(name) {
+        Some(name) => Ok(name),
+        None if subtypes.len() >= MAX_SUBTYPES =>
+            Err(format!("Too many damage subtypes, can't add {}.", name)),
+        None => {
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            subtypes.insert(name);
+            Ok(name)
+        }
+    }
+}
+
+/// Looks up a damage subtype that was already interned while loading
+fn interned(name: &str) -> Result<&'static str, String> {
+    SUBTYPES.lock().unwrap().as_ref().and_then(|subtypes| subtypes.get(name).copied())
+        .ok_or(format!("Unknown damage subtype {}.", name))
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Compiled Definitions ~~~~~~~~~~~~~~~~~~~
+
+struct ManeuverDef {
+    describe: String,
+    mp_cost: i64,
+    cooldown: i64,
+    charge_turns: i64,
+    range: Range,
+    available: Option<Block>,
+    execute: Block,
+}
+
+struct ReactionDef {
+    describe: String,
+    ap_cost: i64,
+    mp_cost: i64,
+    react: Block,
+}
+
+struct EffectDef {
+    describe: String,
+    negative: bool,
+    stacking: StackingPolicy,
+    strength: i64,
+    order: i64,
+    stats: Option<Block>,
+    game_stats: Option<Block>,
+    on_damage: Option<Block>,
+}
+
+/// Reads the properties of a definition, making sure every property is known and evaluated once
+struct Properties<'d> {
+    definition: &'d Definition,
+    values: HashMap<&'d str, (Value<'static>, usize)>,
+}
+
+impl<'d> Properties<'d> {
+    fn of(definition: &'d Definition, known: &[&str], hooks: &[&str]) -> Result<Properties<'d>, String> {
+        let mut values = HashMap::new();
+        for (key, expr, line) in definition.properties.iter() {
+            if !known.contains(&key.as_str()) {
+                return Err(format!("Line {}: Unknown {} property {}.", line, definition.kind, key));
+            }
+            // Properties are constant, so they are evaluated once, without access to the game
+            let value = Interpreter::detached().eval(expr).map_err(|e| format!("Line {}: {}", line, e))?;
+            values.insert(key.as_str(), (value, *line));
+        }
+        if let Some(hook) = definition.hooks.keys().find(|h| !hooks.contains(&h.as_str())) {
+            return Err(format!("Line {}: Unknown {} hook {}.", definition.line, definition.kind, hook));
+        }
+        Ok(Properties {
+            definition,
+            values,
+        })
+    }
+
+    fn get<T>(&self, key: &str, default: T, convert: impl Fn(&Value<'static>) -> Result<T, String>) -> Result<T, String> {
+        match self.values.get(key) {
+            Some((value, line)) => convert(value).map_err(|e| format!("Line {}: {}: {}", line, key, e)),
+            None => Ok(default),
+        }
+    }
+
+    fn describe(&self) -> Result<String, String> {
+        self.get("describe", self.definition.name.clone(), |v| v.as_str().map(|s| s.to_string()))
+    }
+
+    fn hook(&self, name: &str) -> Option<Block> {
+        self.definition.hooks.get(name).cloned()
+    }
+
+    fn required_hook(&self, name: &str) -> Result<Block, String> {
+        self.hook(name).ok_or(format!("Line {}: {} {} needs a {} hook.", self.definition.line,
+                                      self.definition.kind, self.definition.name, name))
+    }
+}
+
+fn compile_maneuver(definition: &Definition) -> Result<ManeuverDef, String> {
+    let p = Properties::of(definition, &["describe", "mp_cost", "cooldown", "charge_turns", "range"],
+                           &["available", "execute"])?;
+    Ok(ManeuverDef {
+        describe: p.describe()?,
+        mp_cost: p.get("mp_cost", 0, |v| v.as_int())?,
+        cooldown: p.get("cooldown", 0, |v| v.as_int())?,
+        charge_turns: p.get("charge_turns", 0, |v| v.as_int())?,
+        range: p.get("range", Range::Ranged, |v| match v.as_str()? {
+            "melee" => Ok(Range::Melee),
+            "ranged" => Ok(Range::Ranged),
+            other => Err(format!("Unknown range {}.", other)),
+        })?,
+        available: p.hook("available"),
+        execute: p.required_hook("execute")?,
+    })
+}
+
+fn compile_reaction(definition: &Definition) -> Result<ReactionDef, String> {
+    let p = Properties::of(definition, &["describe", "ap_cost", "mp_cost"], &["react"])?;
+    Ok(ReactionDef {
+        describe: p.describe()?,
+        ap_cost: p.get("ap_cost", 1, |v| v.as_int())?,
+        mp_cost: p.get("mp_cost", 0, |v| v.as_int())?,
+        react: p.required_hook("react")?,
+    })
+}
+
+fn compile_effect(definition: &Definition) -> Result<EffectDef, String> {
+    let p = Properties::of(definition, &["describe", "negative", "stacking", "max_stacks", "strength", "order"],
+                           &["stats", "game_stats", "on_damage"])?;
+    let max_stacks = p.get("max_stacks", 3, |v| v.as_int())?.max(1) as usize;
+    Ok(EffectDef {
+        describe: p.describe()?,
+        negative: p.get("negative", false, |v| v.as_bool())?,
+        stacking: p.get("stacking", StackingPolicy::Refresh, |v| match v.as_str()? {
+            "refresh" => Ok(StackingPolicy::Refresh),
+            "intensity" => Ok(StackingPolicy::Intensity(max_stacks)),
+            "strongest" => Ok(StackingPolicy::Strongest),
+            "independent" => Ok(StackingPolicy::Independent),
+            other => Err(format!("Unknown stacking policy {}.", other)),
+        })?,
+        strength: p.get("strength", 0, |v| v.as_int())?,
+        order: p.get("order", 1, |v| v.as_int())?,
+        stats: p.hook("stats"),
+        game_stats: p.hook("game_stats"),
+        on_damage: p.hook("on_damage"),
+    })
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Registry ~~~~~~~~~~~~~~~~~~~
+
+/// All loaded definitions, by name
+#[derive(Default)]
+struct Definitions {
+    maneuvers: HashMap<String, Arc<ManeuverDef>>,
+    reactions: HashMap<String, Arc<ReactionDef>>,
+    effects: HashMap<String, Arc<EffectDef>>,
+    /// The last error each ability ran into while running, by name
+    errors: HashMap<String, String>,
+}
+
+/// Registers script abilities by name. Registries are cheap to clone, and all clones share the
+/// same definitions.
+#[derive(Clone, Default)]
+pub struct ScriptRegistry {
+    definitions: Arc<RwLock<Definitions>>,
+}
+
+impl ScriptRegistry {
+    pub fn new() -> ScriptRegistry {
+        ScriptRegistry::default()
+    }
+
+    /// Loads all definitions of the script file at `path`. See `load_str`.
+    pub fn load(&self, path: &str) -> Result<Vec<String>, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Could not read script {}: {}", path, e))?;
+        self.load_str(&source).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Compiles and registers all definitions of `source`, returning their names. Definitions
+    /// replace earlier ones of the same kind and name. If any definition doesn't compile, nothing
+    /// is registered.
+    pub fn load_str(&self, source: &str) -> Result<Vec<String>, String> {
+        let parsed = Parser::new(tokenize(source)?).parse_file()?;
+
+        let mut maneuvers = vec![];
+        let mut reactions = vec![];
+        let mut effects = vec![];
+        for definition in parsed.iter() {
+            match definition.kind.as_str() {
+                "maneuver" => maneuvers.push((definition.name.clone(), Arc::new(compile_maneuver(definition)?))),
+                "reaction" => reactions.push((definition.name.clone(), Arc::new(compile_reaction(definition)?))),
+                _ => effects.push((definition.name.clone(), Arc::new(compile_effect(definition)?))),
+            }
+        }
+
+        let mut definitions = self.definitions.write().unwrap();
+        for (name, definition) in maneuvers {
+            definitions.errors.remove(&name);
+            definitions.maneuvers.insert(name, definition);
+        }
+        for (name, definition) in reactions {
+            definitions.errors.remove(&name);
+            definitions.reactions.insert(name, definition);
+        }
+        for (name, definition) in effects {
+            definitions.errors.remove(&name);
+            definitions.effects.insert(name, definition);
+        }
+        Ok(parsed.into_iter().map(|d| d.name).collect())
+    }
+
+    /// Replaces all definitions with those of `staged`, e.g. once reloaded scripts have been
+    /// validated. Script abilities of this registry run the new definitions from now on, and
+    /// abilities that are no longer defined do nothing. Cached game stats are calculated anew, as
+    /// script effects may change them differently now.
+    pub fn replace_with(&self, staged: &ScriptRegistry) {
+        if Arc::ptr_eq(&self.definitions, &staged.definitions) {
+            return;
+        }
+        {
+            let staged = staged.definitions.read().unwrap();
+            let mut definitions = self.definitions.write().unwrap();
+            definitions.maneuvers = staged.maneuvers.clone();
+            definitions.reactions = staged.reactions.clone();
+            definitions.effects = staged.effects.clone();
+            definitions.errors.clear();
+        }
+        crate::ruleset::bump_generation();
+    }
+
+    /// Returns the script maneuver `name`
+    pub fn maneuver(&self, name: &str) -> Result<Box<dyn Maneuver>, String> {
+        match self.definitions.read().unwrap().maneuvers.contains_key(name) {
+            true => Ok(Box::new(ScriptManeuver { name: name.to_string(), registry: self.clone() })),
+            false => Err(format!("There is no script maneuver {}.", name)),
+        }
+    }
+
+    /// Returns the script reaction `name`
+    pub fn reaction(&self, name: &str) -> Result<Box<dyn Reaction>, String> {
+        match self.definitions.read().unwrap().reactions.contains_key(name) {
+            true => Ok(Box::new(ScriptReaction { name: name.to_string(), registry: self.clone() })),
+            false => Err(format!("There is no script reaction {}.", name)),
+        }
+    }
+
+    /// Returns the script effect `name`
+    pub fn effect(&self, name: &str) -> Result<Box<dyn Effect>, String> {
+        match self.definitions.read().unwrap().effects.contains_key(name) {
+            true => Ok(Box::new(ScriptEffect { name: name.to_string(), registry: self.clone() })),
+            false => Err(format!("There is no script effect {}.", name)),
+        }
+    }
+
+    /// Returns the last error the ability `name` ran into, if any. Abilities that fail simply
+    /// do nothing, so the game carries on.
+    pub fn last_error(&self, name: &str) -> Option<String> {
+        self.definitions.read().unwrap().errors.get(name).cloned()
+    }
+
+    fn get_maneuver(&self, name: &str) -> Option<Arc<ManeuverDef>> {
+        self.definitions.read().unwrap().maneuvers.get(name).cloned()
+    }
+
+    fn get_reaction(&self, name: &str) -> Option<Arc<ReactionDef>> {
+        self.definitions.read().unwrap().reactions.get(name).cloned()
+    }
+
+    fn get_effect(&self, name: &str) -> Option<Arc<EffectDef>> {
+        self.definitions.read().unwrap().effects.get(name).cloned()
+    }
+
+    /// Unwraps the `result` of running the ability `name`, keeping the error (if any)
+    fn report<T>(&self, name: &str, result: Result<T, String>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.definitions.write().unwrap().errors.insert(name.to_string(), e);
+                None
+            }
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~ Script Abilities ~~~~~~~~~~~~~~~~~~~
+
+/// A maneuver defined by a script. Runs the definition currently registered under its name, and
+/// does nothing if there is none.
+pub struct ScriptManeuver {
+    name: String,
+    registry: ScriptRegistry,
+}
+
+impl Move for ScriptManeuver {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn describe(&self) -> String {
+        self.registry.get_maneuver(&self.name).map_or(String::new(), |d| d.describe.clone())
+    }
+
+    fn mp_cost(&self) -> i64 {
+        self.registry.get_maneuver(&self.name).map_or(0, |d| d.mp_cost)
+    }
+}
+
+impl Maneuver for ScriptManeuver {
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        let definition = match self.registry.get_maneuver(&self.name) {
+            Some(definition) => definition,
+            None => return vec![],
+        };
+        let mut interpreter = Interpreter::attached(character, context, &self.registry);
+        interpreter.range = definition.range;
+        let result = interpreter.run_hook(&definition.execute);
+        if self.registry.report(&self.name, result).is_none() {
+            return vec![];
+        }
+
+        // Like spells, the MP cost is paid once for all actions
+        let mut actions = interpreter.emitted;
+        Action::share_cost(&mut actions, ActionCost { ap: 0, mp: definition.mp_cost });
+        actions
+    }
+
+    /// Script maneuvers can only be chosen if the character can afford them, and their
+    /// `available` hook (if any) agrees
+    fn is_available(&self, character: &Character, context: &dyn WorldContext) -> bool {
+        let definition = match self.registry.get_maneuver(&self.name) {
+            Some(definition) => definition,
+            None => return false,
+        };
+        if character.mp() < definition.mp_cost {
+            return false;
+        }
+        match &definition.available {
+            Some(hook) => {
+                let mut interpreter = Interpreter::attached(character, context, &self.registry);
+                interpreter.range = definition.range;
+                let result = interpreter.run_hook(hook).and_then(|v| v.as_bool());
+                self.registry.report(&self.name, result).unwrap_or(false)
+            }
+            None => true,
+        }
+    }
+
+    fn range(&self) -> Range {
+        self.registry.get_maneuver(&self.name).map_or(Range::Melee, |d| d.range)
+    }
+
+    fn cooldown(&self) -> i64 {
+        self.registry.get_maneuver(&self.name).map_or(0, |d| d.cooldown)
+    }
+
+    fn charge_turns(&self) -> i64 {
+        self.registry.get_maneuver(&self.name).map_or(0, |d| d.charge_turns)
+    }
+}
+
+/// A reaction defined by a script. Runs the definition currently registered under its name, and
+/// never reacts if there is none.
+pub struct ScriptReaction {
+    name: String,
+    registry: ScriptRegistry,
+}
+
+impl Move for ScriptReaction {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn describe(&self) -> String {
+        self.registry.get_reaction(&self.name).map_or(String::new(), |d| d.describe.clone())
+    }
+
+    fn mp_cost(&self) -> i64 {
+        self.registry.get_reaction(&self.name).map_or(0, |d| d.mp_cost)
+    }
+}
+
+impl Reaction for ScriptReaction {
+    fn ap_cost(&self) -> i64 {
+        self.registry.get_reaction(&self.name).map_or(0, |d| d.ap_cost)
+    }
+
+    fn react(&self, character: &Character, action: &Action, context: &dyn WorldContext) -> Option<Vec<Action>> {
+        let definition = self.registry.get_reaction(&self.name)?;
+        let mut interpreter = Interpreter::attached(character, context, &self.registry);
+        interpreter.action = Some(action);
+        interpreter.define("action", Value::Action(action));
+        let result = interpreter.run_hook(&definition.react);
+        self.registry.report(&self.name, result)?;
+
+        // Reactions that didn't emit anything don't apply
+        match interpreter.emitted.is_empty() {
+            true => None,
+            false => Some(interpreter.emitted),
+        }
+    }
+}
+
+/// An effect defined by a script. Uses the definition currently registered under its name, and
+/// has no effect if there is none.
+#[derive(Clone)]
+pub struct ScriptEffect {
+    name: String,
+    registry: ScriptRegistry,
+}
+
+impl ScriptEffect {
+    /// Runs `hook` of the effect's definition with the given variables, returning its value and
+    /// the variables afterwards
+    fn run<'a>(&self, hook: impl Fn(&EffectDef) -> Option<&Block>, variables: Vec<(&str, Value<'a>)>)
+        -> Option<(Value<'a>, HashMap<String, Value<'a>>)> {
+        let definition = self.registry.get_effect(&self.name)?;
+        let block = hook(&definition)?;
+        let mut interpreter = Interpreter::detached();
+        for (name, value) in variables {
+            interpreter.define(name, value);
+        }
+        let result = interpreter.run_hook(block);
+        let value = self.registry.report(&self.name, result)?;
+        Some((value, interpreter.scopes.pop().unwrap_or_default()))
+    }
+}
+
+impl Effect for ScriptEffect {
+    fn describe(&self) -> String {
+        self.registry.get_effect(&self.name).map_or(self.name.clone(), |d| d.describe.clone())
+    }
+
+    fn apply_to_stats(&self, stats: &mut Stats) {
+        if let Some((_, variables)) = self.run(|d| d.stats.as_ref(), vec![("stats", Value::Stats(*stats))]) {
+            if let Some(Value::Stats(changed)) = variables.get("stats") {
+                *stats = *changed;
+            }
+        }
+    }
+
+    fn apply_to_game_stats(&self, game_stats: &mut GameStats) {
+        let current = [
+            ("mhp", game_stats.mhp()), ("mmp", game_stats.mmp()), ("tap", game_stats.tap()),
+            ("mve", game_stats.mve()), ("pdf", game_stats.pdf()), ("mdf", game_stats.mdf()),
+            ("mob", game_stats.mob()), ("hrg", game_stats.hrg()), ("mrg", game_stats.mrg()),
+        ];
+        let variables = current.iter().map(|(name, value)| (*name, Value::Int(*value))).collect();
+        let (_, variables) = match self.run(|d| d.game_stats.as_ref(), variables) {
+            Some(result) => result,
+            None => return,
+        };
+
+        // Game stats can only be changed by adding to them
+        for (name, value) in current {
+            let delta = match variables.get(name).map(|v| v.as_int()) {
+                Some(Ok(changed)) => changed - value,
+                _ => continue,
+            };
+            game_stats.add(&match name {
+                "mhp" => CharStat::MHP(delta),
+                "mmp" => CharStat::MMP(delta),
+                "tap" => CharStat::TAP(delta),
+                "mve" => CharStat::MVE(delta),
+                "pdf" => CharStat::PDF(delta),
+                "mdf" => CharStat::MDF(delta),
+                "mob" => CharStat::MOB(delta),
+                "hrg" => CharStat::HRG(delta),
+                _ => CharStat::MRG(delta),
+            });
+        }
+    }
+
+    fn on_damage_receive(&self, damage: &Damage, amount: i64) -> i64 {
+        let variables = vec![
+            ("amount", Value::Int(amount)),
+            ("damage_type", Value::Str(damage.dmg_type().get_damage_type_name().to_string())),
+            ("damage_subtype", Value::Str(damage.dmg_type().get_subtype_name().to_string())),
+        ];
+        match self.run(|d| d.on_damage.as_ref(), variables) {
+            Some((value, _)) => value.as_int().unwrap_or(amount),
+            None => amount,
+        }
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+
+    fn stacking(&self) -> StackingPolicy {
+        self.registry.get_effect(&self.name).map_or(StackingPolicy::Refresh, |d| d.stacking)
+    }
+
+    fn strength(&self) -> i64 {
+        self.registry.get_effect(&self.name).map_or(0, |d| d.strength)
+    }
+
+    fn is_negative(&self) -> bool {
+        self.registry.get_effect(&self.name).is_some_and(|d| d.negative)
+    }
+
+    fn effect_order(&self) -> i64 {
+        self.registry.get_effect(&self.name).map_or(1, |d| d.order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combat::{Actor, Combat};
+    use super::*;
+
+    const SCRIPT: &str = r#"
+# Abilities used by the tests
+maneuver "Fireball" {
+    mp_cost = 4
+    execute {
+        for target in targets(2) {
+            attack(target, "MAG", "Fire", stats.int * 3)
+        }
+    }
+}
+
+reaction "Thorns" {
+    react {
+        if action.is_attack and action.targets_me {
+            attack(action.source, "PHY", "Thorns", action.damage / 4)
+        } else if action.is_attack { adjust_damage(0.5) }
+    }
+}
+
+effect "Burning" {
+    negative = true
+    stats { stats.grt = stats.grt - 2 }
+    on_damage {
+        if damage_subtype == "Fire" { return amount * 2 }
+        amount
+    }
+}
+"#;
+
+    fn test_character(name: &str, party: &str) -> Character {
+        let mut character = Character::new(name.to_string(), None, Stats {
+            str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+        character.set_party(party.to_string());
+        character
+    }
+
+    #[test]
+    fn test_script_abilities() {
+        let registry = ScriptRegistry::new();
+        assert_eq!(registry.load_str(SCRIPT).unwrap(), vec!["Fireball", "Thorns", "Burning"]);
+
+        let combat = Combat::from_participants(vec![
+            test_character("Mage", "Best Friends"),
+            test_character("Baddie", "Baddies!"),
+            test_character("Other Baddie", "Baddies!"),
+        ]);
+        let mage = combat.get_character("Mage").unwrap();
+        let baddie = combat.get_character("Baddie").unwrap();
+
+        // Fireball hits both baddies, and is paid for once by both actions
+        let fireball = registry.maneuver("Fireball").unwrap();
+        assert!(fireball.is_available(mage, &combat));
+        let actions = fireball.execute(mage, &combat);
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].get_shared_cost().map(|c| c.mp), Some(4));
+        assert!(matches!(actions[1].get_effect(), ActionEffect::Attack(Damage(DamageType::MAG("Fire"), 15))));
+
+        // Thorns strikes back at the attacker, and only reacts to attacks
+        let thorns = registry.reaction("Thorns").unwrap();
+        let reaction = thorns.react(baddie, &actions[0], &combat).unwrap();
+        assert!(matches!(reaction[0].get_effect(), ActionEffect::Attack(Damage(DamageType::PHY("Thorns"), 3))));
+        assert!(reaction[0].get_target().num_entities() == 1);
+        let heal = Action::from_source(mage.as_target(), ActionEffect::Heal(CharUnit::HP(5)), mage.as_target());
+        assert!(thorns.react(baddie, &heal, &combat).is_none());
+
+        // Burning lowers grit and doubles fire damage
+        let burning = registry.effect("Burning").unwrap();
+        let mut stats = mage.calculate_current_stats();
+        burning.apply_to_stats(&mut stats);
+        assert_eq!(stats.grt, 4);
+        assert_eq!(burning.on_damage_receive(&Damage(DamageType::MAG("Fire"), 10), 10), 20);
+        assert_eq!(burning.on_damage_receive(&Damage(DamageType::PHY("Cut"), 10), 10), 10);
+        assert!(burning.is_negative());
+
+        assert!(registry.maneuver("Meteor").is_err());
+    }
+
+    #[test]
+    fn test_script_errors() {
+        let registry = ScriptRegistry::new();
+        assert_eq!(registry.load_str("maneuver \"Broken\" {\n    execute {\n        let = 3\n    }\n}"),
+                   Err("Line 3: Expected a name.".to_string()));
+        assert_eq!(registry.load_str("reaction \"Lazy\" {\n    ap_cost = 1\n}"),
+                   Err("Line 1: reaction Lazy needs a react hook.".to_string()));
+
+        // Failing scripts do nothing, and their error is kept
+        registry.load_str("maneuver \"Loop\" {\n  execute {\n    for c in characters() {\n      \
+            attack(c, \"PHY\", \"Cut\", 1 / 0)\n    }\n  }\n}").unwrap();
+        let combat = Combat::from_participants(vec![test_character("Mage", "Best Friends")]);
+        let mage = combat.get_character("Mage").unwrap();
+        assert!(registry.maneuver("Loop").unwrap().execute(mage, &combat).is_empty());
+        assert_eq!(registry.last_error("Loop"), Some("Line 4: Division by zero.".to_string()));
+
+        // Overflowing arithmetic and negative heals are errors too
+        registry.load_str("maneuver \"Overflow\" {\n  execute {\n    let min = -9223372036854775807 - 1\n    \
+            heal(me, \"HP\", min / -1)\n  }\n}").unwrap();
+        assert!(registry.maneuver("Overflow").unwrap().execute(mage, &combat).is_empty());
+        assert_eq!(registry.last_error("Overflow"), Some("Line 4: Can't divide -9223372036854775808 by -1.".to_string()));
+        registry.load_str("maneuver \"Drain\" {\n  execute {\n    heal(me, \"HP\", -5)\n  }\n}").unwrap();
+        assert!(registry.maneuver("Drain").unwrap().execute(mage, &combat).is_empty());
+        assert_eq!(registry.last_error("Drain"), Some("Line 3: Can't heal a negative amount (-5).".to_string()));
+
+        // Damage subtypes can't be made up while running
+        assert_eq!(registry.load_str("maneuver \"Made Up\" {\n  execute {\n    \
+            attack(me, \"PHY\", \"Cut\" + 1, 1)\n  }\n}"),
+                   Err("Line 3: Damage subtypes must be written as text.".to_string()));
+
+        // Deeply nested scripts are refused while loading
+        let nested = format!("maneuver \"Deep\" {{\n  execute {{\n    let x = {}1{}\n  }}\n}}",
+                             "(".repeat(5000), ")".repeat(5000));
+        assert_eq!(registry.load_str(&nested), Err("Line 3: Nested more than 64 levels deep.".to_string()));
+    }
+
+    #[test]
+    fn test_reload_effect() {
+        let registry = ScriptRegistry::new();
+        registry.load_str("effect \"Sturdy\" {\n  stats { stats.grt = stats.grt + 2 }\n}").unwrap();
+        let mut mage = test_character("Mage", "Best Friends");
+        mage.apply_timed_effect(registry.effect("Sturdy").unwrap(), 3, None);
+        let before = mage.calculate_game_stats();
+
+        // Characters already under the effect get the reloaded modifier
+        let staged = ScriptRegistry::new();
+        staged.load_str("effect \"Sturdy\" {\n  stats { stats.grt = stats.grt + 20 }\n}").unwrap();
+        registry.replace_with(&staged);
+        assert!(mage.calculate_game_stats().mhp() > before.mhp());
+    }
+
+    #[test]
+    fn test_reload_subtypes() {
+        // Reloading a script reuses the subtypes it named before instead of leaking them again
+        let source = "maneuver \"Quake\" {\n  execute {\n    attack(me, \"PHY\", \"Tremor\", 1)\n  }\n}";
+        ScriptRegistry::new().load_str(source).unwrap();
+        let tremor = interned("Tremor").unwrap();
+        ScriptRegistry::new().load_str(source).unwrap();
+        assert!(std::ptr::eq(interned("Tremor").unwrap(), tremor));
+        assert!(std::ptr::eq(intern("Tremor").unwrap(), tremor));
+    }
+}
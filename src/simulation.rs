@@ -0,0 +1,497 @@
+//! Contains the Monte Carlo battle simulator, used to balance encounters and formulas.
+//!
+//! A `Simulation` runs many seeded copies of one encounter, spread across worker threads, and
+//! sums them up in a `SimulationReport`:
+//!
+//! * **Win rates** per party (runs without a winner within the turn limit count as undecided)
+//! * **Average turns** until combat ended
+//! * **HP remaining** per party, i.e. the share of the party's max HP left after each run
+//! * **Reaction impact**, i.e. how often each reaction triggered, and how much damage it dealt or
+//! prevented
+//!
+//! Every run `i` is seeded with `base_seed + i`, so the report of a simulation is reproducible
+//! regardless of the number of threads. Simulations run with the game's active `Ruleset`, unless
+//! given a ruleset of their own (e.g. to compare rulesets). Reports are exported as CSV (one row per run and party)
+//! or JSON (the summary).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use crate::combat::{ActionEffect, ActionStack, Combat, EntityPointer};
+use crate::world::{TurnLogger, WorldContext};
+use crate::ruleset::{active_ruleset, with_ruleset, Ruleset};
+
+/// Runs seeded copies of an encounter, built by the encounter function, across threads
+pub struct Simulation {
+    /// Builds a fresh combat of the simulated encounter for every run
+    encounter: Box<dyn Fn() -> Combat + Send + Sync>,
+    /// Number of runs
+    runs: usize,
+    /// Number of worker threads the runs are spread across
+    threads: usize,
+    /// Seed of the first run. Run `i` is seeded with `base_seed + i`.
+    base_seed: u64,
+    /// Runs still going on after this many turns are stopped without a winner
+    max_turns: u64,
+    /// The ruleset all runs use. If `None`, the game's active ruleset is used.
+    ruleset: Option<Arc<Ruleset>>,
+}
+
+impl Simulation {
+    pub fn new(encounter: impl Fn() -> Combat + Send + Sync + 'static, runs: usize) -> Simulation {
+        Simulation {
+            encounter: Box::new(encounter),
+            runs,
+            // By default, use all cores available
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            base_seed: 0,
+            max_turns: 100,
+            ruleset: None,
+        }
+    }
+
+    pub fn set_runs(&mut self, runs: usize) {
+        self.runs = runs;
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn set_base_seed(&mut self, base_seed: u64) {
+        self.base_seed = base_seed;
+    }
+
+    pub fn set_max_turns(&mut self, max_turns: u64) {
+        self.max_turns = max_turns;
+    }
+
+    pub fn set_ruleset(&mut self, ruleset: Option<Arc<Ruleset>>) {
+        self.ruleset = ruleset;
+    }
+
+    /// Runs the simulation, returning the report of all runs
+    pub fn run(&self) -> SimulationReport {
+        let chunk_size = self.runs.div_ceil(self.threads);
+        let seeds: Vec<u64> = (0..self.runs as u64).map(|i| self.base_seed.wrapping_add(i)).collect();
+        // Worker threads don't see rulesets only active on this thread, so hand them over
+        let ruleset = self.ruleset.clone().unwrap_or_else(|| active_ruleset().1);
+
+        let mut runs: Vec<RunResult> = thread::scope(|scope| {
+            let workers: Vec<_> = seeds.chunks(chunk_size.max(1))
+                .map(|chunk| {
+                    let ruleset = ruleset.clone();
+                    scope.spawn(move || with_ruleset(ruleset, ||
+                        chunk.iter().map(|seed| self.run_once(*seed)).collect::<Vec<_>>()))
+                })
+                .collect();
+            workers.into_iter().flat_map(|w| w.join().expect("Simulation run panicked")).collect()
+        });
+        // Chunks come back in order already, but keep the report independent of scheduling
+        runs.sort_by_key(|r| r.seed);
+
+        SimulationReport::from_runs(runs)
+    }
+
+    /// Runs a single copy of the encounter, seeded with `seed`
+    fn run_once(&self, seed: u64) -> RunResult {
+        let mut combat = (self.encounter)();
+        combat.set_seed(seed);
+
+        let mut parties: Vec<String> = vec![];
+        for character in combat.iter_characters() {
+            if !parties.contains(character.party()) {
+                parties.push(character.party().clone());
+            }
+        }
+
+        let mut tally = ReactionTally::new();
+        while combat.get_outcome().is_none() && combat.get_turn() < self.max_turns {
+            combat.process_turn(Some(&mut tally)).expect("Simulated turn failed");
+        }
+
+        // Winners are the last party standing, whether the others were defeated or escaped
+        let remaining = combat.remaining_parties();
+        let winner = if remaining.len() == 1 { Some(remaining[0].clone()) } else { None };
+
+        // Characters that escaped combat keep the HP they escaped with
+        let hp_remaining = parties.into_iter().map(|party| {
+            let members: Vec<_> = combat.iter_characters().chain(combat.iter_holding())
+                .filter(|c| c.party_check(&party))
+                .collect();
+            let hp: i64 = members.iter().map(|c| c.hp().max(0)).sum();
+            let max_hp: i64 = members.iter().map(|c| c.calculate_game_stats().mhp()).sum();
+            (party, if max_hp > 0 { hp as f64 / max_hp as f64 } else { 0f64 })
+        }).collect();
+
+        RunResult {
+            seed,
+            winner,
+            turns: combat.get_turn(),
+            hp_remaining,
+            reactions: tally.reactions,
+        }
+    }
+}
+
+/// The result of a single simulated run
+pub struct RunResult {
+    pub seed: u64,
+    /// The party that won, if any party won within the turn limit
+    pub winner: Option<String>,
+    pub turns: u64,
+    /// Share of each party's max HP remaining at the end, in order of appearance
+    pub hp_remaining: Vec<(String, f64)>,
+    /// Impact of every reaction that triggered, by reaction name
+    pub reactions: HashMap<String, ReactionImpact>,
+}
+
+/// Sums up how much a reaction mattered
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct ReactionImpact {
+    /// Number of actions the reaction put on the stack
+    pub actions: usize,
+    /// Damage dealt by the reaction's attacks
+    pub damage_dealt: i64,
+    /// Damage the reaction prevented by adjusting attacks
+    pub damage_prevented: i64,
+}
+
+impl ReactionImpact {
+    /// Total damage the reaction dealt or prevented
+    pub fn total(&self) -> i64 {
+        self.damage_dealt + self.damage_prevented
+    }
+
+    fn absorb(&mut self, other: &ReactionImpact) {
+        self.actions += other.actions;
+        self.damage_dealt += other.damage_dealt;
+        self.damage_prevented += other.damage_prevented;
+    }
+}
+
+/// Logs the impact of every reaction on the action stacks of a combat
+struct ReactionTally {
+    reactions: HashMap<String, ReactionImpact>,
+}
+
+impl ReactionTally {
+    fn new() -> ReactionTally {
+        ReactionTally {
+            reactions: HashMap::new(),
+        }
+    }
+}
+
+impl TurnLogger for ReactionTally {
+    fn maneuver_stack(&mut self, stack: &ActionStack) {
+        let actions: Vec<_> = stack.iter_actions().collect();
+        for action in actions.iter() {
+            let name = match action.get_reaction() {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // Damage adjustments are measured against the attack they adjust
+            let adjusted = match action.get_target() {
+                EntityPointer::Action(i) => actions.get(*i).and_then(|a| match a.get_effect() {
+                    ActionEffect::Attack(d) => Some(d.amount()),
+                    _ => None,
+                }),
+                _ => None,
+            };
+            let impact = self.reactions.entry(name.clone()).or_default();
+            impact.actions += 1;
+            match (action.get_effect(), adjusted) {
+                (ActionEffect::Attack(d), _) => impact.damage_dealt += d.amount(),
+                (ActionEffect::AdjustDamageAbs(a), Some(_)) => impact.damage_prevented += (-a).max(0),
+                (ActionEffect::AdjustDamageMul(f), Some(amount))
+                | (ActionEffect::AdjustDamageMulFor(_, f), Some(amount)) =>
+                    impact.damage_prevented += ((1f64 - f) * amount as f64).max(0f64) as i64,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A distribution of samples, e.g. the HP a party has remaining after each run
+#[derive(Clone, Default, Debug)]
+pub struct Distribution {
+    /// All samples, sorted
+    samples: Vec<f64>,
+}
+
+impl Distribution {
+    pub fn from_samples(mut samples: Vec<f64>) -> Distribution {
+        samples.sort_by(|a, b| a.total_cmp(b));
+        Distribution {
+            samples,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0f64;
+        }
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    /// Returns the sample at quantile `q` (in `[0, 1]`), by nearest rank
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.samples.is_empty() {
+            return 0f64;
+        }
+        let rank = (q.clamp(0f64, 1f64) * (self.samples.len() - 1) as f64).round() as usize;
+        self.samples[rank]
+    }
+}
+
+/// Sums up all runs of a `Simulation`
+pub struct SimulationReport {
+    /// All runs, ordered by seed
+    runs: Vec<RunResult>,
+    /// All parties taking part, in order of appearance
+    parties: Vec<String>,
+}
+
+impl SimulationReport {
+    pub fn from_runs(runs: Vec<RunResult>) -> SimulationReport {
+        let mut parties: Vec<String> = vec![];
+        for run in runs.iter() {
+            for (party, _) in run.hp_remaining.iter() {
+                if !parties.contains(party) {
+                    parties.push(party.clone());
+                }
+            }
+        }
+
+        SimulationReport {
+            runs,
+            parties,
+        }
+    }
+
+    pub fn iter_runs(&self) -> core::slice::Iter<RunResult> {
+        self.runs.iter()
+    }
+
+    pub fn get_parties(&self) -> &Vec<String> {
+        &self.parties
+    }
+
+    /// Share of runs the party named `party` won
+    pub fn win_rate(&self, party: &str) -> f64 {
+        self.share_of_runs(|r| r.winner.as_deref() == Some(party))
+    }
+
+    /// Share of runs nobody won within the turn limit
+    pub fn undecided_rate(&self) -> f64 {
+        self.share_of_runs(|r| r.winner.is_none())
+    }
+
+    pub fn average_turns(&self) -> f64 {
+        if self.runs.is_empty() {
+            return 0f64;
+        }
+        self.runs.iter().map(|r| r.turns as f64).sum::<f64>() / self.runs.len() as f64
+    }
+
+    /// Distribution of the share of max HP the party named `party` has remaining after each run
+    pub fn hp_remaining(&self, party: &str) -> Distribution {
+        Distribution::from_samples(self.runs.iter()
+            .flat_map(|r| r.hp_remaining.iter().filter(|(p, _)| p == party).map(|(_, hp)| *hp))
+            .collect())
+    }
+
+    /// Lists the impact of all reactions summed up across runs, most impactful first
+    pub fn reaction_impact(&self) -> Vec<(String, ReactionImpact)> {
+        let mut total: HashMap<String, ReactionImpact> = HashMap::new();
+        for run in self.runs.iter() {
+            for (name, impact) in run.reactions.iter() {
+                total.entry(name.clone()).or_default().absorb(impact);
+            }
+        }
+
+        let mut impact: Vec<(String, ReactionImpact)> = total.into_iter().collect();
+        impact.sort_by(|(a_name, a), (b_name, b)| b.total().cmp(&a.total())
+            .then(b.actions.cmp(&a.actions))
+            .then(a_name.cmp(b_name)));
+        impact
+    }
+
+    fn share_of_runs(&self, filter: impl Fn(&RunResult) -> bool) -> f64 {
+        if self.runs.is_empty() {
+            return 0f64;
+        }
+        self.runs.iter().filter(|r| filter(r)).count() as f64 / self.runs.len() as f64
+    }
+
+    /// Exports all runs as CSV, with one row per run and party
+    pub fn to_csv(&self) -> String {
+        let mut csv = "seed,party,won,turns,hp_remaining\n".to_string();
+        for run in self.runs.iter() {
+            for (party, hp) in run.hp_remaining.iter() {
+                csv.push_str(&format!("{},{},{},{},{:.4}\n", run.seed, csv_field(party),
+                                      run.winner.as_ref() == Some(party), run.turns, hp));
+            }
+        }
+        csv
+    }
+
+    /// Exports the summary of all runs as JSON
+    pub fn to_json(&self) -> String {
+        let parties: Vec<String> = self.parties.iter().map(|party| {
+            let hp = self.hp_remaining(party);
+            format!("{{\"name\":{},\"win_rate\":{:.4},\"hp_remaining\":{{\"mean\":{:.4},\"p10\":{:.4},\"median\":{:.4},\"p90\":{:.4}}}}}",
+                    json_string(party), self.win_rate(party), hp.mean(), hp.quantile(0.1), hp.quantile(0.5),
+                    hp.quantile(0.9))
+        }).collect();
+        let reactions: Vec<String> = self.reaction_impact().iter().map(|(name, impact)|
+            format!("{{\"name\":{},\"actions\":{},\"damage_dealt\":{},\"damage_prevented\":{}}}",
+                    json_string(name), impact.actions, impact.damage_dealt, impact.damage_prevented)
+        ).collect();
+
+        format!("{{\"runs\":{},\"average_turns\":{:.4},\"undecided_rate\":{:.4},\"parties\":[{}],\"reactions\":[{}]}}",
+                self.runs.len(), self.average_turns(), self.undecided_rate(), parties.join(","), reactions.join(","))
+    }
+}
+
+/// Quotes `field` for CSV if needed
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats `s` as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::{Character, Stats};
+    use crate::combat::DamageType;
+    use crate::equipment::{Equipment, EquipmentType};
+    use crate::mov::Counter;
+    use super::*;
+
+    fn test_character(name: &str, party: &str, f: i64) -> Character {
+        let mut character = Character::new(name.to_string(), None, Stats {
+            str: 3 * f, dex: 3 * f, grt: 3 * f, wil: 3 * f, int: 3 * f, cha: 3 * f,
+        });
+        character.set_party(party.to_string());
+        character
+    }
+
+    fn encounter() -> Combat {
+        let mut lindtbert = test_character("Lindtbert", "Best Friends", 3);
+        let mut ring = Equipment::new("Counter Ring".to_string(), EquipmentType::Ring, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        });
+        ring.add_reaction(Box::new(Counter::new(DamageType::PHY(""), 0.5, 1f64)));
+        lindtbert.equip(ring).unwrap();
+        Combat::from_participants(vec![lindtbert, test_character("Baddie", "Baddies!", 1),
+                                       test_character("Baddie", "Baddies!", 1)])
+    }
+
+    #[test]
+    fn test_simulation() {
+        let mut simulation = Simulation::new(encounter, 20);
+        simulation.set_threads(3);
+        let report = simulation.run();
+
+        // The much stronger Lindtbert wins every run, countering along the way
+        assert_eq!(report.iter_runs().count(), 20);
+        assert_eq!(report.win_rate("Best Friends"), 1f64);
+        assert_eq!(report.hp_remaining("Baddies!").quantile(1f64), 0f64);
+        assert!(report.average_turns() > 0f64);
+        let (name, impact) = &report.reaction_impact()[0];
+        assert_eq!(name, "Counter");
+        assert!(impact.damage_dealt > 0 && impact.damage_prevented > 0);
+
+        // Reports don't depend on the number of threads
+        simulation.set_threads(1);
+        assert_eq!(simulation.run().to_json(), report.to_json());
+
+        // Rulesets can be compared without touching the game's ruleset: Tougher characters make
+        // for longer fights
+        let mut tough = Ruleset::default();
+        tough.max_hp.base = 400;
+        simulation.set_ruleset(Some(Arc::new(tough)));
+        assert!(simulation.run().average_turns() > report.average_turns());
+
+        assert_eq!(report.to_csv().lines().count(), 1 + 20 * 2);
+        assert!(report.to_json().starts_with("{\"runs\":20,"));
+    }
+
+    /// Lindtbert with a summoned helper against two parties that escape on their own
+    fn escape_encounter() -> Combat {
+        use crate::battlefield::Row;
+        use crate::escape::EscapePolicy;
+        use crate::summons::{Summon, SummonKind, Summoning};
+
+        let mut lindtbert = test_character("Lindtbert", "Best Friends", 2);
+        let mut totem = Equipment::new("Totem".to_string(), EquipmentType::Accessory, Stats {
+            dex: 0, str: 0, grt: 0, wil: 0, cha: 0, int: 0,
+        });
+        totem.add_move(Box::new(Summon::new("Call Imp".to_string(), SummonKind::Creature(
+            Summoning::new("Imp".to_string(), Stats { str: 3, dex: 3, grt: 3, wil: 3, int: 3, cha: 3 },
+                           Some(2), Row::Front)), 0)));
+        lindtbert.equip(totem).unwrap();
+
+        let mut combat = Combat::from_participants(vec![
+            lindtbert,
+            test_character("Baddie", "Baddies!", 1),
+            test_character("Baddie", "Baddies!", 1),
+            test_character("Goblin", "Goblins", 1),
+        ]);
+        // Both parties keep trying to flee, so the order of their flee contests matters
+        for party in ["Baddies!", "Goblins"] {
+            combat.set_escape_policy(party.to_string(), EscapePolicy {
+                flee_below: Some(1.1),
+                ..EscapePolicy::default()
+            });
+        }
+        combat
+    }
+
+    #[test]
+    fn test_reproducible_escapes_and_summons() {
+        let mut simulation = Simulation::new(escape_encounter, 30);
+        simulation.set_threads(1);
+        simulation.set_base_seed(7);
+
+        // Escapes and expiring summons are processed in a fixed order, so the same seeds always
+        // make for the same report
+        let report = simulation.run();
+        for _ in 0..5 {
+            let again = simulation.run();
+            assert_eq!(again.to_json(), report.to_json());
+            assert_eq!(again.to_csv(), report.to_csv());
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Contains spells, i.e. maneuvers that are paid for with MP.
+//!
+//! Spells deal magical (`MAG`) or mental (`ZAP`) damage, heal allies or buff their caster. Their
+//! power scales with the caster's mental stats (INT, WIL and CHA). Some spells manipulate the
+//! timed effects on characters instead, e.g. dispelling an opponent's buff.
+//!
+//! A spell's MP cost is **paid once its action resolves**, not when it's chosen. A spell that is
+//! canceled on the stack therefore never costs its caster any MP.
+
+use crate::characters::{CharUnit, Character};
+use crate::combat::{Action, ActionCost, ActionEffect, Damage, DamageType, EntityPointer};
+use crate::effects::{Effect, EffectIdentity, EffectSelector};
+use crate::equipment::StatScaling;
+use crate::battlefield::Range;
+use crate::mov::{select_opponents, AreaOfEffect, Maneuver, Move};
+use crate::world::WorldContext;
+
+/// Describes what casting a spell does
+pub enum SpellEffect {
+    /// Damages a single opponent with the given (`MAG` or `ZAP`) damage type
+    Damage(DamageType),
+    /// Restores HP of the most wounded ally (incl. the caster)
+    Heal,
+    /// Gives the caster a timed effect for a number of turns
+    Buff(Box<dyn Effect>, i64),
+    /// Removes the strongest (then longest lasting) positive timed effect among all opponents
+    Dispel,
+    /// Removes all negative timed effects from the most afflicted ally (incl. the caster)
+    Purge,
+    /// Takes the best positive timed effect among all opponents for the caster
+    Steal,
+    /// Extends the caster's positive timed effects by a number of turns. Negative values shorten
+    /// the best positive timed effect among all opponents instead.
+    Prolong(i64),
+}
+
+/// A maneuver that costs MP
+pub struct Spell {
+    /// Name of the spell
+    name: String,
+    /// MP paid by the caster once the spell resolves
+    mp_cost: i64,
+    /// Damage or healing before any stat scaling is applied. Buffs ignore this value.
+    power: i64,
+    /// What happens when casting the spell
+    effect: SpellEffect,
+    /// Scales the spell with the caster's current stats. For buffs, the scaled value extends the
+    /// buff's duration in turns.
+    scaling: StatScaling,
+    /// Number of turns the spell can't be cast again after casting it
+    cooldown: i64,
+    /// Number of turns the caster needs to wind up the spell before it's cast
+    charge_turns: i64,
+    /// If set, damage spells hit this area instead of a single opponent
+    area: Option<AreaOfEffect>,
+}
+
+impl Spell {
+
+    // ~~~~~~~~~~~~~~ Constructors ~~~~~~~~~~~~~~
+
+    pub fn new(name: String, mp_cost: i64, power: i64, effect: SpellEffect, scaling: StatScaling) -> Spell {
+        Spell {
+            name,
+            mp_cost,
+            power,
+            effect,
+            scaling,
+            cooldown: 0,
+            charge_turns: 0,
+            area: None,
+        }
+    }
+
+    /// A bolt damages the most weakened opponent, mainly scaling with INT
+    pub fn bolt(name: String, mp_cost: i64, power: i64, damage_type: DamageType) -> Spell {
+        Spell::new(name, mp_cost, power, SpellEffect::Damage(damage_type),
+                   StatScaling { int: 2.0, wil: 1.0, ..StatScaling::default() })
+    }
+
+    /// A healing spell restores HP of the most wounded ally, mainly scaling with WIL
+    pub fn heal(name: String, mp_cost: i64, power: i64) -> Spell {
+        Spell::new(name, mp_cost, power, SpellEffect::Heal,
+                   StatScaling { wil: 2.0, cha: 1.0, ..StatScaling::default() })
+    }
+
+    /// A buff gives its caster an `effect` for a number of `turns`, lasting longer for
+    /// charismatic casters
+    pub fn buff(name: String, mp_cost: i64, effect: Box<dyn Effect>, turns: i64) -> Spell {
+        Spell::new(name, mp_cost, 0, SpellEffect::Buff(effect, turns),
+                   StatScaling { cha: 0.2, ..StatScaling::default() })
+    }
+
+    /// Spells manipulating effects scale with nothing
+    pub fn manipulation(name: String, mp_cost: i64, effect: SpellEffect) -> Spell {
+        Spell::new(name, mp_cost, 0, effect, StatScaling::default())
+    }
+
+    // ~~~~~~~~~~~~~~ Setters ~~~~~~~~~~~~~~
+
+    pub fn set_cooldown(&mut self, cooldown: i64) {
+        self.cooldown = cooldown;
+    }
+
+    pub fn set_charge_turns(&mut self, charge_turns: i64) {
+        self.charge_turns = charge_turns;
+    }
+
+    pub fn set_area(&mut self, area: AreaOfEffect) {
+        self.area = Some(area);
+    }
+
+    // ~~~~~~~~~~~~~~ Getters ~~~~~~~~~~~~~~
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_effect(&self) -> &SpellEffect {
+        &self.effect
+    }
+
+    /// Calculates the damage or healing this spell does when cast by `character`, based on
+    /// their current stats. Ensures a minimum of 1.
+    fn power_for(&self, character: &Character) -> i64 {
+        (self.power + self.scaling.scale(&character.calculate_current_stats())).max(1)
+    }
+
+    /// Finds the most wounded ally of `character` (incl. themselves) still standing
+    fn most_wounded_ally<'a>(character: &Character, context: &'a dyn WorldContext) -> Option<&'a Character> {
+        context.find_characters(&|c: &Character| c.party_check(character.party()) && !c.is_defeated())
+            .into_iter()
+            .min_by_key(|c| (c.hp_to_max_hp_ratio() * 1000f64) as i64)
+    }
+
+    /// Identifies all timed effects on `character` that are positive (or negative, if `negative`
+    /// is set)
+    fn effect_identities(character: &Character, negative: bool) -> Vec<EffectIdentity> {
+        let mut identities: Vec<EffectIdentity> = vec![];
+        for timed in character.iter_timed_effects().filter(|t| t.effect.is_negative() == negative) {
+            if !identities.contains(&timed.identity()) {
+                identities.push(timed.identity());
+            }
+        }
+        identities
+    }
+
+    /// Ranks the timed effects identified by `identity` on `character`: Stronger effects rank
+    /// higher, then those with more turns left over all their stacks
+    fn effect_rank(character: &Character, identity: &EffectIdentity) -> (i64, i64) {
+        character.iter_timed_effects().filter(|t| &t.identity() == identity)
+            .fold((0, 0), |(strength, turns), t| (strength.max(t.effect.strength()), turns + t.remaining))
+    }
+
+    /// Builds the targets of effect manipulating spells cast by `character`, i.e. the holder and
+    /// the selector of the targeted effect(s)
+    fn effect_targets(&self, character: &Character, context: &dyn WorldContext) -> Vec<EntityPointer> {
+        // The best-ranked buff among all opponents standing
+        let opponent_buffs = || context.find_characters(
+            &|c: &Character| !c.party_check(character.party()) && !c.is_defeated())
+            .into_iter()
+            .flat_map(|c| Spell::effect_identities(c, false).into_iter()
+                .map(move |identity| (Spell::effect_rank(c, &identity), c, identity)))
+            .max_by_key(|(rank, _, _)| *rank)
+            .map(|(_, c, identity)| EntityPointer::Effect(Box::new(c.as_target()), EffectSelector::Only(identity)));
+
+        match self.effect {
+            SpellEffect::Dispel | SpellEffect::Steal | SpellEffect::Prolong(_) if self.shortens() =>
+                opponent_buffs().into_iter().collect(),
+            SpellEffect::Purge => context.find_characters(
+                &|c: &Character| c.party_check(character.party()) && !c.is_defeated())
+                .into_iter()
+                .max_by_key(|c| Spell::effect_identities(c, true).len())
+                .filter(|c| !Spell::effect_identities(c, true).is_empty())
+                .map(|c| EntityPointer::Effect(Box::new(c.as_target()), EffectSelector::All))
+                .into_iter().collect(),
+            // Own buffs are extended one by one, leaving ailments as they are
+            SpellEffect::Prolong(_) => Spell::effect_identities(character, false).into_iter()
+                .map(|identity| EntityPointer::Effect(Box::new(character.as_target()), EffectSelector::Only(identity)))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Checks whether this spell shortens or removes opponents' effects
+    fn shortens(&self) -> bool {
+        match self.effect {
+            SpellEffect::Dispel | SpellEffect::Steal => true,
+            SpellEffect::Prolong(turns) => turns < 0,
+            _ => false,
+        }
+    }
+}
+
+impl Move for Spell {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn describe(&self) -> String {
+        let what = match &self.effect {
+            SpellEffect::Damage(dt) => format!("Deals {} {} damage to an opponent", self.power, dt),
+            SpellEffect::Heal => format!("Restores {} HP of an ally", self.power),
+            SpellEffect::Buff(effect, turns) => format!("Gives {} for {} turns", effect.describe(), turns),
+            SpellEffect::Dispel => "Removes a buff from an opponent".to_string(),
+            SpellEffect::Purge => "Removes all ailments from an ally".to_string(),
+            SpellEffect::Steal => "Steals a buff from an opponent".to_string(),
+            SpellEffect::Prolong(turns) if *turns > 0 => format!("Extends your buffs by {} turns", turns),
+            SpellEffect::Prolong(turns) => format!("Shortens an opponent's buff by {} turns", -turns),
+        };
+        format!("{} (before scaling). Costs {} MP.", what, self.mp_cost)
+    }
+
+    fn mp_cost(&self) -> i64 {
+        self.mp_cost
+    }
+}
+
+impl Maneuver for Spell {
+    fn execute(&self, character: &Character, context: &dyn WorldContext) -> Vec<Action> {
+        let (effect, target) = match &self.effect {
+            // Area spells are paid for once, by all their actions together
+            SpellEffect::Damage(dt) if self.area.is_some() => {
+                let damage = Damage(*dt, self.power_for(character));
+                let mut actions = self.area.unwrap().build_attack(character, context, damage, Range::Ranged);
+                Action::share_cost(&mut actions, ActionCost { ap: 0, mp: self.mp_cost });
+                return actions;
+            }
+            SpellEffect::Damage(dt) => {
+                match select_opponents(character, context, 1, Range::Ranged).first() {
+                    Some(t) => (ActionEffect::Attack(Damage(*dt, self.power_for(character))), t.as_target()),
+                    // Nobody left to attack
+                    None => return vec![],
+                }
+            }
+            SpellEffect::Heal => match Spell::most_wounded_ally(character, context) {
+                Some(t) => (ActionEffect::Heal(CharUnit::HP(self.power_for(character))), t.as_target()),
+                None => return vec![],
+            },
+            SpellEffect::Buff(effect, turns) => {
+                let turns = turns + self.scaling.scale(&character.calculate_current_stats());
+                (ActionEffect::GiveTimedEffect(effect.clone_box(), turns), character.as_target())
+            }
+            // Effect manipulations may target several effects, and are paid for once
+            _ => {
+                let effect = || match self.effect {
+                    SpellEffect::Dispel => ActionEffect::Dispel,
+                    SpellEffect::Purge => ActionEffect::Purge,
+                    SpellEffect::Steal => ActionEffect::Steal,
+                    SpellEffect::Prolong(turns) => ActionEffect::ExtendEffect(turns),
+                    _ => ActionEffect::Canceled,
+                };
+                let mut actions: Vec<Action> = self.effect_targets(character, context).into_iter()
+                    .map(|target| Action::from_source(character.as_target(), effect(), target))
+                    .collect();
+                Action::share_cost(&mut actions, ActionCost { ap: 0, mp: self.mp_cost });
+                return actions;
+            }
+        };
+
+        // The MP cost is attached to the action, and only paid once it resolves
+        let mut action = Action::from_source(character.as_target(), effect, target);
+        action.set_mp_cost(self.mp_cost);
+        vec![action]
+    }
+
+    /// Spells can only be chosen if the caster can afford them. Healing spells are only cast
+    /// once an ally dropped below half HP.
+    fn is_available(&self, character: &Character, context: &dyn WorldContext) -> bool {
+        if character.mp() < self.mp_cost {
+            return false;
+        }
+
+        match self.effect {
+            SpellEffect::Heal => Spell::most_wounded_ally(character, context)
+                .map_or(false, |c| c.hp_to_max_hp_ratio() < 0.5),
+            // Effect manipulations need some effect to manipulate
+            SpellEffect::Dispel | SpellEffect::Purge | SpellEffect::Steal | SpellEffect::Prolong(_) =>
+                !self.effect_targets(character, context).is_empty(),
+            _ => true,
+        }
+    }
+
+    fn cooldown(&self) -> i64 {
+        self.cooldown
+    }
+
+    fn charge_turns(&self) -> i64 {
+        self.charge_turns
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::Stats;
+    use crate::combat::{Actor, Combat};
+    use super::*;
+
+    fn test_character(name: &str, party: &str) -> Character {
+        let mut character = Character::new(name.to_string(), None, Stats {
+            str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6,
+        });
+        character.set_party(party.to_string());
+        character
+    }
+
+    #[test]
+    fn test_mp_check() {
+        let mut mage = test_character("Mage", "Best Friends");
+        let mp = mage.mp();
+        mage.learn_spell(Spell::bolt("Firebolt".to_string(), mp + 1, 20, DamageType::MAG("Fire")));
+        mage.learn_spell(Spell::bolt("Spark".to_string(), mp, 5, DamageType::ZAP("Shock")));
+
+        let combat = Combat::from_participants(vec![mage, test_character("Baddie", "Baddies!")]);
+        let mage = combat.get_character("Mage").unwrap();
+
+        // Firebolt is too expensive, so the mage falls back to Spark
+        assert_eq!(mage.next_move(&combat).name(), "Spark");
+    }
+
+    #[test]
+    fn test_mp_paid_on_resolution() {
+        let mut mage = test_character("Mage", "Best Friends");
+        mage.learn_spell(Spell::bolt("Firebolt".to_string(), 10, 20, DamageType::MAG("Fire")));
+        let baddie = test_character("Baddie", "Baddies!");
+        let mut combat = Combat::from_participants(vec![mage, baddie]);
+
+        let mp_pre = combat.get_character("Mage").unwrap().mp();
+        let hp_pre = combat.get_character("Baddie").unwrap().hp();
+        combat.process_turn(None).unwrap();
+
+        let mage = combat.get_character("Mage").unwrap();
+        // The mage started out with full MP, so regeneration had nothing to restore
+        assert_eq!(mage.mp(), mp_pre - 10);
+        assert!(combat.get_character("Baddie").unwrap().hp() < hp_pre);
+    }
+
+    #[test]
+    fn test_steal_buff() {
+        use crate::characters::CharStat;
+        use crate::effects::StatAdditive;
+
+        let mut thief = test_character("Thief", "Best Friends");
+        thief.learn_spell(Spell::manipulation("Pilfer".to_string(), 5, SpellEffect::Steal));
+        let mut baddie = test_character("Baddie", "Baddies!");
+        baddie.apply_timed_effect(Box::new(StatAdditive(CharStat::STR(5))), 3, None);
+        let mut combat = Combat::from_participants(vec![thief, baddie]);
+
+        assert_eq!(combat.get_character("Thief").unwrap().next_move(&combat).name(), "Pilfer");
+        combat.process_turn(None).unwrap();
+
+        // The buff changed hands, and aged by the turn that passed
+        assert_eq!(combat.get_character("Baddie").unwrap().iter_timed_effects().count(), 0);
+        let timed = combat.get_character("Thief").unwrap().iter_timed_effects().next().unwrap();
+        assert_eq!(timed.effect.describe(), "+5 STR");
+        assert_eq!(timed.remaining, 2);
+    }
+
+    #[test]
+    fn test_dispel_target() {
+        use crate::characters::CharStat;
+        use crate::effects::StatAdditive;
+
+        let mut baddie = test_character("Baddie", "Baddies!");
+        baddie.apply_timed_effect(Box::new(StatAdditive(CharStat::STR(1))), 1, None);
+        let mut other = test_character("Other Baddie", "Baddies!");
+        other.apply_timed_effect(Box::new(StatAdditive(CharStat::DEX(1))), 1, None);
+        other.apply_timed_effect(Box::new(StatAdditive(CharStat::STR(5))), 5, None);
+        let combat = Combat::from_participants(vec![test_character("Mage", "Best Friends"), baddie, other]);
+        let mage = combat.get_character("Mage").unwrap();
+        let other = combat.get_character("Other Baddie").unwrap().id();
+
+        // The longest lasting buff is dispelled, even if it's not the first one found
+        let dispel = Spell::manipulation("Dispel".to_string(), 5, SpellEffect::Dispel);
+        match &dispel.effect_targets(mage, &combat)[..] {
+            [EntityPointer::Effect(holder, EffectSelector::Only(identity))] => {
+                assert!(matches!(&**holder, EntityPointer::Character(c) if c[0].id() == other));
+                assert_eq!(identity.id, "+5 STR");
+            }
+            _ => panic!("Dispel should target a single buff"),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Contains the ways new participants join a combat that's already going on.
+//!
+//! * **Summoned creatures** are called in by a `Summon` maneuver and leave combat again once
+//! their lifetime (in turns) runs out.
+//! * **Clones** are summoned copies of their summoner, using the summoner's current stats.
+//! * **Reinforcements** are scheduled on the combat and arrive at the start of a given turn.
+//!
+//! ## Ordering Newcomers
+//!
+//! The turn order of every round is built at the round's start. Summons join while the round is
+//! already running and **act from the next round on**. Reinforcements arrive before the turn
+//! order is built and act in the very round they arrive. Among participants with equal MOB, those
+//! who joined the combat earlier go first.
+//!
+//! Participants are addressed by their `CharacterId`, so names don't need to be unique. Still,
+//! newcomers whose name is already taken are numbered (e.g. "Imp 2") to tell them apart in
+//! narration.
+
+use crate::battlefield::Row;
+use crate::characters::{Character, Stats};
+use crate::combat::{Action, ActionEffect, EntityPointer};
+use crate::mov::{Maneuver, Move};
+use crate::world::WorldContext;
+
+/// Describes a character to be summoned into combat
+#[derive(Clone)]
+pub struct Summoning {
+    /// Name of the summoned character
+    name: String,
+    /// Base stats of the summoned character
+    stats: Stats,
+    /// Number of turns the summoned character stays in combat. `None` stays until defeated.
+    lifetime: Option<i64>,
+    /// Row of the formation the summoned character appears in
+    row: Row,
+}
+
+impl Summoning {
+    pub fn new(name: String, stats: Stats, lifetime: Option<i64>, row: Row) -> Summoning {
+        Summoning {
+            name,
+            stats,
+            lifetime,
+            row,
+        }
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_lifetime(&self) -> Option<i64> {
+        self.lifetime
+    }
+
+    /// Builds the summoned character on the side of its `summoner`
+    pub fn build(&self, summoner: &Character) -> Character {
+        let mut character = Character::new(self.name.clone(), None, self.stats);
+        character.set_party(summoner.party().clone());
+        character.set_row(self.row);
+        character.set_summoned_by(summoner.id(), self.name.clone());
+        character
+    }
+}
+
+/// Describes whom a `Summon` maneuver calls into combat
+pub enum SummonKind {
+    /// A creature described by the given summoning
+    Creature(Summoning),
+    /// A copy of the summoner, staying for the given number of turns
+    Clone(i64),
+}
+
+/// A maneuver calling a new participant into combat on the summoner's side
+pub struct Summon {
+    /// Name of the maneuver
+    name: String,
+    /// Whom this maneuver summons
+    kind: SummonKind,
+    /// MP paid by the summoner once the summoning resolves
+    mp_cost: i64,
+}
+
+impl Summon {
+    pub fn new(name: String, kind: SummonKind, mp_cost: i64) -> Summon {
+        Summon {
+            name,
+            kind,
+            mp_cost,
+        }
+    }
+
+    /// Builds the summoning for `character` as the summoner
+    fn summoning_for(&self, character: &Character) -> Summoning {
+        match &self.kind {
+            SummonKind::Creature(summoning) => summoning.clone(),
+            SummonKind::Clone(turns) => Summoning::new(format!("{}'s Clone", character.name()),
+                                                       character.calculate_current_stats(),
+                                                       Some(*turns), character.get_row()),
+        }
+    }
+}
+
+impl Move for Summon {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn describe(&self) -> String {
+        match &self.kind {
+            SummonKind::Creature(s) => match s.lifetime {
+                Some(turns) => format!("Summons {} for {} turns. Costs {} MP.", s.name, turns, self.mp_cost),
+                None => format!("Summons {}. Costs {} MP.", s.name, self.mp_cost),
+            },
+            SummonKind::Clone(turns) => format!("Summons a copy of yourself for {} turns. Costs {} MP.",
+                                                turns, self.mp_cost),
+        }
+    }
+
+    fn mp_cost(&self) -> i64 {
+        self.mp_cost
+    }
+}
+
+impl Maneuver for Summon {
+    fn execute(&self, character: &Character, _: &dyn WorldContext) -> Vec<Action> {
+        let mut action = Action::from_source(character.as_target(),
+                                             ActionEffect::Summon(self.summoning_for(character)),
+                                             EntityPointer::Environment);
+        action.set_mp_cost(self.mp_cost);
+        vec![action]
+    }
+
+    /// Summons are only called once no earlier summon of the same kind by the same summoner is
+    /// still standing
+    fn is_available(&self, character: &Character, context: &dyn WorldContext) -> bool {
+        let name = self.summoning_for(character).name;
+        character.mp() >= self.mp_cost && context.find_characters(&|c: &Character|
+            !c.is_defeated() && c.get_summoned_by() == Some((character.id(), &name))).is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::combat::Combat;
+    use crate::equipment::{Equipment, EquipmentType};
+    use super::*;
+
+    fn test_stats() -> Stats {
+        Stats { str: 3, dex: 8, grt: 6, wil: 2, int: 5, cha: 6 }
+    }
+
+    fn test_character(name: &str, party: &str) -> Character {
+        let mut character = Character::new(name.to_string(), None, test_stats());
+        character.set_party(party.to_string());
+        character
+    }
+
+    #[test]
+    fn test_summon_lifetime() {
+        let mut necro = test_character("Necro", "Best Friends");
+        let mut totem = Equipment::new("Totem".to_string(), EquipmentType::Accessory, test_stats());
+        totem.add_move(Box::new(Summon::new("Call Imp".to_string(), SummonKind::Creature(
+            Summoning::new("Imp".to_string(), test_stats(), Some(2), Row::Front)), 0)));
+        necro.equip(totem).unwrap();
+        let mut combat = Combat::from_participants(vec![necro, test_character("Baddie", "Baddies!")]);
+
+        // The imp joins during the first round, but only acts from the second one on
+        combat.process_turn(None).unwrap();
+        assert_eq!(combat.get_character("Imp").unwrap().party(), "Best Friends");
+        assert_eq!(combat.get_character("Imp").unwrap().hp(),
+                   combat.get_character("Imp").unwrap().calculate_game_stats().mhp());
+
+        // ... and leaves after its two turns are up
+        combat.process_turn(None).unwrap();
+        assert!(combat.get_character("Imp").is_some());
+        combat.process_turn(None).unwrap();
+        assert!(combat.get_character("Imp").is_none());
+    }
+
+    #[test]
+    fn test_summon_availability() {
+        let call_imp = Summon::new("Call Imp".to_string(), SummonKind::Creature(
+            Summoning::new("Imp".to_string(), test_stats(), None, Row::Front)), 0);
+        let mut combat = Combat::from_participants(vec![
+            test_character("Necro", "Best Friends"),
+            test_character("Imperial Guard", "Best Friends"),
+        ]);
+
+        // Allies whose names merely look alike don't count as summons
+        let necro = combat.get_character("Necro").unwrap();
+        assert!(call_imp.is_available(necro, &combat));
+
+        // Only one imp at a time, even once it's been renamed
+        let mut imp = call_imp.summoning_for(necro).build(necro);
+        imp.set_name("Bob".to_string());
+        combat.add_participant(imp, None).unwrap();
+        let necro = combat.get_character("Necro").unwrap();
+        assert!(!call_imp.is_available(necro, &combat));
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let mut combat = Combat::from_participants(vec![test_character("Imp", "Baddies!")]);
+        let summoning = Summoning::new("Imp".to_string(), test_stats(), None, Row::Back);
+
+        let summoner = combat.get_character("Imp").unwrap();
+        let id = combat.add_participant(summoning.build(summoner), None).unwrap();
+        assert_eq!(combat.get_character_by_id(id).unwrap().name(), "Imp 2");
+        assert_eq!(combat.get_character("Imp 2").unwrap().get_row(), Row::Back);
+    }
+
+    #[test]
+    fn test_reinforcements() {
+        let mut combat = Combat::from_participants(vec![
+            test_character("Lindtbert", "Best Friends"),
+            test_character("Baddie", "Baddies!"),
+        ]);
+        combat.schedule_reinforcement(2, test_character("Bigger Baddie", "Baddies!"));
+
+        combat.process_turn(None).unwrap();
+        assert!(combat.get_character("Bigger Baddie").is_none());
+
+        // Reinforcements arrive in time to act in their turn
+        combat.process_turn(None).unwrap();
+        let id = |name: &str| combat.get_character(name).unwrap().id();
+        assert!(combat.get_threat(id("Bigger Baddie"), id("Lindtbert")) > 0);
+    }
+}
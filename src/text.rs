@@ -385,7 +385,24 @@ impl InfoGrid for Vec<(String, usize)> {
 /// Implements a **line wrap** over a set
 
 pub mod text_util {
-    use crate::text::{text_util, BarStyle, TextFormatting, InfoLine};
+    use crate::text::{text_util, BarStyle, FrameType, JointType, TextFormatting, InfoLine};
+
+    /// Renders a plain table, with one column per entry of `columns` (header and width). Cells
+    /// are padded to their column's width and separated by `frame`'s vertical lines.
+    pub fn render_table(columns: &[(&str, usize)], rows: &[Vec<String>], frame: &FrameType) -> Vec<String> {
+        let separator = frame.ver().to_string();
+        let render_row = |cells: Vec<String>| columns.iter().zip(cells)
+            .map(|((_, width), cell)| cell.format_line(*width, TextFormatting::Plain))
+            .collect::<Vec<String>>().join(&separator);
+
+        let mut lines = vec![render_row(columns.iter().map(|(header, _)| header.to_string()).collect())];
+        lines.push(columns.iter().map(|(_, width)| frame.hor().to_string().repeat(*width))
+            .collect::<Vec<String>>().join(&frame.joint(JointType::Cross).to_string()));
+        for row in rows {
+            lines.push(render_row(row.clone()));
+        }
+        lines
+    }
 
     /// Renders a nice labeled bar.
     ///
@@ -0,0 +1,68 @@
+//! Contains the threat (aggro) table of a combat.
+//!
+//! Every character in combat keeps track of **how threatening each opponent is to them**. Threat
+//! is generated by damaging a character, by healing allies (which threatens all opponents) and
+//! by taunting. During target selection, characters go for their most threatening opponents
+//! first.
+
+use std::collections::HashMap;
+use crate::characters::CharacterId;
+
+/// Threat generated by a successful taunt
+pub const TAUNT_THREAT: i64 = 100;
+
+/// Share of healing that turns into threat towards the healer's opponents
+pub const HEAL_THREAT_FACTOR: f64 = 0.5;
+
+/// Tracks the threat between attacker / defender pairs of characters, identified by ID.
+pub struct ThreatTable {
+    /// Threat by `(source, target)`, i.e. how much threat `source` generated with `target`
+    threat: HashMap<(CharacterId, CharacterId), i64>,
+}
+
+impl ThreatTable {
+    pub fn new() -> ThreatTable {
+        ThreatTable {
+            threat: HashMap::new(),
+        }
+    }
+
+    /// Adds `amount` threat the character `source` generated with the character `target`
+    pub fn add_threat(&mut self, source: CharacterId, target: CharacterId, amount: i64) {
+        *self.threat.entry((source, target)).or_insert(0) += amount;
+    }
+
+    /// Returns how much threat the character `source` generated with the character `target`
+    pub fn get_threat(&self, source: CharacterId, target: CharacterId) -> i64 {
+        self.threat.get(&(source, target)).copied().unwrap_or(0)
+    }
+
+    /// Returns the ID of the most threatening character for `target`, if anyone generated
+    /// threat with them so far
+    pub fn most_threatening(&self, target: CharacterId) -> Option<CharacterId> {
+        self.threat.iter()
+            .filter(|((_, t), amount)| *t == target && **amount > 0)
+            .max_by_key(|(_, amount)| **amount)
+            .map(|((s, _), _)| *s)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threat_table() {
+        let (lindtbert, healer, baddie) = (CharacterId::generate(), CharacterId::generate(), CharacterId::generate());
+        let mut table = ThreatTable::new();
+        table.add_threat(lindtbert, baddie, 20);
+        table.add_threat(healer, baddie, 15);
+        table.add_threat(healer, baddie, 10);
+
+        assert_eq!(table.get_threat(healer, baddie), 25);
+        assert_eq!(table.get_threat(baddie, healer), 0);
+        assert_eq!(table.most_threatening(baddie).unwrap(), healer);
+        assert!(table.most_threatening(lindtbert).is_none());
+    }
+}
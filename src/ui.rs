@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use crate::battlefield::Row;
 use crate::characters::{Character, Stats};
 use crate::combat::{ActionStack, Combat, DamageType};
 use crate::layouts::{LayoutDirection, LayoutWeight, LinearLayout};
@@ -68,6 +69,9 @@ impl TurnLogger for CombatTurnDisplay {
 /// Baseline visualization function of a combat turn. Based on provided `w` and `h` (and
 /// `formatting`), displays this turn
 /// the
+///
+/// Parties are rendered in formation, with one column per row. Front rows face each other (and
+/// are framed with double lines), i.e. the first party is rendered back to front.
 impl TextUI for CombatTurnDisplay {
     fn render(&self, context: &dyn WorldContext, w: usize, h: usize, formatting: TextFormatting) -> Vec<String> {
         let mut main_layout = LinearLayout::configure(LayoutDirection::Horizontal, None );
@@ -81,12 +85,35 @@ impl TextUI for CombatTurnDisplay {
             acc
         });
         let mut combatant_layout = LinearLayout::configure(LayoutDirection::Horizontal, None );
-        let mut party_layouts: Vec<LinearLayout> = all_parties.iter().map(|_| LinearLayout::configure(LayoutDirection::Vertical, Some(FrameType::Single) )).collect();
 
-        for (i, party_name) in all_parties.iter().enumerate() {
-            context.iter_characters().filter(|character| character.party() == *party_name).for_each(|character| {
-                party_layouts[i].add(character as &dyn InfoGrid, LayoutWeight::Distribute(1));
-            });
+        // Build one layout per occupied row of each party's formation
+        let row_layouts: Vec<Vec<LinearLayout>> = all_parties.iter().enumerate().map(|(i, party_name)| {
+            let rows = if i % 2 == 0 { [Row::Back, Row::Front] } else { [Row::Front, Row::Back] };
+            rows.iter().filter_map(|row| {
+                let members: Vec<&Character> = context.iter_characters()
+                    .filter(|character| character.party() == *party_name && character.get_row() == *row)
+                    .collect();
+                if members.is_empty() {
+                    return None;
+                }
+
+                let frame = match row {
+                    Row::Front => FrameType::Double,
+                    Row::Back => FrameType::Single,
+                };
+                let mut row_layout = LinearLayout::configure(LayoutDirection::Vertical, Some(frame));
+                for character in members {
+                    row_layout.add(character as &dyn InfoGrid, LayoutWeight::Distribute(1));
+                }
+                Some(row_layout)
+            }).collect()
+        }).collect();
+
+        let mut party_layouts: Vec<LinearLayout> = all_parties.iter().map(|_| LinearLayout::configure(LayoutDirection::Horizontal, None )).collect();
+        for (i, rows) in row_layouts.iter().enumerate() {
+            for row_layout in rows {
+                party_layouts[i].add(row_layout, LayoutWeight::Distribute(1));
+            }
         }
 
         for layout in party_layouts.iter() {
@@ -94,8 +121,15 @@ impl TextUI for CombatTurnDisplay {
         }
 
 
+        // Active environments are shown on top of the turn description
+        let mut description_layout = LinearLayout::configure(LayoutDirection::Vertical, None);
+        if let Some(environment) = context.get_environment().filter(|e| !e.is_calm()) {
+            description_layout.add(environment as &dyn InfoGrid, LayoutWeight::Distribute(1));
+        }
+        description_layout.add(&self.turn_description, LayoutWeight::Distribute(2));
+
         main_layout.add(&combatant_layout, LayoutWeight::Distribute(2));
-        main_layout.add(&self.turn_description, LayoutWeight::Distribute(1));
+        main_layout.add(&description_layout, LayoutWeight::Distribute(1));
 
         // Forward render request to now configured layout
         main_layout.display(w, h, formatting)
@@ -109,6 +143,7 @@ mod tests {
     use crate::combat::{ Actor};
     use crate::equipment::{Equipment, EquipmentType};
     use crate::mov::Counter;
+    use crate::spells::Spell;
     use crate::text::{InfoGrid, TextFormatting};
     use crate::world::WorldContext;
     use super::*;
@@ -141,6 +176,50 @@ mod tests {
         combat
     }
 
+    #[test]
+    fn test_formation_view() {
+        let mut combat = build_combat();
+        let mut mage = test_character("Mage".to_string());
+        mage.set_party("Best Friends".to_string());
+        mage.set_row(Row::Back);
+        mage.learn_spell(Spell::bolt("Spark".to_string(), 1, 5, DamageType::ZAP("Shock")));
+        let mut participants: Vec<Character> = combat.iter_characters_mut().map(|c| {
+            let mut moved = test_character(c.name().clone());
+            moved.set_party(c.party().clone());
+            moved
+        }).collect();
+        participants.insert(0, mage);
+        combat = Combat::from_participants(participants);
+
+        let mut ui = CombatTurnDisplay::with(TextFormatting::Plain);
+        combat.process_turn(Some(&mut ui)).unwrap();
+        let lines = ui.render(&combat, 90, 6, TextFormatting::Plain);
+        for line in lines.iter() {
+            println!("{}", line);
+        }
+
+        // The back row is rendered left of the front row, which is framed with double lines
+        let first = &lines[0];
+        assert!(first.find('┌').unwrap() < first.find('━').unwrap());
+    }
+
+    #[test]
+    fn test_environment_view() {
+        use crate::environment::{Condition, Environment};
+
+        let mut combat = build_combat();
+        let mut environment = Environment::new("Volcano".to_string());
+        environment.add_condition(Condition::BurningGround(5));
+        combat.set_environment(environment);
+
+        let mut ui = CombatTurnDisplay::with(TextFormatting::Plain);
+        combat.process_turn(Some(&mut ui)).unwrap();
+        let lines = ui.render(&combat, 90, 8, TextFormatting::Plain);
+
+        assert!(lines.iter().any(|l| l.contains("Volcano:")));
+        assert!(lines.iter().any(|l| l.contains("environment")));
+    }
+
     #[test]
     fn test_combat_view() {
         let mut combat = build_combat();
@@ -1,7 +1,11 @@
-use crate::characters::Character;
+use std::sync::Arc;
+use crate::characters::{Character, CharacterId};
 
-use crate::combat::{Action, ActionStack};
+use crate::combat::{Action, ActionStack, CostLedger};
+use crate::content::ContentLibrary;
+use crate::environment::Environment;
 use crate::player::PlayerInput;
+use crate::ruleset::{new_generation, with_generation, Ruleset};
 
 /// Top-Level Game Structure, containing an arbitrary number of game contexts that are run in
 /// a **turn-based simulation** based on all actors configuration, similarly to a
@@ -14,8 +18,87 @@ use crate::player::PlayerInput;
 /// A game world's overall state is managed within a **world directory**. That directory includes:
 /// * `contexts/`: A directory containing the active contexts
 /// * `players/`: A directory containing player data
-struct World {
+///
+/// # Content
+/// A world can be run with the content of a `ContentLibrary`. Its contexts then play by the
+/// content's rules, which only apply to the thread running this world (see `with_ruleset`), so
+/// other worlds and simulations aren't affected.
+#[derive(Default)]
+pub struct World {
+    /// All contexts running in this world
+    contexts: Vec<Box<dyn WorldContext>>,
+    /// Content this world is run with, if any
+    content: Option<ContentLibrary>,
+    /// The rules of the content, which all contexts of this world play by, with their generation.
+    /// The generation only changes once the content is reloaded, so the game stats characters
+    /// cached stay up to date from one turn to the next.
+    ruleset: Option<(u64, Arc<Ruleset>)>,
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            contexts: vec![],
+            content: None,
+            ruleset: None,
+        }
+    }
+
+    /// Adds `context` to this world. It takes part in every turn from now on.
+    pub fn add_context(&mut self, context: Box<dyn WorldContext>) {
+        self.contexts.push(context);
+    }
+
+    pub fn iter_contexts(&self) -> core::slice::Iter<Box<dyn WorldContext>> {
+        self.contexts.iter()
+    }
+
+    /// Loads the packs of `library` and runs this world with their content from now on. Returns
+    /// notes on what was loaded, or all problems found (leaving the world as it was).
+    pub fn set_content(&mut self, mut library: ContentLibrary) -> Result<Vec<String>, String> {
+        let notes = library.load()?;
+        self.ruleset = Some((new_generation(), Arc::new(library.get_ruleset().clone())));
+        self.content = Some(library);
+        Ok(notes)
+    }
+
+    pub fn get_content(&self) -> Option<&ContentLibrary> {
+        self.content.as_ref()
+    }
+
+    /// Returns the rules this world is run with, unless it's run with the game's rules
+    pub fn get_ruleset(&self) -> Option<&Arc<Ruleset>> {
+        self.ruleset.as_ref().map(|(_, ruleset)| ruleset)
+    }
 
+    /// Reloads this world's content if any of its packs changed (see
+    /// `ContentLibrary::reload_if_changed`). Meant to be called between turns. The new rules apply
+    /// from the next turn on, and items built from the packs are refreshed in all contexts.
+    ///
+    /// Returns `None` if nothing changed, and otherwise notes on what was reloaded and refreshed,
+    /// or all problems found.
+    pub fn reload_content(&mut self) -> Option<Result<Vec<String>, String>> {
+        let library = self.content.as_mut()?;
+        let mut notes = match library.reload_if_changed()? {
+            Ok(notes) => notes,
+            Err(e) => return Some(Err(e)),
+        };
+        self.ruleset = Some((new_generation(), Arc::new(library.get_ruleset().clone())));
+        for context in self.contexts.iter_mut() {
+            notes.extend(library.refresh(context.as_mut()));
+        }
+        Some(Ok(notes))
+    }
+
+    /// Processes a turn in every context of this world, playing by the world's rules
+    pub fn process_turn(&mut self) -> Result<(), String> {
+        let contexts = &mut self.contexts;
+        let mut process = || contexts.iter_mut().try_for_each(|context| context.process_turn(None));
+        match &self.ruleset {
+            Some((generation, ruleset)) => with_generation(*generation, ruleset.clone(), process),
+            None => process(),
+        }
+    }
 }
 
 
@@ -48,7 +131,17 @@ pub trait WorldContext {
 
     // ~~~~~~~~~~~~~~~~~~~ CHARACTER ACCESS ~~~~~~~~~~~~~~~~~~~
 
-    /// Returns the Characater if its' part of this context.
+    /// Returns the character with the given `id` if it's part of this context. Characters are
+    /// looked up by ID all the time (e.g. for every action resolving), so contexts are expected
+    /// to find them without going through all characters.
+    fn get_character_by_id(&self, id: CharacterId) -> Option<&Character>;
+
+    /// Returns a mutable reference to the character with the given `id` if it's part of this
+    /// context.
+    fn get_character_by_id_mut(&mut self, id: CharacterId) -> Option<&mut Character>;
+
+    /// Returns the (first) Characater named `name` if its' part of this context. Names are meant
+    /// for display, so characters should be looked up by ID wherever one is at hand.
     fn get_character(&self, name: &str) -> Option<&Character> {
         let res = self.find_characters(&|c| c.name()==name);
         // If anything, the first match should be considered
@@ -75,7 +168,7 @@ pub trait WorldContext {
     }
 
 
-    /// Returns a mutable reference to the character of `name` if it exists in this context
+    /// Returns a mutable reference to the (first) character of `name` if it exists in this context
     fn get_character_mut(&mut self, name: &String) -> Option<&mut Character> {
         let mut res = self.find_characters_mut(&|c| c.name()==name);
         // If anything, the first match should be considered
@@ -94,8 +187,31 @@ pub trait WorldContext {
     /// Every `action` that happens could offer reactions from (other) sources in the world.
     ///
     /// The returned vector represents all *reactions that have been made*. to the original `action`
-    /// object.
-    fn request_reactions(&mut self, action: &Action) -> Vec<Action>;
+    /// object. Reactions only draw on what their characters can afford beyond the costs still
+    /// pending on the `ledger`.
+    fn request_reactions(&mut self, action: &Action, ledger: &CostLedger) -> Vec<Action>;
+
+    /// Called during action resolution, right after an `action` targeting characters resolved.
+    /// Default is to do nothing.
+    fn record_action(&mut self, _action: &Action) {}
+
+    /// Returns how much threat the character `source` generated with the character `target` so
+    /// far. Default is no threat at all.
+    fn get_threat(&self, _source: CharacterId, _target: CharacterId) -> i64 {
+        0
+    }
+
+    /// Lets `newcomer` join this context while it's running (e.g. summoned characters). A
+    /// newcomer with a `lifetime` leaves again after that many turns. Returns the ID of the
+    /// newcomer. Default is that nobody can join.
+    fn add_participant(&mut self, newcomer: Character, _lifetime: Option<i64>) -> Result<CharacterId, String> {
+        Err(format!("{} can't join here.", newcomer.name()))
+    }
+
+    /// Returns the environment of this context, if it has one. Default is no environment.
+    fn get_environment(&self) -> Option<&Environment> {
+        None
+    }
 }
 
 